@@ -0,0 +1,750 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::IsTerminal;
+use std::path::Path;
+
+use clap::ValueEnum;
+use futures::StreamExt;
+use serde::Deserialize;
+use thiserror::Error;
+use toml_edit::{Document, InlineTable, Item, Table, TableLike};
+
+use crate::config::mods::{ConfigMod, ConfigModContainer};
+use crate::config::pack::{PackConfig, VersionType};
+use crate::config::policy::LoadedPolicy;
+use crate::config::project_lists::{ProjectListViolation, SiteProjectLists};
+use crate::mod_site::{
+    DependencyId, ModDependencyKind, ModId, ModIdValue, ModInfo, ModLoadingError, ModSite,
+};
+use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, SITE_NAME_STYLE, SITE_VAL_STYLE};
+
+#[derive(Debug, Error)]
+pub enum AddModError {
+    #[error("Error loading mod: {0}")]
+    Loading(#[from] ModLoadingError),
+    #[error("No version found for Minecraft {minecraft_version} on {mod_loader}")]
+    NoVersion {
+        minecraft_version: String,
+        mod_loader: String,
+    },
+    #[error("{0}")]
+    ProjectList(#[from] ProjectListViolation),
+    #[error("Mods from this site are not allowed by the policy '{0}'")]
+    SiteNotAllowed(String),
+    #[error("The license {license} is not allowed by the policy '{policy}'")]
+    LicenseNotAllowed { license: String, policy: String },
+}
+
+#[derive(Debug)]
+pub struct ModsAddError {
+    /// The number of mods that were added despite the failures.
+    pub added: usize,
+    pub failures: HashMap<String, AddModError>,
+}
+
+impl Error for ModsAddError {}
+
+impl Display for ModsAddError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut failures_vec = self.failures.iter().collect::<Vec<_>>();
+        failures_vec.sort_by_key(|(k, _)| (*k).clone());
+        for (k, error) in failures_vec {
+            writeln!(f, "Project {}: {}", k, error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ModsAddError {
+    /// Combine the results of adding mods from several sites.
+    pub fn combine(
+        a: Result<usize, ModsAddError>,
+        b: Result<usize, ModsAddError>,
+    ) -> Result<usize, ModsAddError> {
+        match (a, b) {
+            (Ok(a), Ok(b)) => Ok(a + b),
+            (Ok(added), Err(mut e)) | (Err(mut e), Ok(added)) => {
+                e.added += added;
+                Err(e)
+            }
+            (Err(mut a), Err(b)) => {
+                a.added += b.added;
+                a.failures.extend(b.failures);
+                Err(a)
+            }
+        }
+    }
+}
+
+/// A project to add to the config.
+#[derive(Debug, Clone)]
+pub struct AddModRequest<K> {
+    pub project_id: K,
+    /// The version to add, or `None` to use the latest version for the pack.
+    pub version_id: Option<K>,
+    /// Mark the mod as optional on the client.
+    pub client_optional: bool,
+}
+
+impl<K> AddModRequest<K> {
+    pub fn latest(project_id: K) -> Self {
+        Self {
+            project_id,
+            version_id: None,
+            client_optional: false,
+        }
+    }
+}
+
+/// How the config key of an added mod is chosen.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStrategy {
+    /// Derived from the mod's name, e.g. `just-enough-items-jei`. Falls back to the slug if the
+    /// name has no ASCII letters or digits.
+    #[default]
+    Name,
+    /// The mod's slug on the site.
+    Slug,
+    /// The mod's project ID.
+    Id,
+}
+
+/// Options for how mods are added.
+#[derive(Debug, Clone)]
+pub struct AddModsOptions {
+    pub key_strategy: KeyStrategy,
+    /// Look up the latest version regardless of the mod loader it's for.
+    pub ignore_mod_loader: bool,
+    /// Look up the latest version for this Minecraft version instead of the pack's, and record it
+    /// in the config.
+    pub game_version: Option<String>,
+    /// Only consider versions of these types for the latest version, instead of the pack's
+    /// `version_types`.
+    pub version_types: Option<Vec<VersionType>>,
+    /// Don't add the required dependencies of the added mods.
+    pub no_deps: bool,
+    /// The policy of the pack, to refuse mods that verification would reject. Its banned projects
+    /// are blocked in the project lists.
+    pub policy: Option<LoadedPolicy>,
+}
+
+/// An ID type that can be written into the config.
+pub trait ConfigIdValue: ModIdValue {
+    fn to_toml(&self) -> toml_edit::Value;
+}
+
+impl ConfigIdValue for i32 {
+    fn to_toml(&self) -> toml_edit::Value {
+        i64::from(*self).into()
+    }
+}
+
+impl ConfigIdValue for String {
+    fn to_toml(&self) -> toml_edit::Value {
+        self.as_str().into()
+    }
+}
+
+/// Resolve the version of each project to add, and add them to the config document under
+/// `mods.<site>`. Projects that are already in the config are skipped. The required dependencies
+/// of the added versions are added as well, recursively, unless `no_deps` is set.
+///
+/// Returns the number of mods added, or the failures if any project could not be added. Mods that
+/// could be resolved are added to the document either way.
+pub(crate) async fn add_mods_from_site<S, K>(
+    pack_config: &PackConfig<ConfigModContainer>,
+    existing: &HashMap<String, ConfigMod<K>>,
+    project_lists: &SiteProjectLists,
+    document: &mut Document,
+    site: S,
+    requests: Vec<AddModRequest<K>>,
+    options: &AddModsOptions,
+) -> Result<usize, ModsAddError>
+where
+    K: ConfigIdValue,
+    S: ModSite<Id = K>,
+{
+    // Every project requested so far, by the ID it was requested with and by its resolved ID, so
+    // each dependency is only added once, even if it was requested by slug.
+    let mut requested = requests
+        .iter()
+        .map(|r| r.project_id.clone())
+        .collect::<HashSet<_>>();
+    let mut requests = requests;
+    let mut added = 0;
+    let mut failures = HashMap::new();
+    while !requests.is_empty() {
+        let (added_mods, round_failures) = add_mods_round(
+            pack_config,
+            existing,
+            project_lists,
+            document,
+            site,
+            std::mem::take(&mut requests),
+            options,
+        )
+        .await;
+        added += added_mods.len();
+        failures.extend(round_failures);
+        requested.extend(added_mods.iter().map(|(_, id, _)| id.project_id.clone()));
+        if !options.no_deps {
+            requests = required_deps(existing, &mut requested, site, added_mods).await;
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(added)
+    } else {
+        Err(ModsAddError { added, failures })
+    }
+}
+
+/// Add the requested projects to the config document. Projects that resolve to a mod of the config
+/// or to one added in this round are skipped.
+///
+/// Returns the config key, version and whether it's optional on the client of each mod that was
+/// added, and the failures.
+async fn add_mods_round<S, K>(
+    pack_config: &PackConfig<ConfigModContainer>,
+    existing: &HashMap<String, ConfigMod<K>>,
+    project_lists: &SiteProjectLists,
+    document: &mut Document,
+    site: S,
+    requests: Vec<AddModRequest<K>>,
+    options: &AddModsOptions,
+) -> (Vec<(String, ModId<K>, bool)>, HashMap<String, AddModError>)
+where
+    K: ConfigIdValue,
+    S: ModSite<Id = K>,
+{
+    let resolutions = futures::stream::iter(requests)
+        .filter(|request| {
+            let existing_key = existing_key::<S, K>(existing, &request.project_id);
+            futures::future::ready(existing_key.is_none())
+        })
+        .map(|request| async move {
+            let result = resolve_mod(pack_config, project_lists, site, &request, options).await;
+            (request, result)
+        })
+        .buffered(5)
+        .collect::<Vec<_>>()
+        .await;
+
+    let site_table = site_table_mut(document, S::CONFIG_KEY);
+    let mut added = Vec::new();
+    let mut added_projects = HashSet::new();
+    let mut failures = HashMap::new();
+    for (request, result) in resolutions {
+        let (key, info, id) = match result {
+            Ok(v) => v,
+            Err(e) => {
+                failures.insert(request.project_id.to_string(), e);
+                continue;
+            }
+        };
+        // A slug was only compared to the config by itself, so compare its resolved ID as well.
+        if existing_key::<S, K>(existing, &id.project_id).is_some() {
+            continue;
+        }
+        if !added_projects.insert(id.project_id.clone()) {
+            log::info!(
+                "[{}] Project {} was requested twice, skipping.",
+                S::NAME.errstyle(SITE_NAME_STYLE),
+                request.project_id.errstyle(SITE_VAL_STYLE),
+            );
+            continue;
+        }
+        let key = if site_table.contains_key(&key) {
+            let resolved = if std::io::stdin().is_terminal() {
+                tokio::task::block_in_place(|| prompt_key_collision(&*site_table, &key, &info.name))
+            } else {
+                Some(KeyCollision::Rename(free_key(&*site_table, &key)))
+            };
+            match resolved {
+                Some(KeyCollision::Rename(new_key)) => {
+                    log::warn!(
+                        "[{}] Key {} for {} already exists in the config, using {} instead.",
+                        S::NAME.errstyle(SITE_NAME_STYLE),
+                        key.errstyle(CONFIG_VAL_STYLE),
+                        info.name.errstyle(SITE_VAL_STYLE),
+                        new_key.errstyle(CONFIG_VAL_STYLE),
+                    );
+                    new_key
+                }
+                Some(KeyCollision::Overwrite) => {
+                    log::warn!(
+                        "[{}] Overwriting {} with {}.",
+                        S::NAME.errstyle(SITE_NAME_STYLE),
+                        key.errstyle(CONFIG_VAL_STYLE),
+                        info.name.errstyle(SITE_VAL_STYLE),
+                    );
+                    key
+                }
+                None => {
+                    log::warn!(
+                        "[{}] Key {} for {} already exists in the config, skipping.",
+                        S::NAME.errstyle(SITE_NAME_STYLE),
+                        key.errstyle(CONFIG_VAL_STYLE),
+                        info.name.errstyle(SITE_VAL_STYLE),
+                    );
+                    continue;
+                }
+            }
+        } else {
+            key
+        };
+        let mut entry = InlineTable::new();
+        entry.insert("project_id", id.project_id.to_toml());
+        entry.insert("version_id", id.version_id.to_toml());
+        if request.client_optional {
+            entry.insert("client", "optional".into());
+        }
+        if let Some(game_version) = &options.game_version {
+            entry.insert("game_version", game_version.as_str().into());
+        }
+        if options.ignore_mod_loader {
+            entry.insert("ignore_mod_loader", true.into());
+        }
+        site_table.insert(&key, Item::Value(entry.into()));
+        log::info!(
+            "[{}] Added {} ({}) as {} (version {}).",
+            S::NAME.errstyle(SITE_NAME_STYLE),
+            info.name.errstyle(SITE_VAL_STYLE),
+            info.slug.errstyle(SITE_VAL_STYLE),
+            key.errstyle(CONFIG_VAL_STYLE),
+            id.version_id.errstyle(SITE_VAL_STYLE),
+        );
+        added.push((key, id, request.client_optional));
+    }
+    (added, failures)
+}
+
+/// The key of the mod of the config with the given project ID, logging that the project is skipped
+/// if there is one.
+fn existing_key<'e, S, K>(
+    existing: &'e HashMap<String, ConfigMod<K>>,
+    project_id: &K,
+) -> Option<&'e str>
+where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+{
+    let (key, _) = existing
+        .iter()
+        .find(|(_, m)| m.source.project_id == *project_id)?;
+    log::info!(
+        "[{}] Project {} is already in the config as {}, skipping.",
+        S::NAME.errstyle(SITE_NAME_STYLE),
+        project_id.errstyle(SITE_VAL_STYLE),
+        key.errstyle(CONFIG_VAL_STYLE),
+    );
+    Some(key)
+}
+
+/// The required dependencies of the added mods that aren't in the config and weren't requested
+/// yet, as requests for their latest version. They're optional on the client if the mod needing
+/// them is. Dependencies on a specific version are left to verification, as not every site can
+/// tell their project.
+async fn required_deps<S, K>(
+    existing: &HashMap<String, ConfigMod<K>>,
+    requested: &mut HashSet<K>,
+    site: S,
+    added: Vec<(String, ModId<K>, bool)>,
+) -> Vec<AddModRequest<K>>
+where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+{
+    let files = futures::stream::iter(added)
+        .map(|(key, id, client_optional)| async move {
+            let file = site.load_file(id).await;
+            (key, client_optional, file)
+        })
+        .buffered(5)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut deps = Vec::new();
+    for (key, client_optional, file) in files {
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                log::warn!(
+                    "[{}] Couldn't load the dependencies of {}, add them by hand: {}",
+                    S::NAME.errstyle(SITE_NAME_STYLE),
+                    key.errstyle(CONFIG_VAL_STYLE),
+                    e,
+                );
+                continue;
+            }
+        };
+        for dep in file.dependencies {
+            if dep.kind != ModDependencyKind::Required {
+                continue;
+            }
+            let project_id = match dep.id {
+                DependencyId::Project(project_id) => project_id,
+                DependencyId::Version(version_id) => {
+                    if !existing.values().any(|m| m.source.version_id == version_id) {
+                        log::warn!(
+                            "[{}] {} requires version {}, add it by hand.",
+                            S::NAME.errstyle(SITE_NAME_STYLE),
+                            key.errstyle(CONFIG_VAL_STYLE),
+                            version_id.errstyle(SITE_VAL_STYLE),
+                        );
+                    }
+                    continue;
+                }
+            };
+            if existing.values().any(|m| m.source.project_id == project_id)
+                || !requested.insert(project_id.clone())
+            {
+                continue;
+            }
+            log::info!(
+                "[{}] Adding required dependency {} of {}.",
+                S::NAME.errstyle(SITE_NAME_STYLE),
+                project_id.errstyle(SITE_VAL_STYLE),
+                key.errstyle(CONFIG_VAL_STYLE),
+            );
+            deps.push(AddModRequest {
+                project_id,
+                version_id: None,
+                client_optional,
+            });
+        }
+    }
+    deps
+}
+
+/// Resolve a request into its config key, display name, and the ID of the version to add.
+async fn resolve_mod<S, K>(
+    pack_config: &PackConfig<ConfigModContainer>,
+    project_lists: &SiteProjectLists,
+    site: S,
+    request: &AddModRequest<K>,
+    options: &AddModsOptions,
+) -> Result<(String, ModInfo, ModId<K>), AddModError>
+where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+{
+    if let Some(policy) = &options.policy {
+        if !policy.site_allowed(S::CONFIG_KEY) {
+            return Err(AddModError::SiteNotAllowed(policy.source.clone()));
+        }
+    }
+    let info = site.load_metadata(request.project_id.clone()).await?;
+    // Unknown licenses are left to verification, which only rejects them in strict mode.
+    if let (Some(policy), Some(license)) = (&options.policy, &info.license) {
+        if policy
+            .policy
+            .allowed_licenses
+            .as_ref()
+            .is_some_and(|allowed| !allowed.contains(license))
+        {
+            return Err(AddModError::LicenseNotAllowed {
+                license: license.clone(),
+                policy: policy.source.clone(),
+            });
+        }
+    }
+    let minecraft_version = options
+        .game_version
+        .as_ref()
+        .unwrap_or(&pack_config.minecraft_version);
+    let id = match &request.version_id {
+        Some(version_id) => ModId {
+            project_id: site
+                .canonical_project_id(request.project_id.clone())
+                .await?,
+            version_id: version_id.clone(),
+        },
+        None => site
+            .get_latest_version_for_pack(
+                request.project_id.clone(),
+                minecraft_version,
+                &pack_config.mod_loader.id,
+                options.ignore_mod_loader,
+                pack_config.prefer_featured,
+                options
+                    .version_types
+                    .as_deref()
+                    .unwrap_or(&pack_config.version_types),
+            )
+            .await?
+            .ok_or_else(|| AddModError::NoVersion {
+                minecraft_version: minecraft_version.clone(),
+                mod_loader: pack_config.mod_loader.id.to_string(),
+            })?,
+    };
+    project_lists.check(&id.project_id.to_string(), &info.slug)?;
+
+    let key = match options.key_strategy {
+        KeyStrategy::Name => config_key_for_name(&info.name).unwrap_or_else(|| info.slug.clone()),
+        KeyStrategy::Slug => info.slug.clone(),
+        KeyStrategy::Id => id.project_id.to_string(),
+    };
+    Ok((key, info, id))
+}
+
+/// What to do with a mod whose key is already in the config. Skipping is `None`.
+enum KeyCollision {
+    Rename(String),
+    Overwrite,
+}
+
+/// Find a free key by adding a numeric suffix, e.g. `jei-2`.
+fn free_key(site_table: &dyn TableLike, key: &str) -> String {
+    (2..)
+        .map(|n| format!("{}-{}", key, n))
+        .find(|k| !site_table.contains_key(k))
+        .expect("ran out of keys")
+}
+
+/// Ask the user what to do with a mod whose key is already in the config.
+fn prompt_key_collision(site_table: &dyn TableLike, key: &str, name: &str) -> Option<KeyCollision> {
+    let suggestion = free_key(site_table, key);
+    loop {
+        eprint!(
+            "Key {} for {} already exists. [r]ename (default: {}), [o]verwrite, or [s]kip? ",
+            key.errstyle(CONFIG_VAL_STYLE),
+            name.errstyle(SITE_VAL_STYLE),
+            suggestion.errstyle(CONFIG_VAL_STYLE),
+        );
+        let mut answer = String::new();
+        // Treat a closed or broken stdin like a skip, rather than looping forever.
+        if std::io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+            return None;
+        }
+        match answer.trim() {
+            "r" | "rename" => {
+                eprint!("New key [{}]: ", suggestion);
+                let mut new_key = String::new();
+                if std::io::stdin().read_line(&mut new_key).unwrap_or(0) == 0 {
+                    return None;
+                }
+                let new_key = new_key.trim();
+                if new_key.is_empty() {
+                    return Some(KeyCollision::Rename(suggestion));
+                }
+                if site_table.contains_key(new_key) {
+                    eprintln!("Key {} already exists as well.", new_key);
+                    continue;
+                }
+                return Some(KeyCollision::Rename(new_key.to_string()));
+            }
+            "o" | "overwrite" => return Some(KeyCollision::Overwrite),
+            "s" | "skip" => return None,
+            _ => eprintln!("Please answer r, o, or s."),
+        }
+    }
+}
+
+/// Generate a config key from a mod's name, e.g. `Just Enough Items (JEI)` becomes
+/// `just-enough-items-jei`. Returns `None` if the name has no ASCII alphanumeric characters.
+fn config_key_for_name(name: &str) -> Option<String> {
+    let mut key = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            key.push(c.to_ascii_lowercase());
+        } else if !key.is_empty() && !key.ends_with('-') {
+            key.push('-');
+        }
+    }
+    let key = key.trim_end_matches('-');
+    (!key.is_empty()).then(|| key.to_string())
+}
+
+/// Get the `mods.<site_key>` table of the document, creating it if needed.
+fn site_table_mut<'d>(document: &'d mut Document, site_key: &str) -> &'d mut dyn TableLike {
+    let mods = document
+        .entry("mods")
+        .or_insert_with(|| {
+            let mut t = Table::new();
+            t.set_implicit(true);
+            Item::Table(t)
+        })
+        .as_table_like_mut()
+        .expect("mods must be a table");
+    mods.entry(site_key)
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .expect("mods site entry must be a table")
+}
+
+/// Write the config document back to `config.toml`. Back it up with
+/// [crate::backups::backup_config] first.
+pub(crate) fn write_config(source_dir: &Path, document: &Document) -> std::io::Result<()> {
+    std::fs::write(source_dir.join("config.toml"), document.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use toml_edit::Document;
+
+    use crate::config::mods::EnvRequirement;
+    use crate::config::pack::{ModLoaderType, VersionType};
+    use crate::config::project_lists::SiteProjectLists;
+    use crate::config::source::load_pack_config;
+    use crate::mod_site::{
+        DependencyId, ModDependency, ModDependencyKind, ModFileInfo, ModFileLoadingResult, ModId,
+        ModInfo, ModLoadingError, ModLoadingResult, ModSite, ModrinthHash, PublishedVersion,
+        SideInfo,
+    };
+
+    use super::{add_mods_from_site, AddModRequest, AddModsOptions};
+
+    const FABRIC_API: &str = "P7dR8mSH";
+    const SODIUM_EXTRA: &str = "PtjYWJkn";
+
+    /// A site with Fabric API, requested by its slug, and Sodium Extra, which requires it.
+    #[derive(Debug, Copy, Clone)]
+    struct FakeSite;
+
+    fn project_id(project: &str) -> &'static str {
+        match project {
+            "fabric-api" | FABRIC_API => FABRIC_API,
+            "sodium-extra" | SODIUM_EXTRA => SODIUM_EXTRA,
+            _ => panic!("unknown project {}", project),
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ModSite for FakeSite {
+        const NAME: &'static str = "Fake";
+        const CONFIG_KEY: &'static str = "modrinth";
+        type Id = String;
+        type ModHash = ModrinthHash;
+
+        async fn load_metadata(&self, project_id: String) -> ModLoadingResult {
+            let slug = match self::project_id(&project_id) {
+                FABRIC_API => "fabric-api",
+                _ => "sodium-extra",
+            };
+            Ok(ModInfo {
+                name: slug.to_string(),
+                slug: slug.to_string(),
+                distribution_allowed: true,
+                license: None,
+                authors: Vec::new(),
+                team: None,
+                url: None,
+                summary: None,
+                categories: Vec::new(),
+                side_info: SideInfo {
+                    client: EnvRequirement::Required,
+                    server: EnvRequirement::Required,
+                },
+            })
+        }
+
+        async fn get_latest_version_for_pack(
+            &self,
+            project_id: String,
+            _: &str,
+            _: &ModLoaderType,
+            _: bool,
+            _: bool,
+            _: &[VersionType],
+        ) -> Result<Option<ModId<String>>, ModLoadingError> {
+            let project_id = self::project_id(&project_id).to_string();
+            Ok(Some(ModId {
+                version_id: format!("{}-v1", project_id),
+                project_id,
+            }))
+        }
+
+        async fn get_newer_versions_for_pack(
+            &self,
+            _: ModId<String>,
+            _: &str,
+            _: &ModLoaderType,
+        ) -> Result<Vec<PublishedVersion<String>>, ModLoadingError> {
+            unimplemented!()
+        }
+
+        async fn load_metadata_by_version(&self, _: String) -> Option<ModLoadingResult> {
+            unimplemented!()
+        }
+
+        async fn load_file(&self, id: ModId<String>) -> ModFileLoadingResult<String, ModrinthHash> {
+            let dependencies = if id.project_id == SODIUM_EXTRA {
+                vec![ModDependency {
+                    id: DependencyId::Project(FABRIC_API.to_string()),
+                    kind: ModDependencyKind::Required,
+                }]
+            } else {
+                Vec::new()
+            };
+            Ok(ModFileInfo {
+                project_info: self.load_metadata(id.project_id.clone()).await?,
+                filename: format!("{}.jar", id.version_id),
+                url: format!("https://example.com/{}.jar", id.version_id),
+                file_length: 0,
+                minecraft_versions: vec!["1.20.1".to_string()],
+                dependencies,
+                hash: ModrinthHash {
+                    sha1: Default::default(),
+                    sha512: Default::default(),
+                    sha256: None,
+                },
+            })
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dependency_requested_by_slug_is_added_once() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let config = r#"
+name = "Deps"
+description = "A pack that adds dependencies"
+author = "netherfire"
+version = "1.0.0"
+minecraft_version = "1.20.1"
+mod_loader = { id = "fabric", version = "0.15.11" }
+
+[mods.curseforge]
+[mods.modrinth]
+"#;
+        std::fs::write(source_dir.path().join("config.toml"), config).unwrap();
+        let (pack, _) = load_pack_config(source_dir.path()).unwrap();
+        let mut document = config.parse::<Document>().unwrap();
+        let options = AddModsOptions {
+            key_strategy: Default::default(),
+            ignore_mod_loader: false,
+            game_version: None,
+            version_types: None,
+            no_deps: false,
+            policy: None,
+        };
+
+        let added = add_mods_from_site(
+            &pack,
+            &HashMap::new(),
+            &SiteProjectLists::default(),
+            &mut document,
+            FakeSite,
+            vec![
+                AddModRequest::latest("fabric-api".to_string()),
+                AddModRequest::latest("sodium-extra".to_string()),
+            ],
+            &options,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(added, 2);
+        let mods = document["mods"]["modrinth"].as_table().unwrap();
+        assert_eq!(
+            mods.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["fabric-api", "sodium-extra"]
+        );
+        assert_eq!(mods["fabric-api"]["project_id"].as_str(), Some(FABRIC_API));
+    }
+}
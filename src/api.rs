@@ -0,0 +1,298 @@
+//! Minimal clients for the CurseForge and Modrinth APIs. These are like [furse::Furse] and
+//! [ferinth::Ferinth], and return the same types and errors, but allow configuring the base URL.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use ferinth::structures::project::Project;
+use ferinth::structures::user::TeamMember;
+use ferinth::structures::version::Version;
+use furse::structures::file_structs::File;
+use furse::structures::mod_structs::Mod;
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::metrics::{record_request, ApiSite};
+use crate::retry::RetryPolicy;
+
+pub const DEFAULT_CURSEFORGE_API_URL: &str = "https://api.curseforge.com/v1/";
+pub const DEFAULT_MODRINTH_API_URL: &str = "https://api.modrinth.com/v2/";
+
+/// Parse an API base URL, making sure it ends with a `/` so paths can be joined onto it.
+pub fn parse_base_url(url: &str) -> Result<Url, url::ParseError> {
+    if url.ends_with('/') {
+        Url::parse(url)
+    } else {
+        Url::parse(&format!("{}/", url))
+    }
+}
+
+/// Send a request to the API of `site`, recording it in the metrics of the run.
+async fn timed_send(site: ApiSite, request: RequestBuilder) -> Result<Response, reqwest::Error> {
+    let start = Instant::now();
+    let response = request.send().await;
+    record_request(
+        site,
+        start.elapsed(),
+        response.as_ref().ok().map(|r| (r.status(), r.headers())),
+    );
+    response
+}
+
+#[derive(Debug, Clone)]
+pub struct CurseForgeApi {
+    client: Client,
+    base_url: Url,
+    api_key: String,
+    retry: RetryPolicy,
+}
+
+/// CurseForge wraps all responses in a `data` field.
+#[derive(Deserialize)]
+struct CurseForgeResponse<T> {
+    data: T,
+}
+
+impl CurseForgeApi {
+    pub fn new(client: Client, base_url: Url, api_key: &str, retry: RetryPolicy) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key: api_key.to_string(),
+            retry,
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: Url) -> Result<T, furse::Error> {
+        self.retry
+            .run(|| async {
+                let response = timed_send(
+                    ApiSite::CurseForge,
+                    self.client
+                        .get(url.clone())
+                        .header("x-api-key", &self.api_key),
+                )
+                .await?;
+                Ok(response
+                    .error_for_status()?
+                    .json::<CurseForgeResponse<T>>()
+                    .await?
+                    .data)
+            })
+            .await
+    }
+
+    pub async fn get_mod(&self, mod_id: i32) -> Result<Mod, furse::Error> {
+        self.get(self.base_url.join(&format!("mods/{}", mod_id))?)
+            .await
+    }
+
+    pub async fn get_mod_files(&self, mod_id: i32) -> Result<Vec<File>, furse::Error> {
+        let mut url = self.base_url.join(&format!("mods/{}/files", mod_id))?;
+        url.set_query(Some("pageSize=10000"));
+        self.get(url).await
+    }
+
+    pub async fn get_mod_file(&self, mod_id: i32, file_id: i32) -> Result<File, furse::Error> {
+        self.get(
+            self.base_url
+                .join(&format!("mods/{}/files/{}", mod_id, file_id))?,
+        )
+        .await
+    }
+
+    /// Get the changelog of a file, as HTML.
+    pub async fn get_mod_file_changelog(
+        &self,
+        mod_id: i32,
+        file_id: i32,
+    ) -> Result<String, furse::Error> {
+        self.get(
+            self.base_url
+                .join(&format!("mods/{}/files/{}/changelog", mod_id, file_id))?,
+        )
+        .await
+    }
+}
+
+/// An error from the Modrinth API, or in the URL of a request to it.
+#[derive(Debug, Error)]
+pub enum ModrinthError {
+    #[error("{0}")]
+    Api(#[from] ferinth::Error),
+    #[error("Invalid Modrinth API URL: {0}")]
+    UrlParse(#[from] url::ParseError),
+    #[error(
+        "The Modrinth API URL '{0}' doesn't end with `v2/`, so the v3 API can't be found next to it"
+    )]
+    NoV3Api(Url),
+}
+
+impl From<reqwest::Error> for ModrinthError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Api(e.into())
+    }
+}
+
+impl From<serde_json::Error> for ModrinthError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Api(e.into())
+    }
+}
+
+pub type ModrinthResult<T> = Result<T, ModrinthError>;
+
+#[derive(Debug, Clone)]
+pub struct ModrinthApi {
+    client: Client,
+    base_url: Url,
+    /// A personal access token, for private projects.
+    token: Option<String>,
+    retry: RetryPolicy,
+}
+
+impl ModrinthApi {
+    pub fn new(client: Client, base_url: Url, token: Option<String>, retry: RetryPolicy) -> Self {
+        Self {
+            client,
+            base_url,
+            token,
+            retry,
+        }
+    }
+
+    fn url(&self, path: &str) -> ModrinthResult<Url> {
+        Ok(self.base_url.join(path)?)
+    }
+
+    /// The URL of `path` in v3 of the API, which is expected next to the configured v2 API.
+    fn v3_url(&self, path: &str) -> ModrinthResult<Url> {
+        let Some(prefix) = self.base_url.path().strip_suffix("v2/") else {
+            return Err(ModrinthError::NoV3Api(self.base_url.clone()));
+        };
+        let mut base_url = self.base_url.clone();
+        base_url.set_path(&format!("{}v3/", prefix));
+        Ok(base_url.join(path)?)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> ModrinthResult<T> {
+        let request = match &self.token {
+            Some(token) => request.header(AUTHORIZATION, token),
+            None => request,
+        };
+        self.retry
+            .run(|| {
+                // Requests only have JSON bodies, which can always be cloned.
+                Self::send_once(request.try_clone().expect("request body can't be cloned"))
+            })
+            .await
+    }
+
+    async fn send_once<T: DeserializeOwned>(request: RequestBuilder) -> ModrinthResult<T> {
+        let response = timed_send(ApiSite::Modrinth, request).await?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            let reset = response
+                .headers()
+                .get("X-Ratelimit-Reset")
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| h.parse().ok())
+                .unwrap_or(1);
+            return Err(ferinth::Error::RateLimitExceeded(reset).into());
+        }
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    pub async fn get_project(&self, project_id: &str) -> ModrinthResult<Project> {
+        self.send(
+            self.client
+                .get(self.url(&format!("project/{}", project_id))?),
+        )
+        .await
+    }
+
+    pub async fn get_version(&self, version_id: &str) -> ModrinthResult<Version> {
+        self.send(
+            self.client
+                .get(self.url(&format!("version/{}", version_id))?),
+        )
+        .await
+    }
+
+    pub async fn list_versions_filtered(
+        &self,
+        project_id: &str,
+        loaders: Option<&[&str]>,
+        game_versions: Option<&[&str]>,
+        featured: Option<bool>,
+    ) -> ModrinthResult<Vec<Version>> {
+        let mut url = self.url(&format!("project/{}/version", project_id))?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(loaders) = loaders {
+                query.append_pair("loaders", &serde_json::to_string(loaders)?);
+            }
+            if let Some(game_versions) = game_versions {
+                query.append_pair("game_versions", &serde_json::to_string(game_versions)?);
+            }
+            if let Some(featured) = featured {
+                query.append_pair("featured", &featured.to_string());
+            }
+        }
+        self.send(self.client.get(url)).await
+    }
+
+    /// List the members of several teams at once, grouped by team.
+    pub async fn list_multiple_teams_members(
+        &self,
+        team_ids: &[&str],
+    ) -> ModrinthResult<Vec<Vec<TeamMember>>> {
+        let mut url = self.url("teams")?;
+        url.query_pairs_mut()
+            .append_pair("ids", &serde_json::to_string(team_ids)?);
+        self.send(self.client.get(url)).await
+    }
+
+    /// Get the IDs of the projects in a collection. Collections are only available in v3 of the
+    /// API, which is expected next to the configured v2 API.
+    pub async fn get_collection_projects(
+        &self,
+        collection_id: &str,
+    ) -> ModrinthResult<Vec<String>> {
+        #[derive(Deserialize)]
+        struct Collection {
+            projects: Vec<String>,
+        }
+
+        let collection: Collection = self
+            .send(
+                self.client
+                    .get(self.v3_url(&format!("collection/{}", collection_id))?),
+            )
+            .await?;
+        Ok(collection.projects)
+    }
+
+    pub async fn get_versions_from_hashes(
+        &self,
+        hashes: Vec<String>,
+    ) -> ModrinthResult<HashMap<String, Version>> {
+        #[derive(Serialize)]
+        struct HashesBody {
+            hashes: Vec<String>,
+            algorithm: &'static str,
+        }
+
+        self.send(
+            self.client
+                .post(self.url("version_files")?)
+                .json(&HashesBody {
+                    hashes,
+                    algorithm: "sha1",
+                }),
+        )
+        .await
+    }
+}
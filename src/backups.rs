@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::uwu_colors::{ErrStyle, FILE_STYLE};
+
+/// Directory in the pack source directory that holds backups of `config.toml`.
+pub const BACKUP_DIR: &str = ".netherfire-backups";
+
+/// How many backups are kept. Older ones are removed when a new one is made.
+const MAX_BACKUPS: usize = 10;
+
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("I/O error on {0}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("There are no backups of config.toml in {0}")]
+    NoBackup(String),
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> BackupError + '_ {
+    move |e| BackupError::Io(path.display().to_string(), e)
+}
+
+/// The backups in the source directory, oldest first, with the time they were made in
+/// milliseconds since the epoch.
+fn list_backups(source_dir: &Path) -> Result<Vec<(u128, PathBuf)>, BackupError> {
+    let backup_dir = source_dir.join(BACKUP_DIR);
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(&backup_dir).map_err(io_error(&backup_dir))? {
+        let path = entry.map_err(io_error(&backup_dir))?.path();
+        let made_at = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("config.toml."))
+            .and_then(|n| n.parse().ok());
+        if let Some(made_at) = made_at {
+            backups.push((made_at, path));
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Back up the current `config.toml` before it's edited, removing the oldest backups if there are
+/// too many.
+pub fn backup_config(source_dir: &Path) -> Result<(), BackupError> {
+    let backup_dir = source_dir.join(BACKUP_DIR);
+    std::fs::create_dir_all(&backup_dir).map_err(io_error(&backup_dir))?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the epoch")
+        .as_millis();
+    // Keep the backups in order, even if two are made within the same millisecond.
+    let made_at = list_backups(source_dir)?
+        .last()
+        .map_or(now, |(last, _)| now.max(last + 1));
+    let config_file = source_dir.join(CONFIG_FILE);
+    let backup_file = backup_dir.join(format!("{}.{}", CONFIG_FILE, made_at));
+    std::fs::copy(&config_file, &backup_file).map_err(io_error(&config_file))?;
+
+    let backups = list_backups(source_dir)?;
+    for (_, old) in &backups[..backups.len().saturating_sub(MAX_BACKUPS)] {
+        std::fs::remove_file(old).map_err(io_error(old))?;
+    }
+    Ok(())
+}
+
+/// Restore `config.toml` from the latest backup, and remove that backup so the next undo goes
+/// further back.
+pub fn restore_latest_backup(source_dir: &Path) -> Result<(), BackupError> {
+    let (_, backup_file) = list_backups(source_dir)?
+        .pop()
+        .ok_or_else(|| BackupError::NoBackup(source_dir.display().to_string()))?;
+    let config_file = source_dir.join(CONFIG_FILE);
+    std::fs::copy(&backup_file, &config_file).map_err(io_error(&config_file))?;
+    std::fs::remove_file(&backup_file).map_err(io_error(&backup_file))?;
+    log::info!(
+        "Restored '{}' from '{}'.",
+        config_file.display().errstyle(FILE_STYLE),
+        backup_file.display().errstyle(FILE_STYLE)
+    );
+    Ok(())
+}
@@ -0,0 +1,262 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+use walkdir::WalkDir;
+
+use crate::config::global::DIRS;
+
+/// Sub-directory of the cache holding downloaded mod files, by SHA-256 hash.
+const DOWNLOADS: &str = "downloads";
+/// Sub-directory of the cache mapping the hashes mod sites report to the SHA-256 hashes of the
+/// downloads.
+const DOWNLOAD_INDEX: &str = "download-index";
+/// Sub-directory of the cache holding large downloads while they're downloaded and checked, so
+/// they can be moved into the download cache without copying.
+const SPOOL: &str = "spool";
+/// Sub-directory of the cache holding verified mods, by a hash of the inputs of the verification.
+const VERIFICATIONS: &str = "verifications";
+
+pub fn cache_dir() -> &'static Path {
+    DIRS.cache_dir()
+}
+
+/// The path a file with the given hex SHA-256 hash is cached at.
+pub fn download_path(sha256: &str) -> PathBuf {
+    cache_dir().join(DOWNLOADS).join(&sha256[..2]).join(sha256)
+}
+
+fn download_index_path(site_hash: &str) -> PathBuf {
+    cache_dir().join(DOWNLOAD_INDEX).join(site_hash)
+}
+
+/// Store `content` in the download cache under its hex SHA-256 hash, and remember that hash for
+/// the hash the mod site reports, as given by [crate::mod_site::ModHash::site_hash_key].
+pub fn store_download(site_hash: &str, sha256: &str, content: &[u8]) -> Result<(), std::io::Error> {
+    store(&download_path(sha256), content)?;
+    store(&download_index_path(site_hash), sha256.as_bytes())
+}
+
+/// Like [store_download], for a download spooled to `file`, which is moved into the cache.
+pub fn store_download_file(
+    site_hash: &str,
+    sha256: &str,
+    file: &Path,
+) -> Result<(), std::io::Error> {
+    let path = download_path(sha256);
+    std::fs::create_dir_all(path.parent().expect("cache path has a parent"))?;
+    std::fs::rename(file, path)?;
+    store(&download_index_path(site_hash), sha256.as_bytes())
+}
+
+/// Create a temporary file to spool a large download to, which is deleted when it's dropped
+/// unless it was moved into the cache with [store_download_file].
+pub fn spool_file() -> Result<tempfile::NamedTempFile, std::io::Error> {
+    let dir = cache_dir().join(SPOOL);
+    std::fs::create_dir_all(&dir)?;
+    tempfile::NamedTempFile::new_in(dir)
+}
+
+/// The hex SHA-256 hash of a cached download, by the hash the mod site reports.
+pub fn download_sha256(site_hash: &str) -> Option<String> {
+    let sha256 = String::from_utf8(load(&download_index_path(site_hash))?).ok()?;
+    // Don't build paths from a corrupt index entry.
+    (sha256.len() == 64 && sha256.bytes().all(|b| b.is_ascii_hexdigit())).then_some(sha256)
+}
+
+/// Read a file from the download cache, if it's there. Marks the file as recently used.
+pub fn load_download(sha256: &str) -> Option<Vec<u8>> {
+    load(&download_path(sha256))
+}
+
+/// The path of a file in the download cache, if it's there, to read large files without loading
+/// them into memory. Marks the file as recently used.
+pub fn download_file(sha256: &str) -> Option<PathBuf> {
+    let path = download_path(sha256);
+    path.is_file().then(|| {
+        touch(&path);
+        path
+    })
+}
+
+fn verification_path(key: &str) -> PathBuf {
+    cache_dir()
+        .join(VERIFICATIONS)
+        .join(format!("{}.json", key))
+}
+
+/// Store the verified mods of a pack under the hash of the inputs they were verified from.
+pub fn store_verification<T: Serialize>(key: &str, verified: &T) -> Result<(), std::io::Error> {
+    store(&verification_path(key), &serde_json::to_vec(verified)?)
+}
+
+/// Read the verified mods of a pack from the cache, if the same inputs were verified before.
+/// Marks the file as recently used.
+pub fn load_verification<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let path = verification_path(key);
+    let content = load(&path)?;
+    serde_json::from_slice(&content)
+        .map_err(|e| log::debug!("Ignoring invalid cache file {}: {}", path.display(), e))
+        .ok()
+}
+
+/// Write `content` to `path` under a temporary name and rename it, so other runs never see a
+/// partial file.
+fn store(path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+    let dir = path.parent().expect("cache path has a parent");
+    std::fs::create_dir_all(dir)?;
+    let file_name = path.file_name().expect("cache path has a file name");
+    let temp_path = dir.join(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    std::fs::write(&temp_path, content)?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// Read a cached file, if it's there, and mark it as recently used.
+fn load(path: &Path) -> Option<Vec<u8>> {
+    let content = std::fs::read(path).ok()?;
+    touch(path);
+    Some(content)
+}
+
+/// Mark a cached file as recently used.
+fn touch(path: &Path) {
+    if let Err(e) = std::fs::File::options()
+        .append(true)
+        .open(path)
+        .and_then(|f| f.set_modified(SystemTime::now()))
+    {
+        log::debug!("Failed to touch {}: {}", path.display(), e);
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Walk error: {0}")]
+    Walk(#[from] walkdir::Error),
+}
+
+/// Number of files and their total size in a section of the cache.
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Account for the files in every section (top-level directory) of the cache.
+pub fn cache_stats() -> Result<Vec<(String, CacheStats)>, CacheError> {
+    let mut sections = Vec::new();
+    if !cache_dir().exists() {
+        return Ok(sections);
+    }
+    for entry in std::fs::read_dir(cache_dir())? {
+        let entry = entry?;
+        let mut stats = CacheStats::default();
+        for file in WalkDir::new(entry.path()) {
+            let file = file?;
+            if file.file_type().is_file() {
+                stats.files += 1;
+                stats.bytes += file.metadata()?.len();
+            }
+        }
+        sections.push((entry.file_name().to_string_lossy().into_owned(), stats));
+    }
+    sections.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(sections)
+}
+
+/// Delete cached files. If `older_than` is given, only files that weren't used for that long are
+/// deleted. If `max_size` is given, the least recently used files are deleted until the rest fit
+/// into that many bytes. Without either, everything is deleted. Returns what was deleted.
+pub fn clean_cache(
+    older_than: Option<Duration>,
+    max_size: Option<u64>,
+) -> Result<CacheStats, CacheError> {
+    let mut removed = CacheStats::default();
+    if !cache_dir().exists() {
+        return Ok(removed);
+    }
+    let now = SystemTime::now();
+    let mut files = Vec::new();
+    for file in WalkDir::new(cache_dir()) {
+        let file = file?;
+        if file.file_type().is_file() {
+            let metadata = file.metadata()?;
+            files.push((file.into_path(), metadata.modified()?, metadata.len()));
+        }
+    }
+    // Least recently used first.
+    files.sort_by_key(|(_, modified, _)| *modified);
+    let mut kept_bytes = files.iter().map(|(_, _, len)| len).sum::<u64>();
+    for (path, modified, len) in files {
+        let too_old = older_than.is_some_and(|older_than| {
+            now.duration_since(modified).unwrap_or(Duration::ZERO) >= older_than
+        });
+        let too_large = max_size.is_some_and(|max_size| kept_bytes > max_size);
+        if (older_than.is_some() || max_size.is_some()) && !too_old && !too_large {
+            continue;
+        }
+        std::fs::remove_file(&path)?;
+        removed.files += 1;
+        removed.bytes += len;
+        kept_bytes -= len;
+    }
+    for dir in WalkDir::new(cache_dir()).contents_first(true).min_depth(1) {
+        let dir = dir?;
+        // Only succeeds for directories that were emptied.
+        if dir.file_type().is_dir() && std::fs::remove_dir(dir.path()).is_ok() {
+            log::debug!("Removed empty directory {}", dir.path().display());
+        }
+    }
+    Ok(removed)
+}
+
+/// Parse a size like `500M`, `2G` or `2GiB`, in binary units, or a number of bytes.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(split);
+    let amount = amount
+        .parse::<u64>()
+        .map_err(|e| format!("invalid amount in '{}': {}", s, e))?;
+    let unit_bytes: u64 = match unit.trim_end_matches("iB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("unknown unit '{}', use K, M, G or T", unit)),
+    };
+    amount
+        .checked_mul(unit_bytes)
+        .ok_or_else(|| format!("size '{}' is too large", s))
+}
+
+/// Parse a duration like `30d`, `12h`, `45m`, `90s` or `2w`.
+pub fn parse_age(s: &str) -> Result<Duration, String> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in '{}', use s, m, h, d or w", s))?;
+    let (amount, unit) = s.split_at(split);
+    let amount = amount
+        .parse::<u64>()
+        .map_err(|e| format!("invalid amount in '{}': {}", s, e))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}', use s, m, h, d or w", unit)),
+    };
+    amount
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("age '{}' is too large", s))
+}
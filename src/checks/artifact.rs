@@ -0,0 +1,342 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use digest::Digest;
+use futures::StreamExt;
+use thiserror::Error;
+use tokio::io::AsyncReadExt;
+use zip::ZipArchive;
+
+use crate::config::global::furse;
+use crate::config::mods::EnvRequirement;
+use crate::mod_site::{hex_to_hash_output, ModFileInfo, ModInfo, Modrinth, ModrinthHash, SideInfo};
+use crate::output::build_metadata::BUILD_METADATA_FILE;
+use crate::output::curseforge_manifest::CurseForgeManifest;
+use crate::output::mod_download::cached_mod_download;
+use crate::output::modrinth_manifest::{ModFile, ModrinthManifest, EMBEDDED_MANIFEST};
+use crate::output::{is_safe_path, LIT_CLIENT_OVERRIDES, LIT_OVERRIDES, LIT_SERVER_OVERRIDES};
+use crate::uwu_colors::{ErrStyle, FILE_STYLE, SUCCESS_STYLE};
+
+const MODRINTH_MANIFEST: &str = "modrinth.index.json";
+const CURSEFORGE_MANIFEST: &str = "manifest.json";
+/// The optional list of mods that CurseForge adds to its packs.
+const CURSEFORGE_MODLIST: &str = "modlist.html";
+
+/// Hosts Modrinth accepts downloads from in a pack.
+const MODRINTH_DOWNLOAD_HOSTS: [&str; 4] = [
+    "cdn.modrinth.com",
+    "github.com",
+    "raw.githubusercontent.com",
+    "gitlab.com",
+];
+
+#[derive(Debug, Error)]
+pub enum CheckArtifactError {
+    #[error("I/O error on {0}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("ZIP error on {0}: {1}")]
+    Zip(String, #[source] zip::result::ZipError),
+    #[error("{0} has neither a {MODRINTH_MANIFEST} nor a {CURSEFORGE_MANIFEST}")]
+    UnknownFormat(String),
+}
+
+/// The problems found in an artifact.
+#[derive(Default)]
+struct Problems(Vec<String>);
+
+impl Problems {
+    fn add(&mut self, problem: impl Into<String>) {
+        let problem = problem.into();
+        log::error!("{}", problem);
+        self.0.push(problem);
+    }
+}
+
+/// Validate an existing Modrinth pack or CurseForge ZIP against its format. Returns the number of
+/// problems found, which are logged as they're found.
+pub async fn check_artifact(path: &Path) -> Result<usize, CheckArtifactError> {
+    let name = path.display().to_string();
+    let file = File::open(path).map_err(|e| CheckArtifactError::Io(name.clone(), e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| CheckArtifactError::Zip(name.clone(), e))?;
+    let entries = zip.file_names().map(str::to_string).collect::<Vec<_>>();
+
+    let mut problems = Problems::default();
+    if entries.iter().any(|e| e == MODRINTH_MANIFEST) {
+        log::info!("Checking Modrinth pack '{}'...", name.errstyle(FILE_STYLE));
+        let manifest = read_entry(&mut zip, &name, MODRINTH_MANIFEST)?;
+        check_modrinth_pack(&manifest, &entries, &mut problems).await;
+    } else if entries.iter().any(|e| e == CURSEFORGE_MANIFEST) {
+        log::info!("Checking CurseForge ZIP '{}'...", name.errstyle(FILE_STYLE));
+        let manifest = read_entry(&mut zip, &name, CURSEFORGE_MANIFEST)?;
+        check_curseforge_zip(&manifest, &entries, &mut problems).await;
+    } else {
+        return Err(CheckArtifactError::UnknownFormat(name));
+    }
+
+    if problems.0.is_empty() {
+        log::info!("{}", "The artifact is valid.".errstyle(SUCCESS_STYLE));
+    }
+    Ok(problems.0.len())
+}
+
+fn read_entry(
+    zip: &mut ZipArchive<File>,
+    name: &str,
+    entry: &str,
+) -> Result<Vec<u8>, CheckArtifactError> {
+    let entry_name = format!("{}!{}", name, entry);
+    let mut content = Vec::new();
+    zip.by_name(entry)
+        .map_err(|e| CheckArtifactError::Zip(entry_name.clone(), e))?
+        .read_to_end(&mut content)
+        .map_err(|e| CheckArtifactError::Io(entry_name, e))?;
+    Ok(content)
+}
+
+/// Check that every entry is a known file in the root, or in one of the override directories.
+fn check_layout(
+    entries: &[String],
+    root_files: &[&str],
+    overrides: &[&str],
+    problems: &mut Problems,
+) {
+    for entry in entries {
+        if !is_safe_path(entry) {
+            problems.add(format!("Entry '{}' escapes the pack directory", entry));
+        } else if !root_files.contains(&entry.as_str())
+            && !overrides
+                .iter()
+                .any(|o| entry.starts_with(&format!("{}/", o)))
+        {
+            problems.add(format!(
+                "Entry '{}' is neither a manifest nor in an override directory ({})",
+                entry,
+                overrides.join(", ")
+            ));
+        }
+    }
+}
+
+async fn check_modrinth_pack(manifest: &[u8], entries: &[String], problems: &mut Problems) {
+    check_layout(
+        entries,
+        &[MODRINTH_MANIFEST, BUILD_METADATA_FILE, EMBEDDED_MANIFEST],
+        &[LIT_OVERRIDES, LIT_CLIENT_OVERRIDES, LIT_SERVER_OVERRIDES],
+        problems,
+    );
+    let manifest = match serde_json::from_slice::<ModrinthManifest>(manifest) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            problems.add(format!("{} is invalid: {}", MODRINTH_MANIFEST, e));
+            return;
+        }
+    };
+    if manifest.format_version != 1 {
+        problems.add(format!(
+            "Unsupported format version {}, expected 1",
+            manifest.format_version
+        ));
+    }
+    if manifest.dependencies.minecraft.is_empty() {
+        problems.add("The Minecraft version is empty");
+    }
+
+    let mut paths = HashSet::new();
+    let mut downloads = Vec::new();
+    for file in &manifest.files {
+        if !is_safe_path(&file.path) {
+            problems.add(format!("File '{}' escapes the pack directory", file.path));
+        }
+        if !paths.insert(&file.path) {
+            problems.add(format!("File '{}' is listed more than once", file.path));
+        }
+        if file.downloads.is_empty() {
+            problems.add(format!("File '{}' has no downloads", file.path));
+        }
+        for url in &file.downloads {
+            match reqwest::Url::parse(url) {
+                Ok(parsed) if parsed.scheme() != "https" => {
+                    problems.add(format!("Download {} of '{}' isn't HTTPS", url, file.path))
+                }
+                Ok(parsed) => {
+                    if !parsed
+                        .host_str()
+                        .is_some_and(|h| MODRINTH_DOWNLOAD_HOSTS.contains(&h))
+                    {
+                        log::warn!(
+                            "Download {} of '{}' isn't from a host Modrinth allows, so the pack \
+                            can't be uploaded there",
+                            url,
+                            file.path
+                        );
+                    }
+                }
+                Err(e) => problems.add(format!(
+                    "Download {} of '{}' is not a URL: {}",
+                    url, file.path, e
+                )),
+            }
+        }
+        match (
+            hex_to_hash_output::<sha1::Sha1>(&file.hashes.sha1),
+            hex_to_hash_output::<sha2::Sha512>(&file.hashes.sha512),
+        ) {
+            (Some(sha1), Some(sha512)) => {
+                if !file.downloads.is_empty() {
+                    downloads.push((
+                        file,
+                        ModrinthHash {
+                            sha1,
+                            sha512,
+                            sha256: None,
+                        },
+                    ));
+                }
+            }
+            _ => problems.add(format!("File '{}' has invalid hashes", file.path)),
+        }
+    }
+
+    log::info!("Checking the hashes of {} files...", downloads.len());
+    let results = futures::stream::iter(downloads)
+        .map(|(file, hash)| async move { (file, check_download(file, hash).await) })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+    for (file, result) in results {
+        if let Err(e) = result {
+            problems.add(format!("File '{}': {}", file.path, e));
+        }
+    }
+}
+
+/// Download a file listed in a Modrinth pack, checking its hashes and size.
+async fn check_download(file: &ModFile, hash: ModrinthHash) -> Result<(), String> {
+    let sha1 = hash.sha1;
+    let info: ModFileInfo<String, _> = ModFileInfo {
+        project_info: ModInfo {
+            name: file.path.clone(),
+            slug: String::new(),
+            distribution_allowed: true,
+            license: None,
+            authors: Vec::new(),
+            team: None,
+            url: None,
+            summary: None,
+            categories: Vec::new(),
+            side_info: SideInfo {
+                client: EnvRequirement::Unknown,
+                server: EnvRequirement::Unknown,
+            },
+        },
+        filename: file.path.rsplit('/').next().unwrap_or_default().to_string(),
+        url: file.downloads[0].clone(),
+        file_length: file.file_size,
+        minecraft_versions: Vec::new(),
+        dependencies: Vec::new(),
+        hash,
+    };
+    let mut content = Vec::new();
+    cached_mod_download(Modrinth, &info)
+        .await
+        .map_err(|e| e.to_string())?
+        .read_to_end(&mut content)
+        .await
+        .map_err(|e| e.to_string())?;
+    if sha1::Sha1::digest(&content) != sha1 {
+        return Err("the content doesn't match the SHA-1 hash".to_string());
+    }
+    Ok(())
+}
+
+async fn check_curseforge_zip(manifest: &[u8], entries: &[String], problems: &mut Problems) {
+    let manifest = match serde_json::from_slice::<CurseForgeManifest>(manifest) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            problems.add(format!("{} is invalid: {}", CURSEFORGE_MANIFEST, e));
+            return;
+        }
+    };
+    if !is_safe_path(&manifest.overrides) {
+        problems.add(format!(
+            "The overrides directory '{}' escapes the pack directory",
+            manifest.overrides
+        ));
+    }
+    check_layout(
+        entries,
+        &[CURSEFORGE_MANIFEST, CURSEFORGE_MODLIST, BUILD_METADATA_FILE],
+        &[&manifest.overrides],
+        problems,
+    );
+    if manifest.manifest_version != 1 {
+        problems.add(format!(
+            "Unsupported manifest version {}, expected 1",
+            manifest.manifest_version
+        ));
+    }
+    if manifest.minecraft.version.is_empty() {
+        problems.add("The Minecraft version is empty");
+    }
+    let primary_loaders = manifest
+        .minecraft
+        .mod_loaders
+        .iter()
+        .filter(|l| l.primary)
+        .count();
+    if primary_loaders != 1 {
+        problems.add(format!(
+            "Expected exactly one primary mod loader, found {}",
+            primary_loaders
+        ));
+    }
+
+    let mut projects = HashSet::new();
+    for file in &manifest.files {
+        if file.project_id <= 0 || file.file_id <= 0 {
+            problems.add(format!(
+                "File {} of project {} has an invalid ID",
+                file.file_id, file.project_id
+            ));
+        }
+        if !projects.insert(file.project_id) {
+            problems.add(format!(
+                "Project {} is listed more than once",
+                file.project_id
+            ));
+        }
+    }
+
+    // The manifest has no hashes, so the best we can do is check that the files exist.
+    let furse = match furse() {
+        Ok(furse) => furse,
+        Err(e) => {
+            log::warn!("Skipping the check that the listed files exist: {}", e);
+            return;
+        }
+    };
+    log::info!(
+        "Checking that the {} listed files exist...",
+        manifest.files.len()
+    );
+    let results = futures::stream::iter(&manifest.files)
+        .map(|file| async move {
+            (
+                file,
+                furse.get_mod_file(file.project_id, file.file_id).await,
+            )
+        })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+    for (file, result) in results {
+        if let Err(e) = result {
+            problems.add(format!(
+                "File {} of project {} can't be loaded: {}",
+                file.file_id, file.project_id, e
+            ));
+        }
+    }
+}
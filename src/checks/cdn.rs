@@ -0,0 +1,124 @@
+//! Checking that the download URLs of the resolved mods still work, as files are sometimes deleted
+//! from their sites after a pack was verified.
+
+use std::collections::HashMap;
+
+use futures::StreamExt;
+use itertools::Itertools;
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::{Method, StatusCode};
+use thiserror::Error;
+
+use crate::checks::verify_mods::{VerifiedMod, VerifiedModContainer};
+use crate::config::global::{HTTP_CLIENT, RETRY_POLICY};
+use crate::config::pack::PackConfig;
+use crate::mod_site::{CurseForge, ModSite, Modrinth};
+use crate::units;
+use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, SITE_NAME_STYLE, SUCCESS_STYLE};
+
+/// How many URLs are checked at once.
+const CONCURRENCY: usize = 8;
+
+#[derive(Debug, Error)]
+enum LinkProblem {
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("the server responded with {0}")]
+    Status(StatusCode),
+    #[error("the file has {} instead of {}", units::size(*.actual), units::size(*.expected))]
+    Size { expected: u64, actual: u64 },
+}
+
+/// A download URL of a mod, with the size its site reported for the file.
+struct Link<'a> {
+    cfg_id: &'a str,
+    url: &'a str,
+    size: u64,
+}
+
+/// Send a HEAD request to the download URL of every file of the pack, including server files,
+/// and log the ones that are gone or have a different size than their site reported. Returns the
+/// number of such URLs.
+pub(crate) async fn check_cdn(pack: &PackConfig<VerifiedModContainer>) -> usize {
+    let mut problems = check_site(CurseForge, &pack.mods.curseforge).await;
+    problems += check_site(Modrinth, &pack.mods.modrinth).await;
+    if problems == 0 {
+        log::info!("{}", "All download URLs work.".errstyle(SUCCESS_STYLE));
+    }
+    problems
+}
+
+async fn check_site<S: ModSite>(site: S, mods: &HashMap<String, VerifiedMod<S>>) -> usize {
+    let links = mods
+        .iter()
+        .sorted_by_key(|(k, _)| k.as_str())
+        .flat_map(|(k, m)| {
+            [Some(&m.info), m.server_info.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(move |info| Link {
+                    cfg_id: k,
+                    url: &info.url,
+                    size: info.file_length,
+                })
+        })
+        .unique_by(|l| l.url)
+        .collect::<Vec<_>>();
+    if links.is_empty() {
+        return 0;
+    }
+    log::info!(
+        "[{}] Checking {}...",
+        S::NAME.errstyle(SITE_NAME_STYLE),
+        units::count(links.len() as u64, "download URL")
+    );
+    let results = futures::stream::iter(links)
+        .map(|link| async move {
+            let result = check_link(site, &link).await;
+            (link, result)
+        })
+        .buffered(CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+    let mut problems = 0;
+    for (link, result) in results {
+        if let Err(e) = result {
+            problems += 1;
+            log::error!(
+                "[{}] {}: {}: {}",
+                S::NAME.errstyle(SITE_NAME_STYLE),
+                link.cfg_id.errstyle(CONFIG_VAL_STYLE),
+                link.url,
+                e
+            );
+        }
+    }
+    problems
+}
+
+async fn check_link<S: ModSite>(site: S, link: &Link<'_>) -> Result<(), LinkProblem> {
+    let response = RETRY_POLICY
+        .run(|| async {
+            // Build the request like a download, so it's authenticated the same way.
+            let mut request = site.download_request(link.url).build()?;
+            *request.method_mut() = Method::HEAD;
+            HTTP_CLIENT.execute(request).await
+        })
+        .await?;
+    if !response.status().is_success() {
+        return Err(LinkProblem::Status(response.status()));
+    }
+    // The body of a HEAD response is empty, so the length is only in the header.
+    let length = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    match length {
+        Some(actual) if actual != link.size => Err(LinkProblem::Size {
+            expected: link.size,
+            actual,
+        }),
+        _ => Ok(()),
+    }
+}
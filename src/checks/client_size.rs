@@ -0,0 +1,94 @@
+use itertools::Itertools;
+
+use crate::checks::verify_mods::VerifiedModContainer;
+use crate::config::mods::KnownEnvRequirement;
+use crate::config::pack::PackConfig;
+use crate::units;
+use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, SITE_NAME_STYLE};
+
+/// A file a client ends up with when installing the Modrinth pack.
+struct ClientFile<'a> {
+    site: &'static str,
+    cfg_id: &'a str,
+    size: u64,
+    optional: bool,
+}
+
+/// The bytes a client gets when installing the Modrinth pack.
+#[derive(Debug, Default)]
+struct ClientSize {
+    /// Files the launcher downloads from their sites, listed by hash in the manifest.
+    downloads: u64,
+    /// Files of optional mods the launcher downloads if the user picks them.
+    optional_downloads: u64,
+    /// Mods embedded in the overrides of the pack, which come with the pack itself.
+    embedded: u64,
+}
+
+/// Log the size of every mod a client downloads when installing the Modrinth pack, largest first,
+/// and the totals. Modrinth mods are listed in the manifest and downloaded by the launcher, while
+/// CurseForge mods are embedded in the overrides, with optional ones included.
+pub(crate) fn log_client_size(pack: &PackConfig<VerifiedModContainer>) {
+    let downloads = pack
+        .mods
+        .modrinth
+        .iter()
+        .filter(|(_, m)| m.env_requirements.client != KnownEnvRequirement::Unsupported)
+        .map(|(k, m)| ClientFile {
+            site: "modrinth",
+            cfg_id: k,
+            size: m.info.file_length,
+            optional: m.env_requirements.client == KnownEnvRequirement::Optional,
+        })
+        .sorted_by(|a, b| (b.size, a.cfg_id).cmp(&(a.size, b.cfg_id)))
+        .collect::<Vec<_>>();
+    let embedded = pack
+        .mods
+        .curseforge
+        .iter()
+        .filter(|(_, m)| m.env_requirements.client.is_needed(true))
+        .map(|(k, m)| ClientFile {
+            site: "curseforge",
+            cfg_id: k,
+            size: m.info.file_length,
+            optional: false,
+        })
+        .sorted_by(|a, b| (b.size, a.cfg_id).cmp(&(a.size, b.cfg_id)))
+        .collect::<Vec<_>>();
+
+    let mut size = ClientSize::default();
+    if !downloads.is_empty() {
+        log::info!("Downloaded by the launcher:");
+    }
+    for file in &downloads {
+        log_file(file);
+        if file.optional {
+            size.optional_downloads += file.size;
+        } else {
+            size.downloads += file.size;
+        }
+    }
+    if !embedded.is_empty() {
+        log::info!("Embedded in the pack:");
+    }
+    for file in &embedded {
+        log_file(file);
+        size.embedded += file.size;
+    }
+    log::info!(
+        "Total: {} downloaded by the launcher, {} more for optional mods, {} embedded in the pack.",
+        units::size(size.downloads),
+        units::size(size.optional_downloads),
+        units::size(size.embedded)
+    );
+}
+
+fn log_file(file: &ClientFile) {
+    log::info!(
+        "  [{}] {}: {}{}",
+        file.site.errstyle(SITE_NAME_STYLE),
+        file.cfg_id.errstyle(CONFIG_VAL_STYLE),
+        units::size(file.size),
+        if file.optional { " (optional)" } else { "" }
+    );
+}
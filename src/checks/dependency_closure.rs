@@ -0,0 +1,331 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use futures::StreamExt;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::checks::verify_mods::submit_load;
+use crate::config::mods::{ConfigMod, ConfigModContainer};
+use crate::config::pack::PackConfig;
+use crate::mod_site::{
+    CurseForge, DependencyId, ModDependency, ModDependencyKind, ModIdValue, ModInfo,
+    ModLoadingError, ModSite, Modrinth,
+};
+
+#[derive(Debug, Error)]
+pub enum DependencyClosureError {
+    #[error("Error loading mod {0}: {1}")]
+    Loading(String, #[source] ModLoadingError),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The configured mods of a pack and everything they depend on, printed by `netherfire deps`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyClosure {
+    pub minecraft_version: String,
+    pub mod_loader: String,
+    /// Configured mods first, by site and config key, then the other mods by site and name.
+    pub mods: Vec<ClosureMod>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClosureMod {
+    pub site: &'static str,
+    /// The key in the config, if the mod is configured.
+    pub config_key: Option<String>,
+    /// Unknown for dependencies on a specific version, which name only the version.
+    pub project_id: Option<String>,
+    /// The configured version, or the version `add-mods` would pick for a dependency that isn't
+    /// configured. Unknown if there's no version for the pack.
+    pub version_id: Option<String>,
+    pub name: String,
+    pub slug: String,
+    pub dependencies: Vec<ClosureDependency>,
+    /// The mods that depend on this one.
+    pub dependents: Vec<ClosureDependency>,
+}
+
+/// A dependency between two mods of the same site.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClosureDependency {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    /// `required` or `optional`.
+    pub kind: &'static str,
+    /// Whether the depending mod lists it in `ignored_deps`. Ignored dependencies aren't
+    /// followed.
+    pub ignored: bool,
+}
+
+/// A mod in the closure, by the project it's from, or by version for dependencies on a specific
+/// version that isn't configured.
+struct Node<K> {
+    config_key: Option<String>,
+    version_id: Option<K>,
+    info: ModInfo,
+    dependencies: Vec<(DependencyId<K>, ModDependencyKind, bool)>,
+}
+
+/// Resolve the dependencies of the configured mods, following the dependencies of mods that
+/// aren't configured with the versions `add-mods` would pick for them. Dependencies on specific
+/// versions that aren't configured aren't followed further.
+pub(crate) async fn dependency_closure(
+    pack_config: &PackConfig<ConfigModContainer>,
+) -> Result<DependencyClosure, DependencyClosureError> {
+    log::info!("Resolving the dependencies of the configured mods...");
+    let mut mods = site_closure(pack_config, &pack_config.mods.curseforge, CurseForge).await?;
+    mods.extend(site_closure(pack_config, &pack_config.mods.modrinth, Modrinth).await?);
+    Ok(DependencyClosure {
+        minecraft_version: pack_config.minecraft_version.clone(),
+        mod_loader: pack_config.mod_loader.id.to_string(),
+        mods,
+    })
+}
+
+async fn site_closure<K, S>(
+    pack_config: &PackConfig<ConfigModContainer>,
+    mods: &HashMap<String, ConfigMod<K>>,
+    site: S,
+) -> Result<Vec<ClosureMod>, DependencyClosureError>
+where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+{
+    let loads = mods
+        .iter()
+        .map(|(k, m)| (k, m, submit_load(m.source.clone(), m.file.clone(), site)))
+        .collect::<Vec<_>>();
+    let mut nodes = HashMap::<DependencyId<K>, Node<K>>::new();
+    // Dependencies on a configured version are dependencies on the configured mod.
+    let configured_versions = mods
+        .values()
+        .map(|m| (m.source.version_id.clone(), m.source.project_id.clone()))
+        .collect::<HashMap<_, _>>();
+    for (cfg_id, m, load) in loads {
+        let (_, info) = load
+            .await
+            .expect("tokio failure")
+            .map_err(|e| DependencyClosureError::Loading(cfg_id.clone(), e))?;
+        nodes.insert(
+            DependencyId::Project(m.source.project_id.clone()),
+            Node {
+                config_key: Some(cfg_id.clone()),
+                version_id: Some(m.source.version_id.clone()),
+                info: info.project_info,
+                dependencies: node_dependencies(
+                    info.dependencies,
+                    &m.ignored_deps,
+                    &configured_versions,
+                ),
+            },
+        );
+    }
+
+    loop {
+        let unresolved = nodes
+            .values()
+            .flat_map(|n| &n.dependencies)
+            .filter(|(id, _, ignored)| !ignored && !nodes.contains_key(id))
+            .map(|(id, ..)| id.clone())
+            .collect::<HashSet<_>>();
+        if unresolved.is_empty() {
+            break;
+        }
+        let configured_versions = &configured_versions;
+        let resolved = futures::stream::iter(unresolved)
+            .map(|id| async move {
+                resolve_dependency(pack_config, id.clone(), configured_versions, site)
+                    .await
+                    .map(|node| (id.clone(), node))
+                    .map_err(|e| DependencyClosureError::Loading(format!("{:?}", id), e))
+            })
+            .buffer_unordered(5)
+            .collect::<Vec<_>>()
+            .await;
+        for result in resolved {
+            let (id, node) = result?;
+            nodes.insert(id, node);
+        }
+    }
+
+    let mut dependents = HashMap::<&DependencyId<K>, Vec<ClosureDependency>>::new();
+    for (id, node) in &nodes {
+        let DependencyId::Project(project_id) = id else {
+            continue;
+        };
+        for (dep, kind, ignored) in &node.dependencies {
+            dependents.entry(dep).or_default().push(ClosureDependency {
+                project_id: Some(project_id.to_string()),
+                version_id: None,
+                kind: kind_name(*kind),
+                ignored: *ignored,
+            });
+        }
+    }
+
+    let mut sorted = BTreeMap::new();
+    for (id, node) in &nodes {
+        let mut node_dependents = dependents.remove(id).unwrap_or_default();
+        node_dependents.sort_by(|a, b| a.project_id.cmp(&b.project_id));
+        let closure_mod = ClosureMod {
+            site: S::CONFIG_KEY,
+            config_key: node.config_key.clone(),
+            project_id: match id {
+                DependencyId::Project(project_id) => Some(project_id.to_string()),
+                DependencyId::Version(_) => None,
+            },
+            version_id: node.version_id.as_ref().map(|v| v.to_string()),
+            name: node.info.name.clone(),
+            slug: node.info.slug.clone(),
+            dependencies: node
+                .dependencies
+                .iter()
+                .map(|(dep, kind, ignored)| closure_dependency(dep, *kind, *ignored))
+                .collect(),
+            dependents: node_dependents,
+        };
+        let order = (
+            node.config_key.is_none(),
+            node.config_key
+                .clone()
+                .unwrap_or_else(|| node.info.name.clone()),
+            format!("{:?}", id),
+        );
+        sorted.insert(order, closure_mod);
+    }
+    Ok(sorted.into_values().collect())
+}
+
+/// The required and optional dependencies of a mod, with whether they're ignored.
+fn node_dependencies<K: ModIdValue>(
+    dependencies: Vec<ModDependency<K>>,
+    ignored_deps: &[DependencyId<K>],
+    configured_versions: &HashMap<K, K>,
+) -> Vec<(DependencyId<K>, ModDependencyKind, bool)> {
+    dependencies
+        .into_iter()
+        .filter(|d| {
+            matches!(
+                d.kind,
+                ModDependencyKind::Required | ModDependencyKind::Optional
+            )
+        })
+        .map(|d| {
+            let ignored = ignored_deps.contains(&d.id);
+            (normalize(d.id, configured_versions), d.kind, ignored)
+        })
+        .collect()
+}
+
+fn normalize<K: ModIdValue>(
+    id: DependencyId<K>,
+    configured_versions: &HashMap<K, K>,
+) -> DependencyId<K> {
+    match id {
+        DependencyId::Version(version_id) => match configured_versions.get(&version_id) {
+            Some(project_id) => DependencyId::Project(project_id.clone()),
+            None => DependencyId::Version(version_id),
+        },
+        project => project,
+    }
+}
+
+/// Load a dependency that isn't configured.
+async fn resolve_dependency<K, S>(
+    pack_config: &PackConfig<ConfigModContainer>,
+    id: DependencyId<K>,
+    configured_versions: &HashMap<K, K>,
+    site: S,
+) -> Result<Node<K>, ModLoadingError>
+where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+{
+    match id {
+        DependencyId::Project(project_id) => {
+            let version = site
+                .get_latest_version_for_pack(
+                    project_id.clone(),
+                    &pack_config.minecraft_version,
+                    &pack_config.mod_loader.id,
+                    false,
+                    pack_config.prefer_featured,
+                    &pack_config.version_types,
+                )
+                .await?;
+            match version {
+                Some(version) => {
+                    let info = site.load_file(version.clone()).await?;
+                    Ok(Node {
+                        config_key: None,
+                        version_id: Some(version.version_id),
+                        info: info.project_info,
+                        dependencies: node_dependencies(
+                            info.dependencies,
+                            &[],
+                            configured_versions,
+                        ),
+                    })
+                }
+                None => Ok(Node {
+                    config_key: None,
+                    version_id: None,
+                    info: site.load_metadata(project_id).await?,
+                    dependencies: Vec::new(),
+                }),
+            }
+        }
+        DependencyId::Version(version_id) => Ok(Node {
+            config_key: None,
+            info: site
+                .load_metadata_by_version(version_id.clone())
+                .await
+                .expect(
+                "sites that provide only a version in dependencies must allow lookup by version",
+            )?,
+            version_id: Some(version_id),
+            dependencies: Vec::new(),
+        }),
+    }
+}
+
+fn closure_dependency<K: ModIdValue>(
+    id: &DependencyId<K>,
+    kind: ModDependencyKind,
+    ignored: bool,
+) -> ClosureDependency {
+    let (project_id, version_id) = match id {
+        DependencyId::Project(project_id) => (Some(project_id.to_string()), None),
+        DependencyId::Version(version_id) => (None, Some(version_id.to_string())),
+    };
+    ClosureDependency {
+        project_id,
+        version_id,
+        kind: kind_name(kind),
+        ignored,
+    }
+}
+
+fn kind_name(kind: ModDependencyKind) -> &'static str {
+    match kind {
+        ModDependencyKind::Required => "required",
+        ModDependencyKind::Optional => "optional",
+        ModDependencyKind::Embedded => "embedded",
+        ModDependencyKind::Other => "other",
+    }
+}
+
+/// Print the dependency closure of a pack to stdout as JSON.
+pub(crate) async fn print_dependency_closure(
+    pack_config: &PackConfig<ConfigModContainer>,
+) -> Result<(), DependencyClosureError> {
+    let closure = dependency_closure(pack_config).await?;
+    println!("{}", serde_json::to_string_pretty(&closure)?);
+    Ok(())
+}
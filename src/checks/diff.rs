@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use digest::Digest;
+use thiserror::Error;
+use zip::ZipArchive;
+
+use crate::config::pack::{OverrideSide, Symlinks};
+use crate::config::source::{load_pack_config, ConfigLoadError};
+use crate::mod_site::{CurseForge, ModSite, Modrinth};
+use crate::output::build_metadata::{BuildMetadata, BuiltMod, BUILD_METADATA_FILE};
+use crate::output::exclusions::{Exclusions, IGNORE_FILE};
+use crate::output::modrinth_manifest::EMBEDDED_MANIFEST;
+use crate::output::{walk_overrides, LIT_CLIENT_OVERRIDES, LIT_OVERRIDES, LIT_SERVER_OVERRIDES};
+use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, FILE_STYLE, SITE_NAME_STYLE, SUCCESS_STYLE};
+
+/// Names of the manifests in the root of the archives, which aren't overrides.
+const MANIFEST_FILES: [&str; 3] = ["manifest.json", "modrinth.index.json", EMBEDDED_MANIFEST];
+
+#[derive(Debug, Error)]
+pub enum DiffError {
+    #[error("I/O error on {0}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("Walk error: {0}")]
+    Walk(#[from] walkdir::Error),
+    #[error("Error loading {IGNORE_FILE}: {0}")]
+    Ignore(#[from] ignore::Error),
+    #[error("ZIP error on {0}: {1}")]
+    Zip(String, #[source] zip::result::ZipError),
+    #[error("Error loading pack: {0}")]
+    PackConfigLoad(#[source] Box<ConfigLoadError>),
+    #[error(
+        "{0} has no {BUILD_METADATA_FILE}, only archives generated by netherfire can be compared"
+    )]
+    NoMetadata(String),
+    #[error("{0} doesn't record its mods, generate it again to compare it")]
+    NoMods(String),
+}
+
+/// The mods and overrides of a pack source or generated archive.
+struct PackState {
+    /// Mods by site and config key.
+    mods: BTreeMap<(String, String), BuiltMod>,
+    /// SHA-256 hashes of the override files, by their path in the pack, e.g.
+    /// `overrides/config/foo.toml`.
+    overrides: BTreeMap<String, String>,
+}
+
+impl PackState {
+    /// Load the state of a pack source directory, or of an archive generated from one.
+    fn load(path: &Path) -> Result<Self, DiffError> {
+        if path.is_dir() {
+            Self::load_source(path)
+        } else {
+            Self::load_archive(path)
+        }
+    }
+
+    fn load_source(source_dir: &Path) -> Result<Self, DiffError> {
+        let (pack_config, source) =
+            load_pack_config(source_dir).map_err(|e| DiffError::PackConfigLoad(Box::new(e)))?;
+        let curseforge = pack_config
+            .mods
+            .curseforge
+            .iter()
+            .map(|(k, m)| BuiltMod::new(CurseForge::CONFIG_KEY, k, &m.source));
+        let modrinth = pack_config
+            .mods
+            .modrinth
+            .iter()
+            .map(|(k, m)| BuiltMod::new(Modrinth::CONFIG_KEY, k, &m.source));
+        let mods = curseforge
+            .chain(modrinth)
+            .map(|m| ((m.site.clone(), m.key.clone()), m))
+            .collect();
+
+        // Later directories replace the files of the packs they extend.
+        let mut overrides = BTreeMap::new();
+        for kind in [LIT_OVERRIDES, LIT_CLIENT_OVERRIDES, LIT_SERVER_OVERRIDES] {
+            for dir in source.join_all(pack_config.override_dirs.of_kind(kind)) {
+                if !dir.exists() {
+                    continue;
+                }
+                let exclusions = Exclusions::load(&dir, &pack_config.exclude)?;
+                for entry in
+                    walk_overrides(&dir, pack_config.symlinks != Symlinks::Error, &exclusions)
+                {
+                    let entry = entry?;
+                    if !entry.file_type().is_file() {
+                        continue;
+                    }
+                    let relative = entry
+                        .path()
+                        .strip_prefix(&dir)
+                        .expect("walked path is under its root")
+                        .to_string_lossy()
+                        .replace('\\', "/");
+                    let content = std::fs::read(entry.path())
+                        .map_err(|e| DiffError::Io(entry.path().display().to_string(), e))?;
+                    // Files are keyed by where they'd be in a Modrinth pack.
+                    let dest_kind =
+                        match OverrideSide::of(kind, &relative, &pack_config.override_sides) {
+                            OverrideSide::Both => LIT_OVERRIDES,
+                            OverrideSide::Client => LIT_CLIENT_OVERRIDES,
+                            OverrideSide::Server => LIT_SERVER_OVERRIDES,
+                        };
+                    overrides.insert(
+                        format!("{}/{}", dest_kind, relative),
+                        format!("{:x}", sha2::Sha256::digest(content)),
+                    );
+                }
+            }
+        }
+        Ok(Self { mods, overrides })
+    }
+
+    fn load_archive(path: &Path) -> Result<Self, DiffError> {
+        let name = path.display().to_string();
+        let file = File::open(path).map_err(|e| DiffError::Io(name.clone(), e))?;
+        let mut zip = ZipArchive::new(file).map_err(|e| DiffError::Zip(name.clone(), e))?;
+        let metadata: BuildMetadata = zip
+            .by_name(BUILD_METADATA_FILE)
+            .ok()
+            .and_then(|f| serde_json::from_reader(f).ok())
+            .ok_or_else(|| DiffError::NoMetadata(name.clone()))?;
+        let (Some(built_mods), Some(mod_files)) = (metadata.mods, metadata.mod_files) else {
+            return Err(DiffError::NoMods(name));
+        };
+        let mods = built_mods
+            .into_iter()
+            .map(|m| ((m.site.clone(), m.key.clone()), m))
+            .collect();
+
+        let mut overrides = BTreeMap::new();
+        for i in 0..zip.len() {
+            let mut entry = zip
+                .by_index(i)
+                .map_err(|e| DiffError::Zip(name.clone(), e))?;
+            let entry_name = entry.name().to_string();
+            if entry.is_dir()
+                || entry_name == BUILD_METADATA_FILE
+                || MANIFEST_FILES.contains(&entry_name.as_str())
+                || mod_files.contains(&entry_name)
+            {
+                continue;
+            }
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .map_err(|e| DiffError::Io(format!("{}!{}", name, entry_name), e))?;
+            overrides.insert(entry_name, format!("{:x}", sha2::Sha256::digest(content)));
+        }
+        Ok(Self { mods, overrides })
+    }
+}
+
+/// Compare two pack states, each either a pack source directory or a generated archive, and log
+/// the added, removed and changed mods and overrides. Returns the number of differences.
+pub fn diff_packs(old: &Path, new: &Path) -> Result<usize, DiffError> {
+    let old_state = PackState::load(old)?;
+    let new_state = PackState::load(new)?;
+    log::info!(
+        "Comparing '{}' with '{}'...",
+        old.display().errstyle(FILE_STYLE),
+        new.display().errstyle(FILE_STYLE)
+    );
+
+    let mut differences = 0;
+    let keys = old_state
+        .mods
+        .keys()
+        .chain(new_state.mods.keys())
+        .collect::<BTreeSet<_>>();
+    for id @ (site, key) in keys {
+        let (old_mod, new_mod) = (old_state.mods.get(id), new_state.mods.get(id));
+        if old_mod == new_mod {
+            continue;
+        }
+        let site = site.errstyle(SITE_NAME_STYLE);
+        let key = key.errstyle(CONFIG_VAL_STYLE);
+        match (old_mod, new_mod) {
+            (None, Some(m)) => log::info!(
+                "[{}] Added mod {} (project {}, version {})",
+                site,
+                key,
+                m.project_id,
+                m.version_id
+            ),
+            (Some(m), None) => log::info!(
+                "[{}] Removed mod {} (project {}, version {})",
+                site,
+                key,
+                m.project_id,
+                m.version_id
+            ),
+            (Some(o), Some(n)) if o.project_id != n.project_id => log::info!(
+                "[{}] Changed mod {}: project {} -> {}, version {} -> {}",
+                site,
+                key,
+                o.project_id,
+                n.project_id,
+                o.version_id,
+                n.version_id
+            ),
+            (Some(o), Some(n)) => log::info!(
+                "[{}] Changed mod {}: version {} -> {}",
+                site,
+                key,
+                o.version_id,
+                n.version_id
+            ),
+            (None, None) => unreachable!("the key is in one of the states"),
+        }
+        differences += 1;
+    }
+
+    for path in old_state.overrides.keys() {
+        if !new_state.overrides.contains_key(path) {
+            log::info!("Removed override {}", path.errstyle(FILE_STYLE));
+            differences += 1;
+        }
+    }
+    for (path, hash) in &new_state.overrides {
+        match old_state.overrides.get(path) {
+            None => log::info!("Added override {}", path.errstyle(FILE_STYLE)),
+            Some(old_hash) if old_hash != hash => {
+                log::info!("Changed override {}", path.errstyle(FILE_STYLE))
+            }
+            Some(_) => continue,
+        }
+        differences += 1;
+    }
+
+    if differences == 0 {
+        log::info!("{}", "No differences.".errstyle(SUCCESS_STYLE));
+    }
+    Ok(differences)
+}
@@ -0,0 +1,116 @@
+use std::fmt::Display;
+
+use crate::config::global::{
+    furse, global_config_file, load_global_config, DIRS, FERINTH, GLOBAL_CONFIG_TEMPLATE,
+    HTTP_CLIENT,
+};
+use crate::uwu_colors::{ErrStyle, FILE_STYLE, SUCCESS_STYLE};
+
+/// A mod that should always exist, used for cheap API calls.
+const JEI_CURSEFORGE_ID: i32 = 238222;
+const JEI_MODRINTH_ID: &str = "u6dRKJwZ";
+
+const CDNS: [(&str, &str); 2] = [
+    ("CurseForge CDN", "https://edge.forgecdn.net/"),
+    ("Modrinth CDN", "https://cdn.modrinth.com/"),
+];
+
+/// Check that everything netherfire needs is set up, logging a fix for every problem found.
+/// Returns the number of failed checks.
+pub(crate) async fn doctor() -> usize {
+    let mut failed = 0;
+    let mut report = |check: &str, result: Result<(), (String, String)>| match result {
+        Ok(()) => log::info!("{} {}", "OK".errstyle(SUCCESS_STYLE), check),
+        Err((error, fix)) => {
+            failed += 1;
+            log::error!("FAILED {}: {}", check, error);
+            log::error!("  Fix: {}", fix);
+        }
+    };
+
+    let config_file = global_config_file();
+    let config = load_global_config();
+    report(
+        "Global config",
+        config.as_ref().map(|_| ()).map_err(|e| {
+            fix(
+                e,
+                format!(
+                    "Create '{}' containing:\n\n{}",
+                    config_file.display().errstyle(FILE_STYLE),
+                    GLOBAL_CONFIG_TEMPLATE
+                ),
+            )
+        }),
+    );
+
+    match &config {
+        Ok(_) => report(
+            "CurseForge API key",
+            match furse() {
+                Ok(furse) => furse
+                    .get_mod(JEI_CURSEFORGE_ID)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+            .map(|_| ())
+            .map_err(|e| {
+                fix(
+                    e,
+                    "Check that the API key is correct, and that you can reach the CurseForge API.",
+                )
+            }),
+        ),
+        Err(_) => log::warn!("Skipping CurseForge API key check, as there is no global config."),
+    }
+
+    report(
+        "Modrinth API",
+        FERINTH
+            .get_project(JEI_MODRINTH_ID)
+            .await
+            .map(|_| ())
+            .map_err(|e| fix(e, "Check that you can reach the Modrinth API.")),
+    );
+
+    for (name, url) in CDNS {
+        // Any response at all means the CDN is reachable.
+        report(
+            name,
+            HTTP_CLIENT
+                .head(url)
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(|e| fix(e, format!("Check that you can reach {}.", url))),
+        );
+    }
+
+    let cache_dir = DIRS.cache_dir();
+    report(
+        "Cache directory",
+        check_writable(cache_dir).map_err(|e| {
+            fix(
+                e,
+                format!(
+                    "Make sure '{}' is a writable directory.",
+                    cache_dir.display().errstyle(FILE_STYLE)
+                ),
+            )
+        }),
+    );
+
+    failed
+}
+
+fn fix(error: impl Display, fix: impl Display) -> (String, String) {
+    (error.to_string(), fix.to_string())
+}
+
+fn check_writable(dir: &std::path::Path) -> Result<(), std::io::Error> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(".netherfire-doctor");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(probe)
+}
@@ -0,0 +1,200 @@
+//! What a verified pack installs on each side: the exact mod files, with where they go in the game
+//! directory. Every output selects its mods from the plan, and `netherfire verify --plan` prints
+//! it.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::checks::verify_mods::{VerifiedMod, VerifiedModContainer};
+use crate::config::mods::KnownEnvRequirement;
+use crate::config::pack::OverrideSide;
+use crate::mod_site::ModSite;
+use crate::output::left_out::{left_out_mod, LeftOutReason};
+use crate::output::LIT_MODS;
+
+/// A side a pack is installed on.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Side {
+    Client,
+    Server,
+}
+
+impl Side {
+    fn name(self) -> &'static str {
+        match self {
+            Side::Client => "client",
+            Side::Server => "server",
+        }
+    }
+}
+
+/// A mod file installed on a side.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    /// The config key of the mod's site, e.g. `modrinth`.
+    pub site: &'static str,
+    /// The config key of the mod.
+    pub key: String,
+    pub file: String,
+    /// Where the file goes, relative to the game directory, with `/` separators.
+    pub destination: String,
+    pub size: u64,
+    /// Only installed by artifacts that include optional mods.
+    pub optional: bool,
+}
+
+/// A mod that isn't installed on a side.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnusedMod {
+    pub site: &'static str,
+    pub key: String,
+}
+
+/// What is installed on one side, by site and config key.
+#[derive(Debug, Default, Serialize)]
+pub struct SidePlan {
+    pub files: Vec<PlannedFile>,
+    /// The mods that are unsupported on the side.
+    pub unused: Vec<UnusedMod>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct InstallPlan {
+    pub client: SidePlan,
+    pub server: SidePlan,
+}
+
+impl InstallPlan {
+    /// Plan the installation of the verified mods. Servers get the configured server files.
+    pub fn new(mods: &VerifiedModContainer) -> Self {
+        let mut plan = InstallPlan::default();
+        plan.add_site(&mods.curseforge);
+        plan.add_site(&mods.modrinth);
+        plan
+    }
+
+    fn add_site<S: ModSite>(&mut self, mods: &HashMap<String, VerifiedMod<S>>) {
+        for (key, m) in mods.iter().sorted_by_key(|(k, _)| k.as_str()) {
+            for side in [Side::Client, Side::Server] {
+                let (requirement, info) = match side {
+                    Side::Client => (m.env_requirements.client, &m.info),
+                    Side::Server => (m.env_requirements.server, m.server_file()),
+                };
+                let side_plan = self.side_mut(side);
+                if requirement == KnownEnvRequirement::Unsupported {
+                    side_plan.unused.push(UnusedMod {
+                        site: S::CONFIG_KEY,
+                        key: key.clone(),
+                    });
+                    continue;
+                }
+                side_plan.files.push(PlannedFile {
+                    site: S::CONFIG_KEY,
+                    key: key.clone(),
+                    file: info.filename.clone(),
+                    destination: [LIT_MODS, &info.filename].join("/"),
+                    size: info.file_length,
+                    optional: requirement == KnownEnvRequirement::Optional,
+                });
+            }
+        }
+    }
+
+    fn side(&self, side: Side) -> &SidePlan {
+        match side {
+            Side::Client => &self.client,
+            Side::Server => &self.server,
+        }
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut SidePlan {
+        match side {
+            Side::Client => &mut self.client,
+            Side::Server => &mut self.server,
+        }
+    }
+
+    /// The plan as an artifact uses it, with or without the optional mods.
+    pub fn for_artifact(&self, include_optional: bool) -> ArtifactPlan<'_> {
+        ArtifactPlan {
+            plan: self,
+            include_optional,
+        }
+    }
+}
+
+/// An [InstallPlan] with the choice of an artifact whether to include optional mods.
+#[derive(Debug, Copy, Clone)]
+pub struct ArtifactPlan<'a> {
+    plan: &'a InstallPlan,
+    pub include_optional: bool,
+}
+
+impl<'a> ArtifactPlan<'a> {
+    /// The files the artifact installs on `side`.
+    pub fn files(self, side: Side) -> impl Iterator<Item = &'a PlannedFile> {
+        self.plan
+            .side(side)
+            .files
+            .iter()
+            .filter(move |f| self.include_optional || !f.optional)
+    }
+
+    /// The mods of `mods`' site that the artifact installs on `side`, with their files.
+    pub fn mods<S: ModSite>(
+        self,
+        side: Side,
+        mods: &'a HashMap<String, VerifiedMod<S>>,
+    ) -> impl Iterator<Item = (&'a PlannedFile, &'a VerifiedMod<S>)> {
+        self.files(side)
+            .filter(|f| f.site == S::CONFIG_KEY)
+            .filter_map(|f| Some((f, mods.get(&f.key)?)))
+    }
+
+    /// Whether the artifact installs the mod on `side`.
+    fn installs(self, side: Side, site: &str, key: &str) -> bool {
+        self.files(side).any(|f| f.site == site && f.key == key)
+    }
+
+    /// The sides the artifact installs the mod on, if any.
+    pub fn override_side(self, site: &str, key: &str) -> Option<OverrideSide> {
+        match (
+            self.installs(Side::Client, site, key),
+            self.installs(Side::Server, site, key),
+        ) {
+            (true, true) => Some(OverrideSide::Both),
+            (true, false) => Some(OverrideSide::Client),
+            (false, true) => Some(OverrideSide::Server),
+            (false, false) => None,
+        }
+    }
+
+    /// Record the mods that `artifact`, which is for `side`, leaves out, see
+    /// [crate::output::left_out].
+    pub fn record_left_out(self, artifact: &'static str, side: Side) {
+        let side_plan = self.plan.side(side);
+        for m in &side_plan.unused {
+            left_out_mod(artifact, m.site, &m.key, LeftOutReason::Side(side.name()));
+        }
+        if !self.include_optional {
+            for f in side_plan.files.iter().filter(|f| f.optional) {
+                left_out_mod(
+                    artifact,
+                    f.site,
+                    &f.key,
+                    LeftOutReason::Optional(side.name()),
+                );
+            }
+        }
+    }
+}
+
+/// Print the plan as JSON.
+pub fn print_install_plan(plan: &InstallPlan) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(plan).expect("install plan is serializable")
+    );
+}
@@ -0,0 +1,119 @@
+//! Checking that the files the sites serve for the locked mods are still the ones that were
+//! locked, as a file replaced on its site, e.g. by a compromised account, keeps its version ID.
+
+use std::collections::HashMap;
+
+use futures::StreamExt;
+use itertools::Itertools;
+
+use crate::checks::verify_mods::{VerifiedMod, VerifiedModContainer};
+use crate::config::globs::NamePattern;
+use crate::config::mods::{ConfigMod, ConfigModContainer};
+use crate::mod_site::{CurseForge, ModFileInfo, ModHash, ModId, ModSite, Modrinth};
+use crate::units;
+use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, SITE_NAME_STYLE, SUCCESS_STYLE};
+
+/// How many files are loaded at once.
+const CONCURRENCY: usize = 5;
+
+/// Load the file metadata of every locked mod again, including server files, and log the mods
+/// whose hashes differ from the ones in the lockfile, or whose files can't be loaded anymore.
+/// Returns the number of such mods.
+pub(crate) async fn check_locked_files(
+    config: &ConfigModContainer,
+    locked: &VerifiedModContainer,
+) -> usize {
+    let mut problems = check_site(CurseForge, &config.curseforge, &locked.curseforge).await;
+    problems += check_site(Modrinth, &config.modrinth, &locked.modrinth).await;
+    if problems == 0 {
+        log::info!(
+            "{}",
+            "The files of all locked mods are unchanged.".errstyle(SUCCESS_STYLE)
+        );
+    }
+    problems
+}
+
+async fn check_site<S: ModSite>(
+    site: S,
+    config: &HashMap<String, ConfigMod<S::Id>>,
+    locked: &HashMap<String, VerifiedMod<S>>,
+) -> usize {
+    if locked.is_empty() {
+        return 0;
+    }
+    log::info!(
+        "[{}] Checking the files of {}...",
+        S::NAME.errstyle(SITE_NAME_STYLE),
+        units::count(locked.len() as u64, "locked mod")
+    );
+    let results = futures::stream::iter(locked.iter().sorted_by_key(|(k, _)| k.as_str()))
+        .map(|(k, m)| async move {
+            let server_version_id = config.get(k).and_then(|c| c.server_version_id.clone());
+            (k, check_mod(site, m, server_version_id).await)
+        })
+        .buffered(CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+    let mut problems = 0;
+    for (k, mod_problems) in results {
+        if mod_problems.is_empty() {
+            continue;
+        }
+        problems += 1;
+        for problem in mod_problems {
+            log::error!(
+                "[{}] {}: {}",
+                S::NAME.errstyle(SITE_NAME_STYLE),
+                k.errstyle(CONFIG_VAL_STYLE),
+                problem
+            );
+        }
+    }
+    problems
+}
+
+/// The differences between the locked files of a mod and the ones its site serves now.
+async fn check_mod<S: ModSite>(
+    site: S,
+    locked: &VerifiedMod<S>,
+    server_version_id: Option<S::Id>,
+) -> Vec<String> {
+    let mut problems = check_file(site, locked.source.clone(), &locked.info).await;
+    if let (Some(version_id), Some(server_info)) = (server_version_id, &locked.server_info) {
+        let id = ModId {
+            project_id: locked.source.project_id.clone(),
+            version_id,
+        };
+        problems.extend(check_file(site, id, server_info).await);
+    }
+    problems
+}
+
+async fn check_file<S: ModSite>(
+    site: S,
+    id: ModId<S::Id>,
+    locked: &ModFileInfo<S::Id, S::ModHash>,
+) -> Vec<String> {
+    // Look the file up by name, as a version can have several files.
+    let pattern = NamePattern::literal(&locked.filename);
+    let current = match site.load_matching_file(id, &pattern).await {
+        Ok((_, info)) => info,
+        Err(e) => return vec![format!("{}: {}", locked.filename, e)],
+    };
+    let current_hashes = current.hash.site_hashes();
+    locked
+        .hash
+        .site_hashes()
+        .into_iter()
+        .filter_map(|(algorithm, was)| {
+            let (_, now) = current_hashes.iter().find(|(a, _)| *a == algorithm)?;
+            (*now != was).then(|| {
+                format!(
+                    "{}: {} was {}, is now {}",
+                    locked.filename, algorithm, was, now
+                )
+            })
+        })
+        .collect()
+}
@@ -1 +1,14 @@
+pub(crate) mod artifact;
+pub(crate) mod cdn;
+pub(crate) mod client_size;
+pub(crate) mod dependency_closure;
+pub(crate) mod diff;
+pub(crate) mod doctor;
+pub(crate) mod install_plan;
+pub(crate) mod locked_files;
+pub(crate) mod mod_ids;
+pub(crate) mod news;
+pub(crate) mod overrides;
+pub(crate) mod round_trip;
+pub(crate) mod sync_instance;
 pub(crate) mod verify_mods;
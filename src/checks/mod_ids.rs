@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read};
+
+use futures::StreamExt;
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::io::AsyncReadExt;
+use zip::ZipArchive;
+
+use crate::checks::verify_mods::{VerifiedMod, VerifiedModContainer};
+use crate::mod_site::{CurseForge, ModSite, Modrinth};
+use crate::output::mod_download::{cached_mod_download, ModDownloadError};
+use crate::progress::{emit, Phase, ProgressEvent};
+use crate::uwu_colors::{ErrStyle, SUCCESS_STYLE};
+
+#[derive(Debug, Error)]
+pub enum ModIdCheckError {
+    #[error("Error downloading {0}: {1}")]
+    Download(String, #[source] ModDownloadError),
+    #[error("Multiple mods declare the same mod ID:\n{}", .0.join("\n"))]
+    Collisions(Vec<String>),
+}
+
+#[derive(Deserialize)]
+struct ForgeModsToml {
+    #[serde(default)]
+    mods: Vec<ForgeMod>,
+}
+
+#[derive(Deserialize)]
+struct ForgeMod {
+    #[serde(rename = "modId")]
+    mod_id: String,
+}
+
+/// Read the mod IDs the loader metadata in a mod jar declares. Metadata that can't be parsed is
+/// skipped, as the loader is the authority on that.
+fn read_mod_ids(jar: &[u8]) -> Vec<String> {
+    let Ok(mut zip) = ZipArchive::new(Cursor::new(jar)) else {
+        return Vec::new();
+    };
+    let mut read = |name: &str| {
+        let mut content = String::new();
+        zip.by_name(name)
+            .ok()?
+            .read_to_string(&mut content)
+            .ok()
+            .map(|_| content)
+    };
+    let mut ids = Vec::new();
+    for forge_file in ["META-INF/mods.toml", "META-INF/neoforge.mods.toml"] {
+        if let Some(mods) = read(forge_file).and_then(|c| toml::from_str::<ForgeModsToml>(&c).ok())
+        {
+            ids.extend(mods.mods.into_iter().map(|m| m.mod_id));
+        }
+    }
+    if let Some(fabric) =
+        read("fabric.mod.json").and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+    {
+        ids.extend(fabric["id"].as_str().map(str::to_string));
+    }
+    if let Some(quilt) =
+        read("quilt.mod.json").and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+    {
+        ids.extend(quilt["quilt_loader"]["id"].as_str().map(str::to_string));
+    }
+    // Unexpanded build placeholders don't identify anything.
+    ids.retain(|id| !id.contains("${"));
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+async fn mod_ids<S: ModSite>(
+    site: S,
+    cfg_id: &str,
+    m: &VerifiedMod<S>,
+) -> Result<(String, Vec<String>), ModIdCheckError> {
+    let name = format!("[{}] {}", S::CONFIG_KEY, cfg_id);
+    let download_error = |e| ModIdCheckError::Download(name.clone(), e);
+    let mut content = Vec::new();
+    cached_mod_download(site, &m.info)
+        .await
+        .map_err(download_error)?
+        .read_to_end(&mut content)
+        .await
+        .map_err(|e| download_error(e.into()))?;
+    let ids = tokio::task::block_in_place(|| read_mod_ids(&content));
+    Ok((name, ids))
+}
+
+/// Check that no two configured mods declare the same mod ID in their loader metadata, which is
+/// common with forks and renamed mods, and would otherwise only show as a crash when launching.
+/// The mod files are taken from the download cache, or downloaded into it.
+pub async fn check_mod_ids(mods: &VerifiedModContainer) -> Result<(), ModIdCheckError> {
+    log::info!("Checking for mod ID collisions...");
+    emit(ProgressEvent::PhaseStarted {
+        phase: Phase::ModIdCheck,
+    });
+    let curseforge = futures::stream::iter(&mods.curseforge)
+        .map(|(k, m)| mod_ids(CurseForge, k, m))
+        .buffer_unordered(5)
+        .collect::<Vec<_>>();
+    let modrinth = futures::stream::iter(&mods.modrinth)
+        .map(|(k, m)| mod_ids(Modrinth, k, m))
+        .buffer_unordered(5)
+        .collect::<Vec<_>>();
+    let (curseforge, modrinth) = futures::join!(curseforge, modrinth);
+
+    let mut declared_by = BTreeMap::<String, Vec<String>>::new();
+    for result in curseforge.into_iter().chain(modrinth) {
+        let (name, ids) = result?;
+        for id in ids {
+            declared_by.entry(id).or_default().push(name.clone());
+        }
+    }
+    let collisions = declared_by
+        .into_iter()
+        .filter(|(_, mods)| mods.len() > 1)
+        .map(|(id, mut mods)| {
+            mods.sort();
+            format!("'{}' is declared by {}", id, mods.join(", "))
+        })
+        .collect::<Vec<_>>();
+    if !collisions.is_empty() {
+        return Err(ModIdCheckError::Collisions(collisions));
+    }
+    log::info!("{}", "No mod ID collisions.".errstyle(SUCCESS_STYLE));
+    emit(ProgressEvent::PhaseCompleted {
+        phase: Phase::ModIdCheck,
+    });
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use ferinth::structures::UtcTime;
+use futures::StreamExt;
+use thiserror::Error;
+
+use crate::config::mods::{ConfigMod, ConfigModContainer};
+use crate::config::pack::PackConfig;
+use crate::mod_site::{CurseForge, ModIdValue, ModLoadingError, ModSite, Modrinth};
+use crate::uwu_colors::{
+    ErrStyle, CONFIG_VAL_STYLE, SITE_NAME_STYLE, SITE_VAL_STYLE, SUCCESS_STYLE,
+};
+
+#[derive(Debug, Error)]
+pub enum NewsError {
+    #[error("Error loading versions of mod {0}: {1}")]
+    Loading(String, #[source] ModLoadingError),
+}
+
+/// A version published after the configured version of a mod.
+struct NewVersion {
+    site: &'static str,
+    cfg_id: String,
+    name: String,
+    version_id: String,
+    date: UtcTime,
+    changelog: Option<String>,
+}
+
+/// Log the versions of the configured mods that were published for the pack's Minecraft version
+/// and mod loader after the versions in the config, oldest first, with their changelogs. Returns
+/// the number of new versions.
+pub(crate) async fn list_news(
+    pack_config: &PackConfig<ConfigModContainer>,
+) -> Result<usize, NewsError> {
+    log::info!("Looking for new versions of the configured mods...");
+    let mut versions = new_versions(pack_config, &pack_config.mods.curseforge, CurseForge).await?;
+    versions.extend(new_versions(pack_config, &pack_config.mods.modrinth, Modrinth).await?);
+    versions.sort_by(|a, b| (a.date, &a.cfg_id).cmp(&(b.date, &b.cfg_id)));
+
+    for version in &versions {
+        log::info!(
+            "[{}] {} {}: {} (version {})",
+            version.site.errstyle(SITE_NAME_STYLE),
+            version.date.format("%Y-%m-%d"),
+            version.cfg_id.errstyle(CONFIG_VAL_STYLE),
+            version.name,
+            version.version_id.errstyle(SITE_VAL_STYLE),
+        );
+        match &version.changelog {
+            Some(changelog) => {
+                for line in changelog.lines().filter(|l| !l.trim().is_empty()) {
+                    log::info!("    {}", line.trim_end());
+                }
+            }
+            None => log::info!("    (no changelog)"),
+        }
+    }
+    if versions.is_empty() {
+        log::info!(
+            "{}",
+            "All mods are at their latest version.".errstyle(SUCCESS_STYLE)
+        );
+    }
+    Ok(versions.len())
+}
+
+async fn new_versions<K, S>(
+    pack_config: &PackConfig<ConfigModContainer>,
+    mods: &HashMap<String, ConfigMod<K>>,
+    site: S,
+) -> Result<Vec<NewVersion>, NewsError>
+where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+{
+    let results = futures::stream::iter(mods)
+        .map(|(k, m)| async move {
+            site.get_newer_versions_for_pack(
+                m.source.clone(),
+                &pack_config.minecraft_version,
+                &pack_config.mod_loader.id,
+            )
+            .await
+            .map(|versions| (k, versions))
+            .map_err(|e| NewsError::Loading(k.clone(), e))
+        })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+    let mut new_versions = Vec::new();
+    for result in results {
+        let (cfg_id, versions) = result?;
+        new_versions.extend(versions.into_iter().map(|v| NewVersion {
+            site: S::NAME,
+            cfg_id: cfg_id.clone(),
+            name: v.name,
+            version_id: v.version_id.to_string(),
+            date: v.date_published,
+            changelog: v.changelog,
+        }));
+    }
+    Ok(new_versions)
+}
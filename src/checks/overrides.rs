@@ -0,0 +1,184 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::config::globs::Globs;
+use crate::config::pack::{PackConfig, Symlinks};
+use crate::config::source::PackSource;
+use crate::output::exclusions::{Exclusions, IGNORE_FILE};
+use crate::output::{walk_overrides, LIT_CLIENT_OVERRIDES, LIT_OVERRIDES, LIT_SERVER_OVERRIDES};
+use crate::warnings::{warn, Warning, WarningKind};
+
+/// Names Windows reserves for devices, also with an extension, e.g. `con.txt`.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters Windows doesn't allow in file names.
+const RESERVED_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// The override kinds that end up in the same directory when a pack is installed.
+const INSTALLED_TREES: [[&str; 2]; 2] = [
+    [LIT_OVERRIDES, LIT_CLIENT_OVERRIDES],
+    [LIT_OVERRIDES, LIT_SERVER_OVERRIDES],
+];
+
+#[derive(Debug, Error)]
+pub enum OverridesCheckError {
+    #[error("I/O error on {0}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("Walk error: {0}")]
+    Walk(#[from] walkdir::Error),
+    #[error("Error loading {IGNORE_FILE}: {0}")]
+    Ignore(#[from] ignore::Error),
+    #[error("Some overrides can't be extracted on every OS:\n{}", .0.join("\n"))]
+    Problems(Vec<String>),
+}
+
+/// Describe why a file name can't be used on Windows, if it can't.
+fn name_problem(name: &str) -> Option<&'static str> {
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Some("ends with a dot or space, which Windows removes");
+    }
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES.contains(&stem.to_ascii_uppercase().as_str()) {
+        return Some("is a device name on Windows");
+    }
+    if name
+        .chars()
+        .any(|c| c.is_control() || RESERVED_CHARS.contains(&c))
+    {
+        return Some("contains characters Windows doesn't allow");
+    }
+    None
+}
+
+fn parent(relative: &str) -> Option<&str> {
+    relative.rsplit_once('/').map(|(parent, _)| parent)
+}
+
+/// Check that the overrides can be extracted on Windows and macOS, before publishing an archive
+/// that fails there: no files or directories may differ only by case once the overrides are
+/// merged on install, and none may use a name Windows reserves. Also warns about files that are
+/// in both `overrides/` and a side's override directory.
+pub fn check_overrides<MC>(
+    source: &PackSource,
+    pack: &PackConfig<MC>,
+) -> Result<(), OverridesCheckError> {
+    // Paths relative to the installed directory, by override kind, with the file used for each.
+    // Directories are included, as `Config/` and `config/` collide too.
+    let mut entries = BTreeMap::<&str, BTreeMap<String, PathBuf>>::new();
+    let mut problems = BTreeSet::new();
+    for kind in [LIT_OVERRIDES, LIT_CLIENT_OVERRIDES, LIT_SERVER_OVERRIDES] {
+        let kind_entries = entries.entry(kind).or_default();
+        for dir in source.join_all(pack.override_dirs.of_kind(kind)) {
+            if !dir.exists() {
+                continue;
+            }
+            let exclusions = Exclusions::load(&dir, &pack.exclude)?;
+            let follow_links = pack.symlinks != Symlinks::Error;
+            for entry in walk_overrides(&dir, follow_links, &exclusions) {
+                let entry = entry?;
+                if entry.depth() == 0 {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy();
+                if let Some(problem) = name_problem(&name) {
+                    problems.insert(format!("'{}' {}", entry.path().display(), problem));
+                }
+                let relative = entry
+                    .path()
+                    .strip_prefix(&dir)
+                    .expect("walked path is under its root")
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                // Later directories replace the files of the packs they extend.
+                kind_entries.insert(relative, entry.into_path());
+            }
+        }
+    }
+
+    for tree in INSTALLED_TREES {
+        let mut by_folded_case = BTreeMap::<String, BTreeMap<&str, &PathBuf>>::new();
+        for kind in tree {
+            for (relative, path) in &entries[kind] {
+                by_folded_case
+                    .entry(relative.to_lowercase())
+                    .or_default()
+                    .entry(relative)
+                    .or_insert(path);
+            }
+        }
+        // Only report the highest colliding directory, not everything in it.
+        for variants in by_folded_case
+            .values()
+            .filter(|v| v.len() > 1 && v.keys().map(|r| parent(r)).all_equal())
+        {
+            let paths = variants
+                .values()
+                .map(|p| format!("'{}'", p.display()))
+                .collect::<Vec<_>>();
+            problems.insert(format!("{} only differ by case", paths.join(" and ")));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(OverridesCheckError::Problems(
+            problems.into_iter().collect(),
+        ));
+    }
+    warn_duplicates(&entries, &pack.patches)?;
+    Ok(())
+}
+
+/// Warn about files that are both in `overrides/` and a side's override directory. The file for
+/// the side replaces the other one, which is easy to miss when editing the other one. Patches
+/// are merged into the other one instead.
+fn warn_duplicates(
+    entries: &BTreeMap<&str, BTreeMap<String, PathBuf>>,
+    patches: &Globs,
+) -> Result<(), OverridesCheckError> {
+    for (kind, side) in [
+        (LIT_CLIENT_OVERRIDES, "clients"),
+        (LIT_SERVER_OVERRIDES, "servers"),
+    ] {
+        for (relative, path) in &entries[kind] {
+            let Some(shared_path) = entries[LIT_OVERRIDES].get(relative) else {
+                continue;
+            };
+            if kind == LIT_SERVER_OVERRIDES && patches.is_match(relative) {
+                continue;
+            }
+            if !path.is_file() || !shared_path.is_file() {
+                continue;
+            }
+            let read = |p: &PathBuf| {
+                std::fs::read(p).map_err(|e| OverridesCheckError::Io(p.display().to_string(), e))
+            };
+            if read(path)? == read(shared_path)? {
+                warn(Warning::new(
+                    WarningKind::Overrides,
+                    format!(
+                        "'{}' is the same as '{}', so it isn't needed",
+                        path.display(),
+                        shared_path.display()
+                    ),
+                ));
+            } else {
+                warn(Warning::new(
+                    WarningKind::Overrides,
+                    format!(
+                        "'{}' differs from '{}', {} get the former",
+                        path.display(),
+                        shared_path.display(),
+                        side
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
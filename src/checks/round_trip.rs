@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::fs::File;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::checks::verify_mods::VerifiedModContainer;
+use crate::config::pack::{ModLoaderType, PackConfig};
+use crate::import::mrpack::{read_mrpack, MODRINTH_MANIFEST};
+use crate::import::ImportError;
+use crate::output::build_metadata::{built_mods, BUILD_METADATA_FILE};
+use crate::output::modrinth_manifest::EMBEDDED_MANIFEST;
+use crate::output::{LIT_CLIENT_OVERRIDES, LIT_OVERRIDES, LIT_SERVER_OVERRIDES};
+use crate::uwu_colors::{ErrStyle, FILE_STYLE};
+
+#[derive(Debug, Error)]
+pub enum RoundTripError {
+    #[error("Error reading {0} back: {1}")]
+    Read(String, #[source] ImportError),
+    #[error("{0} doesn't match the pack it was generated from:\n{}", .1.join("\n"))]
+    Mismatch(String, Vec<String>),
+}
+
+/// Read a generated Modrinth pack back and check that it describes the pack it was generated
+/// from: the manifest survives being parsed, lists exactly the verified Modrinth mods with their
+/// hashes, sizes, downloads and sides, and every other entry is an override. Catches format
+/// regressions before launchers do.
+pub(crate) fn check_mrpack_round_trip(
+    pack: &PackConfig<VerifiedModContainer>,
+    path: &Path,
+) -> Result<(), RoundTripError> {
+    let name = path.display().to_string();
+    log::info!("Reading back '{}'...", name.errstyle(FILE_STYLE));
+    let mrpack = File::open(path)
+        .map_err(ImportError::from)
+        .and_then(read_mrpack)
+        .map_err(|e| RoundTripError::Read(name.clone(), e))?;
+    let manifest = &mrpack.manifest;
+
+    let mut problems = Problems::default();
+    if serde_json::to_value(manifest).expect("manifest is serializable") != mrpack.raw_manifest {
+        problems.0.push(format!(
+            "{} changes when it's parsed and written again",
+            MODRINTH_MANIFEST
+        ));
+    }
+    problems.expect("The format version", manifest.format_version, 1);
+    problems.expect("The name", &manifest.name, &pack.name);
+    problems.expect("The version", &manifest.version_id, &pack.version);
+    problems.expect(
+        "The summary",
+        manifest.summary.as_ref(),
+        Some(&pack.description),
+    );
+    let dependencies = &manifest.dependencies;
+    problems.expect(
+        "The Minecraft version",
+        &dependencies.minecraft,
+        &pack.minecraft_version,
+    );
+    for (loader, version) in [
+        (ModLoaderType::Forge, &dependencies.forge),
+        (ModLoaderType::Neoforge, &dependencies.neoforge),
+        (ModLoaderType::Fabric, &dependencies.fabric_loader),
+        (ModLoaderType::Quilt, &dependencies.quilt_loader),
+    ] {
+        let expected = pack.loader_version(&loader);
+        problems.expect(
+            format!("The {} version", loader),
+            version.as_ref(),
+            expected,
+        );
+    }
+
+    let mut files = HashMap::new();
+    for file in &manifest.files {
+        if files.insert(file.path.as_str(), file).is_some() {
+            problems
+                .0
+                .push(format!("'{}' is listed more than once", file.path));
+        }
+    }
+    for (cfg_id, m) in &pack.mods.modrinth {
+        let path = format!("mods/{}", m.info.filename);
+        let Some(file) = files.remove(path.as_str()) else {
+            problems
+                .0
+                .push(format!("Mod {} isn't listed as '{}'", cfg_id, path));
+            continue;
+        };
+        let what = |field: &str| format!("The {} of '{}'", field, path);
+        problems.expect(
+            what("SHA-1"),
+            &file.hashes.sha1,
+            &format!("{:x}", m.info.hash.sha1),
+        );
+        problems.expect(
+            what("SHA-512"),
+            &file.hashes.sha512,
+            &format!("{:x}", m.info.hash.sha512),
+        );
+        problems.expect(what("size"), file.file_size, m.info.file_length);
+        problems.expect(
+            what("downloads"),
+            &file.downloads,
+            &vec![m.info.url.clone()],
+        );
+        problems.expect(
+            what("sides"),
+            file.env.as_ref().map(|e| (e.client, e.server)),
+            Some((m.env_requirements.client, m.env_requirements.server)),
+        );
+    }
+    for path in files.keys() {
+        problems
+            .0
+            .push(format!("'{}' is listed, but isn't a configured mod", path));
+    }
+
+    for file in mrpack.embedded.iter().flat_map(|e| &e.files) {
+        if !mrpack.entries.contains(&file.path) {
+            problems
+                .0
+                .push(format!("Embedded file '{}' isn't in the pack", file.path));
+        }
+        let Some((cfg_id, m)) = pack
+            .mods
+            .curseforge
+            .iter()
+            .find(|(_, m)| m.source.project_id == file.project_id)
+        else {
+            problems.0.push(format!(
+                "Embedded file '{}' isn't a configured mod",
+                file.path
+            ));
+            continue;
+        };
+        problems.expect(
+            format!("The recorded sides of mod {}", cfg_id),
+            (file.env.client, file.env.server),
+            (m.env_requirements.client, m.env_requirements.server),
+        );
+    }
+
+    for entry in &mrpack.entries {
+        let in_overrides = [LIT_OVERRIDES, LIT_CLIENT_OVERRIDES, LIT_SERVER_OVERRIDES]
+            .iter()
+            .any(|o| entry.starts_with(&format!("{}/", o)));
+        if !in_overrides
+            && entry != MODRINTH_MANIFEST
+            && entry != BUILD_METADATA_FILE
+            && entry != EMBEDDED_MANIFEST
+        {
+            problems
+                .0
+                .push(format!("Entry '{}' isn't a manifest or an override", entry));
+        }
+    }
+    match &mrpack.metadata {
+        Some(metadata) => problems.expect(
+            "The mods in the build metadata",
+            &metadata.mods,
+            &Some(built_mods(&pack.mods)),
+        ),
+        None => problems
+            .0
+            .push(format!("{} is missing", BUILD_METADATA_FILE)),
+    }
+
+    if !problems.0.is_empty() {
+        return Err(RoundTripError::Mismatch(name, problems.0));
+    }
+    Ok(())
+}
+
+/// The differences found between a generated pack and what it was generated from.
+#[derive(Default)]
+struct Problems(Vec<String>);
+
+impl Problems {
+    fn expect<T: PartialEq + Debug>(&mut self, what: impl Display, actual: T, expected: T) {
+        if actual != expected {
+            self.0
+                .push(format!("{} is {:?}, expected {:?}", what, actual, expected));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::checks::install_plan::InstallPlan;
+    use crate::checks::verify_mods::VerifiedModContainer;
+    use crate::config::source::load_pack_config;
+    use crate::import::mrpack::read_mrpack;
+    use crate::output::build_metadata::BuildInfo;
+    use crate::output::create_modrinth_pack;
+
+    use super::check_mrpack_round_trip;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn generated_mrpack_reads_back() {
+        let source_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            source_dir.path().join("config.toml"),
+            r#"
+name = "Round Trip"
+description = "A pack that is read back"
+author = "netherfire"
+version = "1.0.0"
+minecraft_version = "1.20.1"
+mod_loader = { id = "fabric", version = "0.15.11" }
+
+[mods.curseforge]
+[mods.modrinth]
+"#,
+        )
+        .unwrap();
+        let config_dir = source_dir.path().join("overrides/config");
+        std::fs::create_dir_all(&config_dir).unwrap();
+        std::fs::write(config_dir.join("example.toml"), "enabled = true\n").unwrap();
+        let output_dir = tempfile::tempdir().unwrap();
+
+        let (pack, source) = load_pack_config(source_dir.path()).unwrap();
+        let pack = pack.with_mods(VerifiedModContainer {
+            curseforge: HashMap::new(),
+            modrinth: HashMap::new(),
+        });
+        let build = BuildInfo::new(&pack, &source, None).unwrap();
+        let plan = InstallPlan::new(&pack.mods);
+        let path = create_modrinth_pack(
+            &pack,
+            &build,
+            &source,
+            output_dir.path().to_path_buf(),
+            plan.for_artifact(true),
+            false,
+            false,
+        )
+        .await
+        .unwrap();
+
+        let mrpack = read_mrpack(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(mrpack.manifest.name, "Round Trip");
+        assert_eq!(mrpack.manifest.version_id, "1.0.0");
+        assert!(mrpack.manifest.files.is_empty());
+        assert!(mrpack.metadata.is_some());
+        assert!(mrpack
+            .entries
+            .iter()
+            .any(|e| e == "overrides/config/example.toml"));
+        tokio::task::block_in_place(|| check_mrpack_round_trip(&pack, &path)).unwrap();
+    }
+}
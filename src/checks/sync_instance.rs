@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use digest::Digest;
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::checks::verify_mods::submit_load;
+use crate::config::global::FERINTH;
+use crate::config::mods::{ConfigMod, ConfigModContainer};
+use crate::config::pack::PackConfig;
+use crate::mod_site::{CurseForge, ModHash, ModIdValue, ModLoadingError, ModSite, Modrinth};
+use crate::uwu_colors::{
+    ErrStyle, CONFIG_VAL_STYLE, FILE_STYLE, SITE_NAME_STYLE, SITE_VAL_STYLE, SUCCESS_STYLE,
+};
+
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error loading mod {0}: {1}")]
+    Loading(String, #[source] ModLoadingError),
+}
+
+/// A mod from the config, with the hash of its file.
+struct ConfigModFile {
+    site: &'static str,
+    cfg_id: String,
+    filename: String,
+    sha1: Option<String>,
+}
+
+/// Compare the mods in the config with the mods folder of a launcher instance, by SHA-1 hash, and
+/// log the differences.
+pub(crate) async fn sync_instance(
+    pack_config: &PackConfig<ConfigModContainer>,
+    instance_dir: &Path,
+) -> Result<(), SyncError> {
+    let mods_dir = find_mods_dir(instance_dir);
+    log::info!(
+        "Comparing with instance mods in '{}'...",
+        mods_dir.display().errstyle(FILE_STYLE)
+    );
+
+    let mut instance_files = tokio::task::block_in_place(|| hash_instance_mods(&mods_dir))?;
+
+    let mut config_files = load_config_files(&pack_config.mods.curseforge, CurseForge).await?;
+    config_files.extend(load_config_files(&pack_config.mods.modrinth, Modrinth).await?);
+
+    let mut missing_in_instance = Vec::new();
+    let mut matched = 0;
+    for file in config_files {
+        let Some(sha1) = &file.sha1 else {
+            log::warn!(
+                "[{}] Mod {} has no SHA-1 hash, so it can't be compared.",
+                file.site.errstyle(SITE_NAME_STYLE),
+                file.cfg_id.errstyle(CONFIG_VAL_STYLE),
+            );
+            continue;
+        };
+        if instance_files.remove(sha1).is_some() {
+            matched += 1;
+        } else {
+            missing_in_instance.push(file);
+        }
+    }
+
+    log::info!(
+        "{} mods match between the config and the instance.",
+        matched
+    );
+
+    for file in &missing_in_instance {
+        log::info!(
+            "[{}] In config but not in instance: {} ({})",
+            file.site.errstyle(SITE_NAME_STYLE),
+            file.cfg_id.errstyle(CONFIG_VAL_STYLE),
+            file.filename.errstyle(FILE_STYLE),
+        );
+    }
+
+    if !instance_files.is_empty() {
+        // Try to find the projects on Modrinth, so the user knows how to add them.
+        let known_versions = FERINTH
+            .get_versions_from_hashes(instance_files.keys().cloned().collect())
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to look up instance-only mods on Modrinth: {}", e);
+                HashMap::new()
+            });
+        for (sha1, filename) in instance_files.iter().sorted_by_key(|(_, f)| *f) {
+            match known_versions.get(sha1) {
+                Some(version) => log::info!(
+                    "In instance but not in config: {} (Modrinth project {})",
+                    filename.errstyle(FILE_STYLE),
+                    version.project_id.errstyle(SITE_VAL_STYLE),
+                ),
+                None => log::info!(
+                    "In instance but not in config: {}",
+                    filename.errstyle(FILE_STYLE),
+                ),
+            }
+        }
+    }
+
+    if missing_in_instance.is_empty() && instance_files.is_empty() {
+        log::info!(
+            "{}",
+            "The config and the instance are in sync.".errstyle(SUCCESS_STYLE)
+        );
+    }
+
+    Ok(())
+}
+
+/// Find the mods folder of a Prism/MultiMC or CurseForge instance. Falls back to the given
+/// directory itself.
+fn find_mods_dir(instance_dir: &Path) -> PathBuf {
+    [".minecraft/mods", "minecraft/mods", "mods"]
+        .into_iter()
+        .map(|p| instance_dir.join(p))
+        .find(|p| p.is_dir())
+        .unwrap_or_else(|| instance_dir.to_path_buf())
+}
+
+/// Hash the enabled mod jars in the given directory, mapping hex SHA-1 hash to file name.
+fn hash_instance_mods(mods_dir: &Path) -> Result<HashMap<String, String>, std::io::Error> {
+    let mut files = HashMap::new();
+    for entry in std::fs::read_dir(mods_dir)? {
+        let entry = entry?;
+        let filename = entry.file_name().to_string_lossy().into_owned();
+        if !entry.file_type()?.is_file() || !filename.ends_with(".jar") {
+            continue;
+        }
+        let content = std::fs::read(entry.path())?;
+        files.insert(format!("{:x}", sha1::Sha1::digest(&content)), filename);
+    }
+    Ok(files)
+}
+
+async fn load_config_files<K, S>(
+    mods: &HashMap<String, ConfigMod<K>>,
+    site: S,
+) -> Result<Vec<ConfigModFile>, SyncError>
+where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+{
+    let loads = mods
+        .iter()
+        .sorted_by_key(|(k, _)| k.as_str())
+        .map(|(k, m)| {
+            (
+                k.clone(),
+                submit_load(m.source.clone(), m.file.clone(), site),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut files = Vec::with_capacity(loads.len());
+    for (cfg_id, load) in loads {
+        let (_, info) = load
+            .await
+            .expect("tokio failure")
+            .map_err(|e| SyncError::Loading(cfg_id.clone(), e))?;
+        files.push(ConfigModFile {
+            site: S::NAME,
+            cfg_id,
+            filename: info.filename,
+            sha1: info.hash.sha1().map(|h| format!("{:x}", h)),
+        });
+    }
+    Ok(files)
+}
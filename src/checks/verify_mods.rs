@@ -1,39 +1,68 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 
 use itertools::Itertools;
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize, Serializer};
 use thiserror::Error;
 use tokio::sync::Semaphore;
 use tokio::task::JoinHandle;
 
+use crate::config::globs::NamePattern;
 use crate::config::mods::{
-    compute_env, ConfigMod, ConfigModContainer, EnvRequirement, KnownEnvRequirement,
+    compute_env, ConfigMod, ConfigModContainer, EnvRequirement, KnownEnvRequirement, SideDefaults,
 };
-use crate::config::pack::PackConfig;
+use crate::config::pack::{MinecraftVersionCheck, ModLoaderType, PackConfig, VersionType};
+use crate::config::policy::LoadedPolicy;
+use crate::config::project_lists::{ProjectListViolation, ProjectLists, SiteProjectLists};
+use crate::config::source::ChangedMods;
+use crate::json_report::{failure_reports, FailureReport};
 use crate::mod_site::{
-    CurseForge, DependencyId, ModDependencyKind, ModFileInfo, ModFileLoadingResult, ModId,
-    ModIdValue, ModLoadingError, ModSite, Modrinth,
+    CurseForge, DependencyId, MatchingFileLoadingResult, ModDependency, ModDependencyKind,
+    ModFileInfo, ModId, ModIdValue, ModInfo, ModLoadingError, ModSite, Modrinth,
 };
+use crate::progress::{emit, Phase, ProgressEvent};
 use crate::uwu_colors::{
     ErrStyle, CONFIG_VAL_STYLE, SITE_NAME_STYLE, SITE_VAL_STYLE, SUCCESS_STYLE,
 };
+use crate::warnings::{warn, Warning, WarningKind};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerifiedModContainer {
     pub curseforge: HashMap<String, VerifiedMod<CurseForge>>,
     pub modrinth: HashMap<String, VerifiedMod<Modrinth>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct VerifiedMod<S: ModSite> {
     pub source: ModId<S::Id>,
+    /// The ID of the file picked with `file`, if it isn't `source.version_id`.
+    #[serde(default)]
+    pub file_id: Option<S::Id>,
     pub info: ModFileInfo<S::Id, S::ModHash>,
+    /// The file to use in the server base instead of [Self::info], if configured.
+    pub server_info: Option<ModFileInfo<S::Id, S::ModHash>>,
     pub env_requirements: KnownEnvRequirements,
+    /// The `note` of the mod in the config.
+    #[serde(default)]
+    pub note: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl<S: ModSite> VerifiedMod<S> {
+    /// The ID of the file to install.
+    pub fn file_id(&self) -> &S::Id {
+        self.file_id.as_ref().unwrap_or(&self.source.version_id)
+    }
+
+    /// The file to install on a server.
+    pub fn server_file(&self) -> &ModFileInfo<S::Id, S::ModHash> {
+        self.server_info.as_ref().unwrap_or(&self.info)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct KnownEnvRequirements {
     pub client: KnownEnvRequirement,
     pub server: KnownEnvRequirement,
@@ -45,8 +74,11 @@ pub enum ModVerificationError {
     Loading(#[from] ModLoadingError),
     #[error("The mod does not allow third-party distribution. Add it to `mods/`.")]
     DistributionDenied,
-    #[error("Required dependencies are not specified in the mods list: {0:?}")]
-    MissingRequiredDependencies(Vec<String>),
+    #[error(
+        "Required dependencies are not specified in the mods list: {}",
+        .0.iter().join(", ")
+    )]
+    MissingRequiredDependencies(Vec<MissingDependency>),
     #[error("Expected Minecraft version {expected}, but got {actual:?}")]
     MinecraftVersionMismatch {
         expected: String,
@@ -54,39 +86,212 @@ pub enum ModVerificationError {
     },
     #[error("Error loading dependency {0}: {1}")]
     DependencyLoading(String, #[source] ModLoadingError),
+    #[error("{0}")]
+    ProjectList(#[from] ProjectListViolation),
+    #[error("Mods from this site are not allowed by the policy '{0}'")]
+    SiteNotAllowed(String),
+    #[error("The license {license} is not allowed by the policy '{policy}'")]
+    LicenseNotAllowed { license: String, policy: String },
+    #[error("The site doesn't report the license, which the policy '{0}' requires in strict mode")]
+    UnknownLicense(String),
+    #[error("Warnings are not allowed in strict mode: {0:?}")]
+    StrictWarnings(Vec<String>),
+    #[error("The mod is unsupported on both the client and the server, so it's never installed")]
+    NeverInstalled,
+    #[error("Its optional dependency {0} is in `include_optional_deps`, but has no version for the pack")]
+    NoVersionForIncludedDependency(String),
+}
+
+impl ModVerificationError {
+    /// A stable identifier for the kind of error, for machine-readable output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ModVerificationError::Loading(_) => "loading",
+            ModVerificationError::DistributionDenied => "distribution_denied",
+            ModVerificationError::MissingRequiredDependencies(_) => "missing_required_dependencies",
+            ModVerificationError::MinecraftVersionMismatch { .. } => "minecraft_version_mismatch",
+            ModVerificationError::DependencyLoading(..) => "dependency_loading",
+            ModVerificationError::ProjectList(_) => "project_list",
+            ModVerificationError::SiteNotAllowed(_) => "site_not_allowed",
+            ModVerificationError::LicenseNotAllowed { .. } => "license_not_allowed",
+            ModVerificationError::UnknownLicense(_) => "unknown_license",
+            ModVerificationError::StrictWarnings(_) => "strict_warnings",
+            ModVerificationError::NeverInstalled => "never_installed",
+            ModVerificationError::NoVersionForIncludedDependency(_) => {
+                "no_version_for_included_dependency"
+            }
+        }
+    }
+
+    /// A name for failures of this kind, and a hint on how to fix them.
+    fn summary(&self) -> (&'static str, &'static str) {
+        match self {
+            ModVerificationError::Loading(_) => (
+                "Mods that failed to load",
+                "check their IDs, and run `netherfire doctor` to check access to the sites",
+            ),
+            ModVerificationError::DistributionDenied => (
+                "Distribution denials",
+                "download them yourself and put them in `overrides/mods/`, or use their versions \
+                from Modrinth",
+            ),
+            ModVerificationError::MissingRequiredDependencies(_) => (
+                "Missing dependencies",
+                "add them to the config, or to `ignored_deps` of the mod if they aren't needed",
+            ),
+            ModVerificationError::MinecraftVersionMismatch { .. } => (
+                "Minecraft version mismatches",
+                "pick versions for the pack's Minecraft version, or set `game_version` on mods \
+                known to work with it",
+            ),
+            ModVerificationError::DependencyLoading(..) => (
+                "Dependencies that failed to load",
+                "add them to `ignored_deps` of the mod if they don't exist anymore",
+            ),
+            ModVerificationError::ProjectList(_) => (
+                "Project list violations",
+                "remove the mods, or change the project lists",
+            ),
+            ModVerificationError::SiteNotAllowed(_) => (
+                "Sites not allowed by the policy",
+                "use the mods from a site the policy allows",
+            ),
+            ModVerificationError::LicenseNotAllowed { .. } => (
+                "Licenses not allowed by the policy",
+                "replace the mods, or allow their licenses in the policy",
+            ),
+            ModVerificationError::UnknownLicense(_) => (
+                "Unknown licenses",
+                "replace the mods, or check their licenses and turn off strict mode",
+            ),
+            ModVerificationError::StrictWarnings(_) => (
+                "Warnings in strict mode",
+                "set `client` and `server` of the mods to match the site, or turn off strict mode",
+            ),
+            ModVerificationError::NeverInstalled => (
+                "Mods that are never installed",
+                "set `client` or `server` of the mods to `required` or `optional`, or remove them",
+            ),
+            ModVerificationError::NoVersionForIncludedDependency(_) => (
+                "Included optional dependencies without a version for the pack",
+                "add a version of them to the config, or remove them from `include_optional_deps`",
+            ),
+        }
+    }
+}
+
+/// A required dependency of a mod that isn't in the config.
+#[derive(Debug)]
+pub struct MissingDependency {
+    name: String,
+    slug: String,
+    id: String,
+    /// What to pass to `add-mods` to add it.
+    add_mods_arg: String,
+}
+
+impl Display for MissingDependency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}, {})", self.name, self.slug, self.id)
+    }
 }
 
 #[derive(Debug)]
 pub struct ModsVerificationError {
-    pub failures: HashMap<String, ModVerificationError>,
+    /// Failures by site config key (e.g. `modrinth`) and mod config key.
+    pub failures: HashMap<(&'static str, String), ModVerificationError>,
 }
 
 impl Error for ModsVerificationError {}
 
+impl ModsVerificationError {
+    pub fn failure_reports(&self) -> Vec<FailureReport> {
+        failure_reports(&self.failures, ModVerificationError::code)
+    }
+}
+
+impl Serialize for ModsVerificationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.failure_reports().serialize(serializer)
+    }
+}
+
 impl Display for ModsVerificationError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut failures_vec = self.failures.iter().collect::<Vec<_>>();
         failures_vec.sort_by_key(|(k, _)| (*k).clone());
-        for (k, error) in failures_vec {
-            writeln!(f, "Mod {}: {}", k, error)?;
+        for ((site, k), error) in failures_vec {
+            writeln!(f, "[{}] Mod {}: {}", site, k, error)?;
+        }
+
+        let mut by_kind = BTreeMap::<(&str, &str), usize>::new();
+        let mut missing_deps = BTreeMap::<&str, BTreeSet<&str>>::new();
+        for ((site, _), error) in &self.failures {
+            *by_kind.entry(error.summary()).or_default() += 1;
+            if let ModVerificationError::MissingRequiredDependencies(deps) = error {
+                missing_deps
+                    .entry(site)
+                    .or_default()
+                    .extend(deps.iter().map(|d| d.add_mods_arg.as_str()));
+            }
+        }
+        writeln!(f, "Summary:")?;
+        for ((name, hint), count) in by_kind
+            .into_iter()
+            .sorted_by_key(|(kind, count)| (std::cmp::Reverse(*count), *kind))
+        {
+            writeln!(f, "- {} ({}): {}", name, count, hint)?;
+        }
+        for (site, args) in missing_deps {
+            writeln!(
+                f,
+                "  To add the missing dependencies from {}: netherfire add-mods <source directory> \
+                {} {}",
+                site,
+                site,
+                args.iter().join(" ")
+            )?;
         }
 
         Ok(())
     }
 }
 
+/// Verify the mods of a pack. With `only`, only the given mods are verified, and the others are
+/// left out of the result.
 pub(crate) async fn verify_mods(
-    pack_config: PackConfig<ConfigModContainer>,
+    mut pack_config: PackConfig<ConfigModContainer>,
+    project_lists: ProjectLists,
+    policy: Option<&LoadedPolicy>,
+    only: Option<&ChangedMods>,
 ) -> Result<PackConfig<VerifiedModContainer>, ModsVerificationError> {
+    emit(ProgressEvent::PhaseStarted {
+        phase: Phase::Verification,
+    });
+    let settings = PackSettings {
+        minecraft_version: pack_config.minecraft_version.clone(),
+        policy: policy.cloned(),
+        strict: pack_config.strict || policy.is_some_and(|p| p.policy.require_strict),
+        minecraft_version_check: pack_config.minecraft_version_check,
+        defaults: pack_config.defaults,
+        mod_loader: pack_config.mod_loader.id.clone(),
+        prefer_featured: pack_config.prefer_featured,
+        version_types: pack_config.version_types.clone(),
+        include_optional_deps: pack_config.include_optional_deps.clone(),
+    };
     let cf_verify = tokio::spawn(verify_mods_site(
-        pack_config.minecraft_version.clone(),
-        pack_config.mods.curseforge,
+        settings.clone(),
+        std::mem::take(&mut pack_config.mods.curseforge),
+        project_lists.curseforge,
+        only.map(|o| o.curseforge.clone()),
         CurseForge,
     ));
 
     let modrinth_verify = tokio::spawn(verify_mods_site(
-        pack_config.minecraft_version.clone(),
-        pack_config.mods.modrinth,
+        settings,
+        std::mem::take(&mut pack_config.mods.modrinth),
+        project_lists.modrinth,
+        only.map(|o| o.modrinth.clone()),
         Modrinth,
     ));
 
@@ -102,11 +307,11 @@ pub(crate) async fn verify_mods(
             let mut failures = HashMap::new();
 
             if let Err(e) = cf_result {
-                failures.extend(e);
+                failures.extend(e.into_iter().map(|(k, v)| ((CurseForge::CONFIG_KEY, k), v)));
             }
 
             if let Err(e) = modrinth_result {
-                failures.extend(e);
+                failures.extend(e.into_iter().map(|(k, v)| ((Modrinth::CONFIG_KEY, k), v)));
             }
 
             return Err(ModsVerificationError { failures });
@@ -114,21 +319,32 @@ pub(crate) async fn verify_mods(
     };
 
     log::info!("{}", "Verified mods successfully.".errstyle(SUCCESS_STYLE));
+    emit(ProgressEvent::PhaseCompleted {
+        phase: Phase::Verification,
+    });
 
-    Ok(PackConfig {
-        name: pack_config.name,
-        description: pack_config.description,
-        author: pack_config.author,
-        version: pack_config.version,
-        minecraft_version: pack_config.minecraft_version,
-        mod_loader: pack_config.mod_loader,
-        mods: mod_container,
-    })
+    Ok(pack_config.with_mods(mod_container))
 }
 
-async fn verify_mods_site<K, S>(
+/// The settings of a pack that apply to every mod.
+#[derive(Clone)]
+struct PackSettings {
     minecraft_version: String,
+    policy: Option<LoadedPolicy>,
+    strict: bool,
+    minecraft_version_check: MinecraftVersionCheck,
+    defaults: SideDefaults,
+    mod_loader: ModLoaderType,
+    prefer_featured: bool,
+    version_types: Vec<VersionType>,
+    include_optional_deps: Vec<String>,
+}
+
+async fn verify_mods_site<K, S>(
+    settings: PackSettings,
     mods: HashMap<String, ConfigMod<K>>,
+    project_lists: SiteProjectLists,
+    only: Option<HashSet<String>>,
     site: S,
 ) -> Result<HashMap<String, VerifiedMod<S>>, HashMap<String, ModVerificationError>>
 where
@@ -136,12 +352,30 @@ where
     S: ModSite<Id = K>,
     S::ModHash: Clone + Send + Sync + 'static,
 {
+    let mods = include_optional_deps(&settings, mods, site).await?;
+    let PackSettings {
+        minecraft_version,
+        policy,
+        strict,
+        minecraft_version_check,
+        defaults,
+        ..
+    } = settings;
     let mut mods_by_project_id = HashSet::with_capacity(mods.len());
     let mut mods_by_version_id = HashSet::with_capacity(mods.len());
+    let mut configured_mods = HashMap::with_capacity(mods.len() * 2);
     let mut verifications = Vec::with_capacity(mods.len());
     for (k, m) in mods.into_iter().sorted_by_key(|(k, _)| k.to_string()) {
         mods_by_project_id.insert(m.source.project_id.clone());
         mods_by_version_id.insert(m.source.version_id.clone());
+        configured_mods.insert(
+            DependencyId::Project(m.source.project_id.clone()),
+            k.clone(),
+        );
+        configured_mods.insert(
+            DependencyId::Version(m.source.version_id.clone()),
+            k.clone(),
+        );
         // Include the ignored mods in the mods_by* tables to skip them.
         for ignored_mod in m.ignored_deps.iter() {
             match ignored_mod.clone() {
@@ -154,67 +388,60 @@ where
             }
         }
 
+        // Skipped mods still count as configured for the dependency checks of the others.
+        if only.as_ref().is_some_and(|o| !o.contains(&k)) {
+            continue;
+        }
+
         let id = m.source.clone();
-        verifications.push((k, m, submit_load(id, site)));
+        emit(ProgressEvent::VerificationStarted {
+            site: S::CONFIG_KEY,
+            key: k.clone(),
+        });
+        let file = m.file.clone();
+        verifications.push((k, m, submit_load(id, file, site)));
     }
-    let mut verification_results = HashMap::with_capacity(verifications.len());
+    let context = SiteVerificationContext {
+        minecraft_version,
+        project_lists,
+        policy,
+        strict,
+        minecraft_version_check,
+        defaults,
+        mods_by_project_id,
+        mods_by_version_id,
+        configured_mods,
+    };
+    // Mods are checked as their files come in, so slow lookups of one mod's dependencies don't
+    // hold up the others.
+    let context = &context;
+    let results = futures::future::join_all(verifications.into_iter().map(
+        |(cfg_id, m, load)| async move {
+            let result = verify_loaded_mod(context, &cfg_id, m, load, site).await;
+            match &result {
+                Ok(verified) => emit(ProgressEvent::ModVerified {
+                    site: S::CONFIG_KEY,
+                    key: cfg_id.clone(),
+                    name: verified.info.project_info.name.clone(),
+                    slug: verified.info.project_info.slug.clone(),
+                }),
+                Err(_) => emit(ProgressEvent::ModFailed {
+                    site: S::CONFIG_KEY,
+                    key: cfg_id.clone(),
+                }),
+            }
+            (cfg_id, result)
+        },
+    ))
+    .await;
+    let mut verification_results = HashMap::with_capacity(results.len());
     let mut failures = HashMap::new();
-    for (cfg_id, m, verification_ftr) in verifications {
-        let failure = match verification_ftr.await.expect("tokio failure") {
-            Err(e) => Err(e.into()),
-            Ok(loaded_mod) => verify_mod(
-                &minecraft_version,
-                &mods_by_project_id,
-                &mods_by_version_id,
-                &cfg_id,
-                loaded_mod.clone(),
-                &site,
-            )
-            .await
-            .map(|_| loaded_mod),
-        };
-        match failure {
-            Ok(mod_info) => {
-                log::info!(
-                    "[{}] Mod {} (in config: {}) verified.",
-                    S::NAME.errstyle(SITE_NAME_STYLE),
-                    mod_info.project_info.name.errstyle(SITE_VAL_STYLE),
-                    cfg_id.errstyle(CONFIG_VAL_STYLE)
-                );
-
-                let map_env = |side: &'static str,
-                               cfg_env: EnvRequirement,
-                               site_env: EnvRequirement|
-                 -> KnownEnvRequirement {
-                    let (ret, warning) = compute_env(cfg_env, site_env);
-                    if let Some(warning) = warning {
-                        log::warn!(
-                            "Warning about env requirement for {} on side {}: {}",
-                            cfg_id.errstyle(CONFIG_VAL_STYLE),
-                            side,
-                            warning
-                        );
-                    }
-                    ret
-                };
-
-                let client = map_env("client", m.client, mod_info.project_info.side_info.client);
-                let server = map_env("server", m.server, mod_info.project_info.side_info.server);
-                verification_results.insert(
-                    cfg_id,
-                    VerifiedMod {
-                        source: m.source,
-                        info: mod_info,
-                        env_requirements: KnownEnvRequirements { client, server },
-                    },
-                );
+    for (cfg_id, result) in results {
+        match result {
+            Ok(verified) => {
+                verification_results.insert(cfg_id, verified);
             }
             Err(failure) => {
-                log::info!(
-                    "[{}] Mod (in config: {}) FAILED verification.",
-                    S::NAME.errstyle(SITE_NAME_STYLE),
-                    cfg_id.errstyle(CONFIG_VAL_STYLE)
-                );
                 failures.insert(cfg_id, failure);
             }
         }
@@ -226,84 +453,365 @@ where
     }
 }
 
-async fn verify_mod<K, H, S>(
-    minecraft_version: &String,
-    mods_by_project_id: &HashSet<K>,
-    mods_by_version_id: &HashSet<K>,
-    cfg_id: &str,
-    loaded_mod: ModFileInfo<K, H>,
-    site: &S,
-) -> Result<(), ModVerificationError>
+/// Add the optional dependencies of the mods that the pack includes with `include_optional_deps`
+/// and that aren't configured yet, at their latest version for the pack, keyed by their slug. The
+/// added mods get their included optional dependencies as well.
+async fn include_optional_deps<K, S>(
+    settings: &PackSettings,
+    mut mods: HashMap<String, ConfigMod<K>>,
+    site: S,
+) -> Result<HashMap<String, ConfigMod<K>>, HashMap<String, ModVerificationError>>
 where
     K: ModIdValue,
     S: ModSite<Id = K>,
+    S::ModHash: Clone + Send + Sync + 'static,
 {
-    if !loaded_mod.project_info.distribution_allowed {
-        return Err(ModVerificationError::DistributionDenied);
+    if settings.include_optional_deps.is_empty() {
+        return Ok(mods);
     }
-    // Verify that the MC version matches
-    if !loaded_mod.minecraft_versions.contains(minecraft_version) {
-        return Err(ModVerificationError::MinecraftVersionMismatch {
-            expected: minecraft_version.clone(),
-            actual: loaded_mod.minecraft_versions,
-        });
-    }
-    // Verify that all dependencies are specified.
-    let mut missing_deps = Vec::new();
-    for dep in loaded_mod.dependencies {
-        match dep.kind {
-            ModDependencyKind::Required => {
-                match get_dep_name_if_missing(
-                    site,
-                    dep.id.clone(),
-                    mods_by_project_id,
-                    mods_by_version_id,
-                )
-                .await
+    let mut pending = mods.keys().cloned().sorted().collect::<Vec<_>>();
+    let mut failures = HashMap::new();
+    while !pending.is_empty() {
+        let loads = pending
+            .drain(..)
+            .map(|k| {
+                let m = &mods[&k];
+                (k, submit_load(m.source.clone(), m.file.clone(), site))
+            })
+            .collect::<Vec<_>>();
+        for (cfg_id, load) in loads {
+            // Mods that fail to load fail their verification.
+            let Ok((_, info)) = load.await.expect("tokio failure") else {
+                continue;
+            };
+            for dep in info.dependencies {
+                let DependencyId::Project(project_id) = dep.id else {
+                    continue;
+                };
+                if dep.kind != ModDependencyKind::Optional
+                    || mods.values().any(|m| m.source.project_id == project_id)
                 {
-                    Ok(Some(v)) => missing_deps.push(format!("{} ({:?})", v, dep.id)),
-                    Ok(None) => {}
-                    Err(e) => {
-                        return Err(ModVerificationError::DependencyLoading(
-                            format!("{:?}", dep.id),
-                            e,
-                        ));
-                    }
+                    continue;
                 }
-            }
-            ModDependencyKind::Optional => {
-                match get_dep_name_if_missing(
-                    site,
-                    dep.id.clone(),
-                    mods_by_project_id,
-                    mods_by_version_id,
-                )
-                .await
-                {
-                    Ok(Some(v)) => {
+                match include_optional_dep(settings, project_id, site).await {
+                    Ok(Some((dep_info, source))) => {
+                        let key = (1..)
+                            .map(|i| match i {
+                                1 => dep_info.slug.clone(),
+                                _ => format!("{}-{}", dep_info.slug, i),
+                            })
+                            .find(|k| !mods.contains_key(k))
+                            .expect("some key is free");
                         log::info!(
-                            "[{}] [{}] Missing optional dependency for {}: {} (ID: {:?})",
+                            "[{}] Including optional dependency {} of {} as {} (version {}).",
                             S::NAME.errstyle(SITE_NAME_STYLE),
-                            "FYI".errstyle(|s| s.bold().yellow()),
+                            dep_info.name.errstyle(SITE_VAL_STYLE),
                             cfg_id.errstyle(CONFIG_VAL_STYLE),
-                            v.errstyle(SITE_VAL_STYLE),
-                            dep.id.errstyle(CONFIG_VAL_STYLE),
+                            key.errstyle(CONFIG_VAL_STYLE),
+                            source.version_id.errstyle(SITE_VAL_STYLE),
                         );
+                        mods.insert(key.clone(), ConfigMod::new(source));
+                        pending.push(key);
                     }
                     Ok(None) => {}
                     Err(e) => {
-                        log::warn!(
-                            "[{}] Error loading optional dependency for {}, dependency ID = {:?}: {}",
-                            S::NAME.errstyle(SITE_NAME_STYLE),
-                            cfg_id.errstyle(CONFIG_VAL_STYLE),
-                            dep.id.errstyle(CONFIG_VAL_STYLE),
-                            e,
-                        );
+                        failures.insert(cfg_id.clone(), e);
                     }
                 }
             }
-            _ => {}
+        }
+    }
+    if failures.is_empty() {
+        Ok(mods)
+    } else {
+        Err(failures)
+    }
+}
+
+/// The info and latest version for the pack of an optional dependency, if the pack includes it.
+async fn include_optional_dep<K, S>(
+    settings: &PackSettings,
+    project_id: K,
+    site: S,
+) -> Result<Option<(ModInfo, ModId<K>)>, ModVerificationError>
+where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+{
+    let dep_error = |e| ModVerificationError::DependencyLoading(project_id.to_string(), e);
+    let info = site
+        .load_metadata(project_id.clone())
+        .await
+        .map_err(dep_error)?;
+    let id = project_id.to_string();
+    if !settings
+        .include_optional_deps
+        .iter()
+        .any(|d| *d == info.slug || *d == id)
+    {
+        return Ok(None);
+    }
+    let version = site
+        .get_latest_version_for_pack(
+            project_id.clone(),
+            &settings.minecraft_version,
+            &settings.mod_loader,
+            false,
+            settings.prefer_featured,
+            &settings.version_types,
+        )
+        .await
+        .map_err(dep_error)?
+        .ok_or_else(|| ModVerificationError::NoVersionForIncludedDependency(info.slug.clone()))?;
+    Ok(Some((info, version)))
+}
+
+/// Pack-wide state shared by the verification of every mod from a site.
+struct SiteVerificationContext<K> {
+    minecraft_version: String,
+    project_lists: SiteProjectLists,
+    policy: Option<LoadedPolicy>,
+    strict: bool,
+    minecraft_version_check: MinecraftVersionCheck,
+    defaults: SideDefaults,
+    mods_by_project_id: HashSet<K>,
+    mods_by_version_id: HashSet<K>,
+    /// The config keys of the configured mods, by their project and version IDs.
+    configured_mods: HashMap<DependencyId<K>, String>,
+}
+
+/// Verify a mod once its file is loaded, and load its server file, if configured.
+async fn verify_loaded_mod<K, S>(
+    context: &SiteVerificationContext<K>,
+    cfg_id: &str,
+    m: ConfigMod<K>,
+    load: JoinHandle<MatchingFileLoadingResult<K, S::ModHash>>,
+    site: S,
+) -> Result<VerifiedMod<S>, ModVerificationError>
+where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+    S::ModHash: Clone + Send + Sync + 'static,
+{
+    let (file_id, mut mod_info) = load.await.expect("tokio failure")?;
+    verify_mod(
+        context,
+        &m.source.project_id,
+        m.game_version
+            .as_ref()
+            .unwrap_or(&context.minecraft_version),
+        cfg_id,
+        mod_info.clone(),
+        &site,
+    )
+    .await?;
+    if !m.allow_embedded_duplicates {
+        check_embedded_deps::<K, S>(context, cfg_id, &mod_info.dependencies);
+    }
+    let server_info = match &m.server_version_id {
+        Some(server_version_id) => {
+            let id = ModId {
+                project_id: m.source.project_id.clone(),
+                version_id: server_version_id.clone(),
+            };
+            let (_, info) = submit_load(id, None, site).await.expect("tokio failure")?;
+            Some(info)
+        }
+        None => None,
+    };
+    if let Some(download_url) = &m.download_url {
+        log::debug!(
+            "Using download URL {} for {} instead of {}",
+            download_url,
+            cfg_id,
+            mod_info.url
+        );
+        mod_info.url = download_url.clone();
+    }
+    let mut strict_warnings = Vec::new();
+    let mut map_env = |side: &'static str,
+                       cfg_env: EnvRequirement,
+                       site_env: EnvRequirement,
+                       default: KnownEnvRequirement|
+     -> KnownEnvRequirement {
+        let (ret, warning) = compute_env(cfg_env, site_env, default);
+        if let Some(warning) = warning {
+            if context.strict {
+                strict_warnings.push(format!("side {}: {}", side, warning));
+            } else {
+                warn(Warning::for_mod(
+                    WarningKind::EnvRequirement,
+                    S::CONFIG_KEY,
+                    cfg_id,
+                    format!("side {}: {}", side, warning),
+                ));
+            }
+        }
+        ret
+    };
+
+    let client = map_env(
+        "client",
+        m.client,
+        mod_info.project_info.side_info.client,
+        context.defaults.client,
+    );
+    let server = map_env(
+        "server",
+        m.server,
+        mod_info.project_info.side_info.server,
+        context.defaults.server,
+    );
+    if !strict_warnings.is_empty() {
+        return Err(ModVerificationError::StrictWarnings(strict_warnings));
+    }
+    if client == KnownEnvRequirement::Unsupported && server == KnownEnvRequirement::Unsupported {
+        return Err(ModVerificationError::NeverInstalled);
+    }
+    Ok(VerifiedMod {
+        file_id: (file_id != m.source).then_some(file_id.version_id),
+        source: m.source,
+        info: mod_info,
+        server_info,
+        env_requirements: KnownEnvRequirements { client, server },
+        note: m.note,
+    })
+}
+
+/// Warn about the mods of the config that the mod embeds, as they end up on the classpath twice.
+fn check_embedded_deps<K, S>(
+    context: &SiteVerificationContext<K>,
+    cfg_id: &str,
+    dependencies: &[ModDependency<K>],
+) where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+{
+    for dep in dependencies {
+        if dep.kind != ModDependencyKind::Embedded {
+            continue;
+        }
+        match context.configured_mods.get(&dep.id) {
+            Some(embedded) if embedded != cfg_id => warn(Warning::for_mod(
+                WarningKind::EmbeddedDependency,
+                S::CONFIG_KEY,
+                cfg_id,
+                format!(
+                    "Embeds {}, which is in the config as well, so it's on the classpath twice. \
+                    Remove {}, or set `allow_embedded_duplicates` on this mod if that's intended",
+                    embedded, embedded
+                ),
+            )),
+            _ => log::debug!("{} embeds {:?}", cfg_id, dep.id),
+        }
+    }
+}
+
+async fn verify_mod<K, H, S>(
+    context: &SiteVerificationContext<K>,
+    project_id: &K,
+    minecraft_version: &str,
+    cfg_id: &str,
+    loaded_mod: ModFileInfo<K, H>,
+    site: &S,
+) -> Result<(), ModVerificationError>
+where
+    K: ModIdValue,
+    S: ModSite<Id = K>,
+{
+    context
+        .project_lists
+        .check(&project_id.to_string(), &loaded_mod.project_info.slug)?;
+    if let Some(policy) = &context.policy {
+        check_policy(policy, context.strict, cfg_id, S::CONFIG_KEY, &loaded_mod)?;
+    }
+    if !loaded_mod.project_info.distribution_allowed {
+        return Err(ModVerificationError::DistributionDenied);
+    }
+    // Verify that the MC version matches
+    let version_check = context.minecraft_version_check;
+    if version_check != MinecraftVersionCheck::Off
+        && !loaded_mod.is_for_minecraft_version(minecraft_version)
+    {
+        let mismatch = ModVerificationError::MinecraftVersionMismatch {
+            expected: minecraft_version.to_string(),
+            actual: loaded_mod.minecraft_versions.clone(),
         };
+        if version_check == MinecraftVersionCheck::Strict || context.strict {
+            return Err(mismatch);
+        }
+        warn(Warning::for_mod(
+            WarningKind::MinecraftVersion,
+            S::CONFIG_KEY,
+            cfg_id,
+            mismatch.to_string(),
+        ));
+    }
+    // Verify that all dependencies are specified. The dependencies are looked up together.
+    let deps = loaded_mod
+        .dependencies
+        .into_iter()
+        .filter(|dep| {
+            matches!(
+                dep.kind,
+                ModDependencyKind::Required | ModDependencyKind::Optional
+            )
+        })
+        .collect::<Vec<_>>();
+    let dep_infos = futures::future::join_all(deps.iter().map(|dep| async {
+        let _guard = CONCURRENCY_LIMITER.acquire().await.expect("tokio failure");
+        get_dep_info_if_missing(
+            site,
+            dep.id.clone(),
+            &context.mods_by_project_id,
+            &context.mods_by_version_id,
+        )
+        .await
+    }))
+    .await;
+    let mut missing_deps = Vec::new();
+    for (dep, dep_info) in deps.into_iter().zip(dep_infos) {
+        match dep.kind {
+            ModDependencyKind::Required => match dep_info {
+                Ok(Some(v)) => missing_deps.push(MissingDependency {
+                    add_mods_arg: match &dep.id {
+                        DependencyId::Project(project_id) => project_id.to_string(),
+                        // Only Modrinth has version dependencies, and it accepts slugs.
+                        DependencyId::Version(_) => v.slug.clone(),
+                    },
+                    id: format!("{:?}", dep.id),
+                    name: v.name,
+                    slug: v.slug,
+                }),
+                Ok(None) => {}
+                Err(e) => {
+                    return Err(ModVerificationError::DependencyLoading(
+                        format!("{:?}", dep.id),
+                        e,
+                    ));
+                }
+            },
+            ModDependencyKind::Optional => match dep_info {
+                Ok(Some(v)) => warn(Warning::for_mod(
+                    WarningKind::OptionalDependency,
+                    S::CONFIG_KEY,
+                    cfg_id,
+                    format!(
+                        "Missing optional dependency {} ({}, ID: {:?}), add `{}` to \
+                        `include_optional_deps` to include it",
+                        v.name, v.slug, dep.id, v.slug
+                    ),
+                )),
+                Ok(None) => {}
+                Err(e) => warn(Warning::for_mod(
+                    WarningKind::OptionalDependency,
+                    S::CONFIG_KEY,
+                    cfg_id,
+                    format!("Error loading optional dependency {:?}: {}", dep.id, e),
+                )),
+            },
+            ModDependencyKind::Embedded | ModDependencyKind::Other => {}
+        }
     }
     if !missing_deps.is_empty() {
         return Err(ModVerificationError::MissingRequiredDependencies(
@@ -314,12 +822,51 @@ where
     Ok(())
 }
 
-async fn get_dep_name_if_missing<K, S>(
+/// Check a mod against the site and license rules of a policy.
+fn check_policy<K, H>(
+    policy: &LoadedPolicy,
+    strict: bool,
+    cfg_id: &str,
+    site: &str,
+    loaded_mod: &ModFileInfo<K, H>,
+) -> Result<(), ModVerificationError> {
+    if !policy.site_allowed(site) {
+        return Err(ModVerificationError::SiteNotAllowed(policy.source.clone()));
+    }
+    let Some(allowed_licenses) = &policy.policy.allowed_licenses else {
+        return Ok(());
+    };
+    match &loaded_mod.project_info.license {
+        Some(license) if !allowed_licenses.contains(license) => {
+            Err(ModVerificationError::LicenseNotAllowed {
+                license: license.clone(),
+                policy: policy.source.clone(),
+            })
+        }
+        Some(_) => Ok(()),
+        None if strict => Err(ModVerificationError::UnknownLicense(policy.source.clone())),
+        None => {
+            warn(Warning::for_mod(
+                WarningKind::License,
+                site,
+                cfg_id,
+                format!(
+                    "The license is unknown, so it can't be checked against the policy '{}'",
+                    policy.source
+                ),
+            ));
+            Ok(())
+        }
+    }
+}
+
+/// Load the metadata of a dependency, if it isn't in the config.
+async fn get_dep_info_if_missing<K, S>(
     site: &S,
     id: DependencyId<K>,
     mods_by_project_id: &HashSet<K>,
     mods_by_version_id: &HashSet<K>,
-) -> Result<Option<String>, ModLoadingError>
+) -> Result<Option<ModInfo>, ModLoadingError>
 where
     K: ModIdValue,
     S: ModSite<Id = K>,
@@ -327,7 +874,7 @@ where
     match id {
         DependencyId::Project(project_id) => {
             if !(mods_by_project_id.contains(&project_id)) {
-                site.load_metadata(project_id).await.map(|v| Some(v.name))
+                site.load_metadata(project_id).await.map(Some)
             } else {
                 Ok(None)
             }
@@ -336,7 +883,7 @@ where
             if !mods_by_version_id.contains(&version_id) {
                 site.load_metadata_by_version(version_id).await
                     .expect("sites that provide only a version in dependencies must allow lookup by version")
-                    .map(|v| Some(v.name))
+                    .map(Some)
             } else {
                 Ok(None)
             }
@@ -344,18 +891,28 @@ where
     }
 }
 
-fn submit_load<K, H>(
+/// Limits the requests to the sites for loading mods and their dependencies.
+static CONCURRENCY_LIMITER: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(5));
+
+/// Load the file of a mod in the background. If `file` is set, the file of the version matching
+/// it is loaded, and its ID is returned with it.
+pub(crate) fn submit_load<K, H>(
     mod_id: ModId<K>,
+    file: Option<NamePattern>,
     site: impl ModSite<Id = K, ModHash = H>,
-) -> JoinHandle<ModFileLoadingResult<K, H>>
+) -> JoinHandle<MatchingFileLoadingResult<K, H>>
 where
     K: ModIdValue,
     H: Send + Sync + 'static,
 {
-    static CONCURRENCY_LIMITER: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(5));
-
     tokio::task::spawn(async move {
         let _guard = CONCURRENCY_LIMITER.acquire().await.expect("tokio failure");
-        site.load_file(mod_id).await
+        match file {
+            Some(pattern) => site.load_matching_file(mod_id, &pattern).await,
+            None => site
+                .load_file(mod_id.clone())
+                .await
+                .map(|info| (mod_id, info)),
+        }
     })
 }
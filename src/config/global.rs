@@ -1,34 +1,203 @@
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
 use directories::ProjectDirs;
-use ferinth::Ferinth;
-use furse::Furse;
 use once_cell::sync::Lazy;
 use serde::Deserialize;
+use thiserror::Error;
+
+use crate::add_mods::KeyStrategy;
+use crate::api::{
+    parse_base_url, CurseForgeApi, ModrinthApi, DEFAULT_CURSEFORGE_API_URL,
+    DEFAULT_MODRINTH_API_URL,
+};
+use crate::import::ImportLimits;
+use crate::retry::RetryPolicy;
 
 pub static DIRS: Lazy<ProjectDirs> = Lazy::new(|| {
     ProjectDirs::from("net.octyl", "Octavia Togami", "netherfire")
         .expect("Couldn't load project directories")
 });
 
-pub static CONFIG: Lazy<GlobalConfig> = Lazy::new(|| {
-    let config_file = DIRS.config_dir().join("config.toml");
-    let config_text = std::fs::read_to_string(&config_file)
-        .unwrap_or_else(|e| panic!("Failed to read {}: {}", config_file.display(), e));
-    toml::from_str(&config_text)
-        .unwrap_or_else(|e| panic!("Failed to parse {}: {}", config_file.display(), e))
+/// What to put in a new global config.
+pub const GLOBAL_CONFIG_TEMPLATE: &str = "\
+# Get an API key at https://console.curseforge.com/
+curse_forge_api_key = \"<your key>\"
+";
+
+/// The global config, if there is one, for settings that have sensible defaults.
+pub static OPTIONAL_CONFIG: Lazy<Option<GlobalConfig>> = Lazy::new(|| load_global_config().ok());
+
+pub static RETRY_POLICY: Lazy<RetryPolicy> = Lazy::new(|| {
+    OPTIONAL_CONFIG
+        .as_ref()
+        .map(|c| c.retry.clone())
+        .unwrap_or_default()
+});
+
+pub static IMPORT_LIMITS: Lazy<ImportLimits> = Lazy::new(|| {
+    OPTIONAL_CONFIG
+        .as_ref()
+        .map(|c| c.import_limits.clone())
+        .unwrap_or_default()
 });
 
-pub static FURSE: Lazy<Furse> = Lazy::new(|| Furse::new(&CONFIG.curse_forge_api_key));
-pub static FERINTH: Lazy<Ferinth> = Lazy::new(|| {
-    Ferinth::new(
-        env!("CARGO_CRATE_NAME"),
-        Some(env!("CARGO_PKG_VERSION")),
-        Some("Octavia Togami"),
-        None,
+/// The size in bytes from which mods are spooled to disk, see [GlobalConfig::spool_threshold_mib].
+pub static SPOOL_THRESHOLD: Lazy<u64> = Lazy::new(|| {
+    OPTIONAL_CONFIG
+        .as_ref()
+        .and_then(|c| c.spool_threshold_mib)
+        .unwrap_or(DEFAULT_SPOOL_THRESHOLD_MIB)
+        * 1024
+        * 1024
+});
+
+const DEFAULT_SPOOL_THRESHOLD_MIB: u64 = 32;
+
+/// The CurseForge API, or why it can't be used. Use [furse] to get it.
+static FURSE: Lazy<Result<CurseForgeApi, String>> = Lazy::new(|| {
+    let config = match load_global_config() {
+        Ok(config) => config,
+        Err(GlobalConfigLoadError::Io(_, e))
+            if e.kind() == std::io::ErrorKind::NotFound && std::io::stdin().is_terminal() =>
+        {
+            tokio::task::block_in_place(|| create_global_config(&global_config_file()))?
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+    let base_url = config
+        .curseforge_api_url
+        .as_deref()
+        .unwrap_or(DEFAULT_CURSEFORGE_API_URL);
+    let base_url = parse_base_url(base_url)
+        .map_err(|e| format!("Invalid CurseForge API URL '{}': {}", base_url, e))?;
+    Ok(CurseForgeApi::new(
+        HTTP_CLIENT.clone(),
+        base_url,
+        &config.curse_forge_api_key,
+        RETRY_POLICY.clone(),
+    ))
+});
+
+/// The CurseForge API. It needs an API key from the global config, so if there is none and this
+/// runs in a terminal, it first offers to create the global config.
+pub fn furse() -> Result<&'static CurseForgeApi, NoCurseForgeApi> {
+    FURSE
+        .as_ref()
+        .map_err(|e| NoCurseForgeApi(e.clone(), global_config_file().display().to_string()))
+}
+
+pub static FERINTH: Lazy<ModrinthApi> = Lazy::new(|| {
+    // Modrinth doesn't need an API key, so it works without a global config.
+    let base_url = OPTIONAL_CONFIG
+        .as_ref()
+        .and_then(|c| c.modrinth_api_url.as_deref())
+        .unwrap_or(DEFAULT_MODRINTH_API_URL);
+    ModrinthApi::new(
+        HTTP_CLIENT.clone(),
+        parse_base_url(base_url).expect("Invalid Modrinth API URL"),
+        OPTIONAL_CONFIG
+            .as_ref()
+            .and_then(|c| c.modrinth_token.clone()),
+        RETRY_POLICY.clone(),
     )
-    .expect("Failed to initialise Ferinth")
+});
+
+/// Client for all HTTP requests.
+pub static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent(concat!(
+            env!("CARGO_CRATE_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION"),
+            " (Octavia Togami)"
+        ))
+        .build()
+        .expect("Failed to initialise HTTP client")
 });
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct GlobalConfig {
     pub curse_forge_api_key: String,
+    /// Base URL of the CurseForge API, e.g. for a caching proxy.
+    #[serde(default)]
+    pub curseforge_api_url: Option<String>,
+    /// Base URL of the Modrinth API, e.g. for a caching proxy or `https://staging-api.modrinth.com/v2/`.
+    #[serde(default)]
+    pub modrinth_api_url: Option<String>,
+    /// A Modrinth personal access token, for private projects. It's sent to the Modrinth API and
+    /// with downloads from Modrinth's CDN.
+    #[serde(default)]
+    pub modrinth_token: Option<String>,
+    /// Mods at least this many MiB large are downloaded to temporary files in the cache directory
+    /// instead of into memory. Defaults to 32 MiB.
+    #[serde(default)]
+    pub spool_threshold_mib: Option<u64>,
+    /// How failed API requests and downloads are retried.
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Limits on the packs `add-mods` imports mods from.
+    #[serde(default)]
+    pub import_limits: ImportLimits,
+    /// How `add-mods` chooses config keys, unless overridden on the command line.
+    #[serde(default)]
+    pub key_strategy: KeyStrategy,
+}
+
+#[derive(Debug, Error)]
+pub enum GlobalConfigLoadError {
+    #[error("Failed to read {0}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("Failed to parse {0}: {1}")]
+    TomlParse(String, #[source] toml::de::Error),
+}
+
+#[derive(Debug, Error)]
+#[error(
+    "CurseForge needs an API key from the global config: {0}\n\
+    Create '{1}' containing:\n\n{GLOBAL_CONFIG_TEMPLATE}"
+)]
+pub struct NoCurseForgeApi(String, String);
+
+pub fn global_config_file() -> PathBuf {
+    DIRS.config_dir().join("config.toml")
+}
+
+pub fn load_global_config() -> Result<GlobalConfig, GlobalConfigLoadError> {
+    let config_file = global_config_file();
+    let config_text = std::fs::read_to_string(&config_file)
+        .map_err(|e| GlobalConfigLoadError::Io(config_file.display().to_string(), e))?;
+    toml::from_str(&config_text)
+        .map_err(|e| GlobalConfigLoadError::TomlParse(config_file.display().to_string(), e))
+}
+
+/// Ask for a CurseForge API key on the terminal and write a global config with it.
+fn create_global_config(config_file: &Path) -> Result<GlobalConfig, String> {
+    eprintln!(
+        "There is no global config at '{}', which is needed for the CurseForge API.",
+        config_file.display()
+    );
+    eprint!("Enter your CurseForge API key from https://console.curseforge.com/ to create it, or leave empty to cancel: ");
+    let mut key = String::new();
+    std::io::stdin()
+        .read_line(&mut key)
+        .map_err(|e| format!("Failed to read the API key: {}", e))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("there is no {}", config_file.display()));
+    }
+    let config_text = GLOBAL_CONFIG_TEMPLATE.replace(
+        "\"<your key>\"",
+        &toml::Value::String(key.to_string()).to_string(),
+    );
+    let write_config = || -> std::io::Result<()> {
+        if let Some(parent) = config_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(config_file, &config_text)
+    };
+    write_config().map_err(|e| format!("Failed to write {}: {}", config_file.display(), e))?;
+    eprintln!("Created {}.", config_file.display());
+    toml::from_str(&config_text)
+        .map_err(|e| format!("Failed to parse {}: {}", config_file.display(), e))
 }
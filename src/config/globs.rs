@@ -0,0 +1,68 @@
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+
+/// A list of glob patterns from a config, e.g. `["*.sh", "bin/**"]`, matched against paths
+/// relative to an override directory.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct Globs {
+    set: GlobSet,
+}
+
+impl Globs {
+    pub fn is_match(&self, path: &str) -> bool {
+        self.set.is_match(path)
+    }
+}
+
+impl TryFrom<Vec<String>> for Globs {
+    type Error = globset::Error;
+
+    fn try_from(patterns: Vec<String>) -> Result<Self, Self::Error> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(&pattern)?);
+        }
+        Ok(Self {
+            set: builder.build()?,
+        })
+    }
+}
+
+/// A single glob pattern from a config, matched against file names, e.g. `"*-fabric.jar"`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct NamePattern {
+    matcher: GlobMatcher,
+}
+
+impl NamePattern {
+    /// A pattern matching exactly the given name, with any glob syntax in it escaped.
+    pub fn literal(name: &str) -> Self {
+        Self {
+            matcher: Glob::new(&globset::escape(name))
+                .expect("an escaped name is a valid glob")
+                .compile_matcher(),
+        }
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.matcher.is_match(name)
+    }
+}
+
+impl std::fmt::Display for NamePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.matcher.glob())
+    }
+}
+
+impl TryFrom<String> for NamePattern {
+    type Error = globset::Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Ok(Self {
+            matcher: Glob::new(&pattern)?.compile_matcher(),
+        })
+    }
+}
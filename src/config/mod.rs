@@ -1,3 +1,9 @@
 pub(crate) mod global;
+pub(crate) mod globs;
 pub(crate) mod mods;
 pub(crate) mod pack;
+pub(crate) mod policy;
+pub(crate) mod project_lists;
+pub(crate) mod scripts;
+pub(crate) mod source;
+pub(crate) mod workspace;
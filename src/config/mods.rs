@@ -4,6 +4,7 @@ use std::fmt::Debug;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::config::globs::NamePattern;
 use crate::mod_site::{DependencyId, ModId, ModIdValue};
 
 #[derive(Debug, Clone, Deserialize)]
@@ -16,7 +17,7 @@ pub struct ConfigModContainer {
 }
 
 #[derive(Debug, Clone, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[serde(deny_unknown_fields, bound = "")]
 pub struct ConfigMod<K: ModIdValue> {
     #[serde(flatten)]
     pub source: ModId<K>,
@@ -27,27 +28,69 @@ pub struct ConfigMod<K: ModIdValue> {
     /// Dependencies to ignore when validating.
     #[serde(default)]
     pub ignored_deps: Vec<DependencyId<K>>,
+    /// URL to download the file from instead of the one provided by the site, e.g. a mirror. The
+    /// file must still match the hash provided by the site.
+    #[serde(default)]
+    pub download_url: Option<String>,
+    /// Version to use in the server base instead of `version_id`, e.g. a dedicated server file.
+    #[serde(default)]
+    pub server_version_id: Option<K>,
+    /// The Minecraft version the mod was picked for, if it isn't the pack's. The file is verified
+    /// against this version instead.
+    #[serde(default)]
+    pub game_version: Option<String>,
+    /// Whether the mod was picked regardless of the pack's mod loader, e.g. a datapack-style mod,
+    /// so it's updated the same way.
+    #[serde(default)]
+    pub ignore_mod_loader: bool,
+    /// Pattern of the name of the file to use, for versions with several files, e.g. the
+    /// alternate and additional files of a CurseForge file, or a Modrinth version with jars for
+    /// several mod loaders.
+    #[serde(default)]
+    pub file: Option<NamePattern>,
+    /// Don't warn when the mod embeds another mod of the config, e.g. if the embedded copy is
+    /// known to be skipped at runtime.
+    #[serde(default)]
+    pub allow_embedded_duplicates: bool,
+    /// A note on the mod, e.g. what it adds. Launchers show it to players choosing whether to
+    /// install the mod, if it's optional.
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl<K: ModIdValue> ConfigMod<K> {
+    /// A mod with only its ID configured, like one added without any options.
+    pub fn new(source: ModId<K>) -> Self {
+        Self {
+            source,
+            client: EnvRequirement::default(),
+            server: EnvRequirement::default(),
+            ignored_deps: Vec::new(),
+            download_url: None,
+            server_version_id: None,
+            game_version: None,
+            ignore_mod_loader: false,
+            file: None,
+            allow_embedded_duplicates: false,
+            note: None,
+        }
+    }
 }
 
-#[derive(Debug, Copy, Clone, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum EnvRequirement {
-    /// Inherit from the state defined by the mod site or [`Required`].
+    /// Inherit from the state defined by the mod site or the pack's [`SideDefaults`].
+    #[default]
     Unknown,
     Required,
     Optional,
     Unsupported,
 }
 
-impl Default for EnvRequirement {
-    fn default() -> Self {
-        Self::Unknown
-    }
-}
-
 // Warning -- this type is explicitly compatible with the Modrinth pack format, and should not be
 // changed incompatibly without adding a different type for the format.
-#[derive(Debug, Copy, Clone, Serialize, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum KnownEnvRequirement {
     Required,
@@ -55,6 +98,29 @@ pub enum KnownEnvRequirement {
     Unsupported,
 }
 
+/// The requirements of mods whose side neither the config nor the mod site declare.
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SideDefaults {
+    #[serde(default = "required")]
+    pub client: KnownEnvRequirement,
+    #[serde(default = "required")]
+    pub server: KnownEnvRequirement,
+}
+
+fn required() -> KnownEnvRequirement {
+    KnownEnvRequirement::Required
+}
+
+impl Default for SideDefaults {
+    fn default() -> Self {
+        Self {
+            client: required(),
+            server: required(),
+        }
+    }
+}
+
 impl KnownEnvRequirement {
     pub fn is_needed(&self, need_optional: bool) -> bool {
         match self {
@@ -77,17 +143,19 @@ pub enum ComputeEnvWarning {
     SiteAllowedButConfigUnsupported,
 }
 
-/// Given the env from the config and the site, compute the actual env. Never returns [`EnvRequirement::Unknown`].
+/// Given the env from the config and the site, compute the actual env. If neither knows it,
+/// `default` is used.
 pub fn compute_env(
     cfg_env: EnvRequirement,
     site_env: EnvRequirement,
+    default: KnownEnvRequirement,
 ) -> (KnownEnvRequirement, Option<ComputeEnvWarning>) {
     match cfg_env {
         EnvRequirement::Unknown => match site_env {
             EnvRequirement::Required => (KnownEnvRequirement::Required, None),
             EnvRequirement::Optional => (KnownEnvRequirement::Optional, None),
             EnvRequirement::Unsupported => (KnownEnvRequirement::Unsupported, None),
-            EnvRequirement::Unknown => (KnownEnvRequirement::Required, None),
+            EnvRequirement::Unknown => (default, None),
         },
         EnvRequirement::Required | EnvRequirement::Optional => {
             let warning = (site_env == EnvRequirement::Unsupported)
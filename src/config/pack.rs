@@ -1,18 +1,156 @@
+use std::num::NonZeroUsize;
+use std::path::{Component, Path};
+
+use clap::ValueEnum;
 use derive_more::Display;
 use serde::Deserialize;
 
+use crate::config::globs::Globs;
+use crate::config::mods::SideDefaults;
+use crate::output::{LIT_CLIENT_OVERRIDES, LIT_OVERRIDES, LIT_SERVER_OVERRIDES};
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct PackConfig<MC> {
     pub name: String,
     pub description: String,
     pub author: String,
+    /// The version of the pack. May contain the `{git_hash}` and `{git_branch}` placeholders.
     pub version: String,
     pub minecraft_version: String,
     pub mod_loader: ModLoader,
+    /// Further mod loaders the pack runs on top of its own, e.g. Fabric for a Forge pack running
+    /// Fabric mods through Sinytra Connector. They're listed in the manifests of the outputs, but
+    /// don't change which versions of mods are looked up.
+    #[serde(default)]
+    pub secondary_loaders: Vec<ModLoader>,
+    /// The Java version the pack needs at least, if it's higher than what its Minecraft version
+    /// needs, e.g. because of a mod.
+    #[serde(default)]
+    pub java_version: Option<u32>,
+    /// Project lists (local paths or URLs) to check mods against.
+    #[serde(default)]
+    pub project_lists: Vec<String>,
+    /// Template for the file names of generated artifacts, without extension. Supports the
+    /// `{name}`, `{version}`, `{target}`, `{git_hash}` and `{git_branch}` placeholders.
+    #[serde(default)]
+    pub artifact_name: Option<String>,
+    /// How many CurseForge ZIPs and Modrinth packs of the pack to keep in an output directory,
+    /// counting the one just generated. Older ones netherfire generated there are removed.
+    #[serde(default)]
+    pub keep_artifacts: Option<NonZeroUsize>,
+    /// Prefer versions marked as featured on Modrinth when looking up the latest version of a mod.
+    #[serde(default)]
+    pub prefer_featured: bool,
+    /// The types of versions considered when looking up the latest version of a mod, e.g. only
+    /// `release`. All types are considered if empty.
+    #[serde(default)]
+    pub version_types: Vec<VersionType>,
+    /// Policy file (local path or URL) to verify the pack against. Defaults to
+    /// `netherfire-policy.toml` in the pack source directory, if it exists.
+    #[serde(default)]
+    pub policy: Option<String>,
+    /// Fail verification on warnings about mods, instead of only logging them.
+    #[serde(default)]
+    pub strict: bool,
+    /// What to do with mods whose files don't list the pack's Minecraft version.
+    #[serde(default)]
+    pub minecraft_version_check: MinecraftVersionCheck,
+    /// What to do with overrides whose names aren't valid UTF-8, which can't be stored in archives.
+    #[serde(default)]
+    pub non_utf8_paths: NonUtf8Paths,
+    /// What to do with symlinks in the overrides.
+    #[serde(default)]
+    pub symlinks: Symlinks,
+    /// Globs of override files to make executable, e.g. `["*.sh"]`, matched against their paths
+    /// relative to the override directory. On Unix, files that are executable already stay so.
+    #[serde(default)]
+    pub executables: Globs,
+    /// Globs of override files and directories to leave out of the outputs, e.g. `["*.bak"]`,
+    /// matched against their paths relative to the override directory.
+    #[serde(default)]
+    pub exclude: Globs,
+    /// Rules for which side the files in `overrides/` are for, so files for only one side don't
+    /// need their own directory. The first rule matching a file applies, the others are for both.
+    #[serde(default)]
+    pub override_sides: Vec<OverrideSideRule>,
+    /// Globs of override files whose placeholders, e.g. `{pack_version}`, are replaced when
+    /// generating outputs, matched against their paths relative to the override directory.
+    #[serde(default)]
+    pub templates: Globs,
+    /// Globs of files in `server-overrides/` that are merged into the file at their place in a
+    /// server base instead of replacing it, matched against their paths relative to the override
+    /// directory.
+    #[serde(default)]
+    pub patches: Globs,
+    /// Slugs or project IDs of optional dependencies to include in the pack whenever a configured
+    /// mod has them, at their latest version for the pack, e.g. `["lazydfu"]`.
+    #[serde(default)]
+    pub include_optional_deps: Vec<String>,
+    /// The side requirements of mods that neither their config entry nor the mod site declare.
+    #[serde(default)]
+    pub defaults: SideDefaults,
+    /// The directories of the pack source directory the overrides are read from.
+    #[serde(default)]
+    pub override_dirs: OverrideDirs,
+    /// Optional fields of the manifest of the CurseForge ZIP.
+    #[serde(default)]
+    pub curseforge_manifest: CurseForgeManifestConfig,
     pub mods: MC,
 }
 
+impl<MC> PackConfig<MC> {
+    /// The Java version the pack needs at least: the configured one, or else the one its Minecraft
+    /// version needs, if that's known.
+    pub fn min_java_version(&self) -> Option<u32> {
+        self.java_version
+            .or_else(|| required_java_version(&self.minecraft_version))
+    }
+
+    /// The version of the given mod loader the pack runs on, if it's the pack's mod loader or one
+    /// of its secondary loaders.
+    pub fn loader_version(&self, id: &ModLoaderType) -> Option<&String> {
+        std::iter::once(&self.mod_loader)
+            .chain(&self.secondary_loaders)
+            .find(|l| l.id == *id)
+            .map(|l| &l.version)
+    }
+
+    /// Replace the mods of the config, e.g. with their verified versions.
+    pub fn with_mods<N>(self, mods: N) -> PackConfig<N> {
+        PackConfig {
+            name: self.name,
+            description: self.description,
+            author: self.author,
+            version: self.version,
+            minecraft_version: self.minecraft_version,
+            mod_loader: self.mod_loader,
+            secondary_loaders: self.secondary_loaders,
+            java_version: self.java_version,
+            project_lists: self.project_lists,
+            artifact_name: self.artifact_name,
+            keep_artifacts: self.keep_artifacts,
+            prefer_featured: self.prefer_featured,
+            version_types: self.version_types,
+            policy: self.policy,
+            strict: self.strict,
+            minecraft_version_check: self.minecraft_version_check,
+            non_utf8_paths: self.non_utf8_paths,
+            symlinks: self.symlinks,
+            executables: self.executables,
+            exclude: self.exclude,
+            override_sides: self.override_sides,
+            templates: self.templates,
+            patches: self.patches,
+            include_optional_deps: self.include_optional_deps,
+            defaults: self.defaults,
+            override_dirs: self.override_dirs,
+            curseforge_manifest: self.curseforge_manifest,
+            mods,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct ModLoader {
@@ -20,7 +158,23 @@ pub struct ModLoader {
     pub version: String,
 }
 
-#[derive(Debug, Display, Clone, Eq, PartialEq, Deserialize)]
+/// Check that the secondary loaders of a pack differ from its mod loader and from each other.
+pub fn validate_secondary_loaders(
+    mod_loader: &ModLoader,
+    secondary_loaders: &[ModLoader],
+) -> Result<(), String> {
+    for (i, loader) in secondary_loaders.iter().enumerate() {
+        if loader.id == mod_loader.id {
+            return Err(format!("{} is the pack's mod loader already", loader.id));
+        }
+        if secondary_loaders[..i].iter().any(|l| l.id == loader.id) {
+            return Err(format!("{} is listed more than once", loader.id));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Display, Clone, Eq, PartialEq, Deserialize, ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum ModLoaderType {
     #[display(fmt = "forge")]
@@ -32,3 +186,224 @@ pub enum ModLoaderType {
     #[display(fmt = "quilt")]
     Quilt,
 }
+
+/// The Java version Minecraft needs at least, or `None` for versions it can't be told for, like
+/// weekly snapshots.
+pub fn required_java_version(minecraft_version: &str) -> Option<u32> {
+    // Pre-releases, release candidates and snapshots named after their release need what it needs.
+    let release = minecraft_version
+        .split_once(['-', ' '])
+        .map_or(minecraft_version, |(release, _)| release);
+    let mut parts = release.split('.').map(str::parse::<u32>);
+    let (Some(Ok(major)), minor, patch) = (parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    let minor = minor.unwrap_or(Ok(0)).ok()?;
+    let patch = patch.unwrap_or(Ok(0)).ok()?;
+    Some(match (major, minor, patch) {
+        // Releases are numbered by year since 26.1.
+        (26.., _, _) => 25,
+        (1, 21.., _) | (1, 20, 5..) => 21,
+        (1, 18..=20, _) => 17,
+        (1, 17, _) => 16,
+        (1, _, _) => 8,
+        _ => return None,
+    })
+}
+
+/// How stable a version of a mod is, as its author marked it on the site.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionType {
+    Release,
+    Beta,
+    Alpha,
+}
+
+impl VersionType {
+    /// Whether versions of this type pass the `allowed` types, where none means all.
+    pub fn is_allowed(self, allowed: &[VersionType]) -> bool {
+        allowed.is_empty() || allowed.contains(&self)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NonUtf8Paths {
+    /// Fail, listing the files.
+    #[default]
+    Error,
+    /// Leave the files out, with a warning.
+    Skip,
+    /// Replace the invalid parts of the names with `_`, with a warning.
+    Transliterate,
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MinecraftVersionCheck {
+    /// Fail verification.
+    #[default]
+    Strict,
+    /// Only log a warning, unless strict mode is on. For packs on versions the mod authors haven't
+    /// added to their files yet.
+    Warn,
+    /// Don't check the Minecraft version.
+    Off,
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Symlinks {
+    /// Include the files and directories the symlinks point to.
+    #[default]
+    Follow,
+    /// Recreate the symlinks in server bases, pointing to the same paths. Archives can't hold
+    /// symlinks, so they're followed there.
+    Preserve,
+    /// Fail, listing the symlinks.
+    Error,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OverrideSideRule {
+    /// Globs of the files the rule applies to, matched against their paths relative to
+    /// `overrides/`.
+    pub paths: Globs,
+    pub side: OverrideSide,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverrideSide {
+    Both,
+    Client,
+    Server,
+}
+
+impl OverrideSide {
+    /// The side every file in an override directory of the given kind is for, or `None` for
+    /// `overrides/`, where it depends on the file.
+    pub fn of_kind(kind: &str) -> Option<Self> {
+        match kind {
+            LIT_CLIENT_OVERRIDES => Some(Self::Client),
+            LIT_SERVER_OVERRIDES => Some(Self::Server),
+            _ => None,
+        }
+    }
+
+    /// The side the file at `relative` in an override directory of the given kind is for, using
+    /// the first of `rules` that matches for files in `overrides/`.
+    pub fn of(kind: &str, relative: &str, rules: &[OverrideSideRule]) -> Self {
+        Self::of_kind(kind).unwrap_or_else(|| {
+            rules
+                .iter()
+                .find(|rule| rule.paths.is_match(relative))
+                .map_or(Self::Both, |rule| rule.side)
+        })
+    }
+}
+
+/// The directories overrides are read from, relative to each pack source directory, e.g. to keep
+/// an existing repository layout. They're always called `overrides/`, `client-overrides/` and
+/// `server-overrides/` in the outputs.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverrideDirs {
+    pub overrides: String,
+    pub client_overrides: String,
+    pub server_overrides: String,
+}
+
+impl Default for OverrideDirs {
+    fn default() -> Self {
+        Self {
+            overrides: LIT_OVERRIDES.to_string(),
+            client_overrides: LIT_CLIENT_OVERRIDES.to_string(),
+            server_overrides: LIT_SERVER_OVERRIDES.to_string(),
+        }
+    }
+}
+
+impl OverrideDirs {
+    /// The directory the overrides of the given kind, e.g. `client-overrides`, are read from.
+    pub fn of_kind(&self, kind: &str) -> &str {
+        match kind {
+            LIT_CLIENT_OVERRIDES => &self.client_overrides,
+            LIT_SERVER_OVERRIDES => &self.server_overrides,
+            _ => &self.overrides,
+        }
+    }
+
+    /// Check that the directories are distinct and inside the pack source directory.
+    pub fn validate(&self) -> Result<(), String> {
+        let dirs = [
+            &self.overrides,
+            &self.client_overrides,
+            &self.server_overrides,
+        ];
+        for dir in dirs {
+            let path = Path::new(dir);
+            if path.components().next().is_none()
+                || !path.components().all(|c| matches!(c, Component::Normal(_)))
+            {
+                return Err(format!(
+                    "override directory '{}' must be a relative path inside the pack source \
+                    directory",
+                    dir
+                ));
+            }
+        }
+        for (i, dir) in dirs.iter().enumerate() {
+            if let Some(other) = dirs[i + 1..].iter().find(|other| {
+                Path::new(dir).starts_with(other) || Path::new(other).starts_with(dir)
+            }) {
+                return Err(format!(
+                    "override directories '{}' and '{}' overlap",
+                    dir, other
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Optional fields of the manifest of the CurseForge ZIP, for what the minimal manifest can't
+/// describe.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CurseForgeManifestConfig {
+    /// The ID of the pack's own project on CurseForge, recorded as `projectID`.
+    pub project_id: Option<i32>,
+    /// The directory of the ZIP the overrides are put in.
+    pub overrides: String,
+    /// More mod loaders to list besides the pack's own and its `secondary_loaders`, as CurseForge
+    /// IDs, e.g. `fabric-0.15.11`. They're listed after the others, and the pack's mod loader stays
+    /// the primary one.
+    pub mod_loaders: Vec<String>,
+}
+
+impl Default for CurseForgeManifestConfig {
+    fn default() -> Self {
+        Self {
+            project_id: None,
+            overrides: LIT_OVERRIDES.to_string(),
+            mod_loaders: Vec::new(),
+        }
+    }
+}
+
+impl CurseForgeManifestConfig {
+    /// Check that the overrides directory is a single directory name.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut components = Path::new(&self.overrides).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) if !self.overrides.contains(['/', '\\']) => Ok(()),
+            _ => Err(format!(
+                "overrides directory '{}' must be a single directory name",
+                self.overrides
+            )),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use sha2::Digest;
+use thiserror::Error;
+
+use crate::config::project_lists::{ProjectLists, ProjectRef};
+use crate::retry;
+
+/// The policy file used by a pack if it doesn't configure one.
+pub const POLICY_FILE: &str = "netherfire-policy.toml";
+
+/// Organization-wide rules that every pack using the policy must follow.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// Config keys of the sites mods may come from, e.g. `modrinth`. Any site if absent.
+    pub allowed_sites: Option<Vec<String>>,
+    /// SPDX IDs of the licenses mods may have. Any license if absent.
+    pub allowed_licenses: Option<Vec<String>>,
+    /// Projects no pack may use.
+    #[serde(default)]
+    pub banned_projects: BannedProjects,
+    /// Force strict mode on for every pack, regardless of its config.
+    #[serde(default)]
+    pub require_strict: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BannedProjects {
+    #[serde(default)]
+    pub curseforge: Vec<ProjectRef>,
+    #[serde(default)]
+    pub modrinth: Vec<ProjectRef>,
+}
+
+/// A policy, with where it was loaded from.
+#[derive(Debug, Clone)]
+pub struct LoadedPolicy {
+    pub source: String,
+    pub policy: Policy,
+    /// SHA-256 hash of the policy file, to tell whether it changed.
+    pub content_hash: String,
+}
+
+impl LoadedPolicy {
+    /// Check whether mods from the site with the given config key may be used.
+    pub fn site_allowed(&self, site: &str) -> bool {
+        self.policy
+            .allowed_sites
+            .as_ref()
+            .is_none_or(|sites| sites.iter().any(|s| s == site))
+    }
+
+    /// Add the banned projects of the policy to the blocked projects of `lists`.
+    pub fn block_banned_projects(&self, lists: &mut ProjectLists) {
+        let banned = &self.policy.banned_projects;
+        lists.curseforge.block(&self.source, &banned.curseforge);
+        lists.modrinth.block(&self.source, &banned.modrinth);
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PolicyLoadError {
+    #[error("I/O error reading policy '{0}': {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("Error fetching policy '{0}': {1}")]
+    Fetch(String, #[source] reqwest::Error),
+    #[error("Error parsing policy '{0}': {1}")]
+    TomlParse(String, #[source] toml::de::Error),
+}
+
+/// Load the policy of a pack. `source` is either an `http(s)://` URL or a path, and defaults to
+/// [POLICY_FILE] in the pack source directory, if that exists.
+pub async fn load_policy(
+    source_dir: &Path,
+    source: Option<&str>,
+) -> Result<Option<LoadedPolicy>, PolicyLoadError> {
+    let source = match source {
+        Some(source) => source.to_string(),
+        None => {
+            let default = source_dir.join(POLICY_FILE);
+            if !default.exists() {
+                return Ok(None);
+            }
+            default.display().to_string()
+        }
+    };
+    let text = if source.starts_with("http://") || source.starts_with("https://") {
+        let content = retry::download(&source)
+            .await
+            .map_err(|e| PolicyLoadError::Fetch(source.clone(), e))?;
+        String::from_utf8_lossy(&content).into_owned()
+    } else {
+        tokio::fs::read_to_string(source_dir.join(&source))
+            .await
+            .map_err(|e| PolicyLoadError::Io(source.clone(), e))?
+    };
+    let policy = toml::from_str::<Policy>(&text)
+        .map_err(|e| PolicyLoadError::TomlParse(source.clone(), e))?;
+    Ok(Some(LoadedPolicy {
+        source,
+        policy,
+        content_hash: format!("{:x}", sha2::Sha256::digest(text.as_bytes())),
+    }))
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use sha2::Digest;
+use thiserror::Error;
+
+use crate::retry;
+
+/// A reference to a project in a project list, either by ID or by slug.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ProjectRef {
+    Number(i64),
+    Text(String),
+}
+
+impl ProjectRef {
+    fn matches(&self, project_id: &str, slug: &str) -> bool {
+        match self {
+            ProjectRef::Number(n) => n.to_string() == project_id,
+            ProjectRef::Text(t) => t == project_id || t == slug,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectListFile {
+    #[serde(default)]
+    curseforge: ProjectListFileSite,
+    #[serde(default)]
+    modrinth: ProjectListFileSite,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectListFileSite {
+    #[serde(default)]
+    blocked: Vec<ProjectRef>,
+    allowed: Option<Vec<ProjectRef>>,
+}
+
+/// All project lists of a pack, merged per site.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectLists {
+    pub curseforge: SiteProjectLists,
+    pub modrinth: SiteProjectLists,
+    /// SHA-256 hash of the contents of the lists, to tell whether they changed.
+    pub content_hash: String,
+}
+
+/// The project lists for a single site. Each entry remembers the list it came from, so that
+/// errors can name the policy source.
+#[derive(Debug, Clone, Default)]
+pub struct SiteProjectLists {
+    blocked: Vec<(String, ProjectRef)>,
+    allowed: Vec<(String, Vec<ProjectRef>)>,
+}
+
+impl SiteProjectLists {
+    fn extend(&mut self, source: &str, site: ProjectListFileSite) {
+        self.blocked
+            .extend(site.blocked.into_iter().map(|r| (source.to_string(), r)));
+        if let Some(allowed) = site.allowed {
+            self.allowed.push((source.to_string(), allowed));
+        }
+    }
+
+    /// Block the given projects, on behalf of `source`.
+    pub fn block(&mut self, source: &str, projects: &[ProjectRef]) {
+        self.blocked
+            .extend(projects.iter().map(|r| (source.to_string(), r.clone())));
+    }
+
+    /// Check a project against the lists. A project must not be blocked by any list, and must be
+    /// allowed by every list that has an allowlist.
+    pub fn check(&self, project_id: &str, slug: &str) -> Result<(), ProjectListViolation> {
+        if let Some((source, _)) = self
+            .blocked
+            .iter()
+            .find(|(_, r)| r.matches(project_id, slug))
+        {
+            return Err(ProjectListViolation::Blocked(source.clone()));
+        }
+        for (source, allowed) in &self.allowed {
+            if !allowed.iter().any(|r| r.matches(project_id, slug)) {
+                return Err(ProjectListViolation::NotAllowed(source.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ProjectListViolation {
+    #[error("The project is blocked by the project list '{0}'")]
+    Blocked(String),
+    #[error("The project is not allowed by the project list '{0}'")]
+    NotAllowed(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ProjectListLoadError {
+    #[error("I/O error reading project list '{0}': {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("Error fetching project list '{0}': {1}")]
+    Fetch(String, #[source] reqwest::Error),
+    #[error("Error parsing project list '{0}': {1}")]
+    TomlParse(String, #[source] toml::de::Error),
+}
+
+/// Load the project lists from the given sources. A source is either an `http(s)://` URL, or a
+/// path relative to the pack source directory.
+pub async fn load_project_lists(
+    source_dir: &Path,
+    sources: &[String],
+) -> Result<ProjectLists, ProjectListLoadError> {
+    let mut lists = ProjectLists::default();
+    let mut hasher = sha2::Sha256::new();
+    for source in sources {
+        let text = if source.starts_with("http://") || source.starts_with("https://") {
+            let content = retry::download(source)
+                .await
+                .map_err(|e| ProjectListLoadError::Fetch(source.clone(), e))?;
+            String::from_utf8_lossy(&content).into_owned()
+        } else {
+            tokio::fs::read_to_string(source_dir.join(source))
+                .await
+                .map_err(|e| ProjectListLoadError::Io(source.clone(), e))?
+        };
+        hasher.update(source.as_bytes());
+        hasher.update(text.as_bytes());
+        let file = toml::from_str::<ProjectListFile>(&text)
+            .map_err(|e| ProjectListLoadError::TomlParse(source.clone(), e))?;
+        lists.curseforge.extend(source, file.curseforge);
+        lists.modrinth.extend(source, file.modrinth);
+    }
+    lists.content_hash = format!("{:x}", hasher.finalize());
+    Ok(lists)
+}
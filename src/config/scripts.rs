@@ -0,0 +1,35 @@
+use std::path::Path;
+
+use rhai::{Dynamic, Engine, Scope};
+
+/// The keys a script may return, which are applied like an extending config.
+const SCRIPT_KEYS: [&str; 2] = ["mods", "remove_mods"];
+
+/// Run a [Rhai](https://rhai.rs) script listed in the `scripts` of a pack config. It gets the
+/// config loaded so far as `config`, and can read environment variables with `env(name)`, which
+/// returns `()` for unset variables. It returns a map with the `mods` to add and the
+/// `remove_mods` to remove, in the same structure as in `config.toml`.
+pub(crate) fn run_script(path: &Path, config: &toml::Table) -> Result<toml::Table, String> {
+    let mut engine = Engine::new();
+    engine.register_fn("env", |name: &str| -> Dynamic {
+        std::env::var(name).map_or(Dynamic::UNIT, Dynamic::from)
+    });
+    let mut scope = Scope::new();
+    scope.push_constant(
+        "config",
+        rhai::serde::to_dynamic(config).map_err(|e| e.to_string())?,
+    );
+    let result = engine
+        .eval_file_with_scope::<Dynamic>(&mut scope, path.to_path_buf())
+        .map_err(|e| e.to_string())?;
+    let overlay: toml::Table = rhai::serde::from_dynamic(&result)
+        .map_err(|e| format!("The script must return a map: {}", e))?;
+    if let Some(key) = overlay.keys().find(|k| !SCRIPT_KEYS.contains(&k.as_str())) {
+        return Err(format!(
+            "The script returned `{}`, but may only return {}",
+            key,
+            SCRIPT_KEYS.map(|k| format!("`{}`", k)).join(" and ")
+        ));
+    }
+    Ok(overlay)
+}
@@ -0,0 +1,373 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::config::mods::ConfigModContainer;
+use crate::config::pack::{required_java_version, validate_secondary_loaders, PackConfig};
+use crate::config::policy::POLICY_FILE;
+use crate::config::scripts::run_script;
+use crate::git;
+
+/// The directories a pack is made of: the directories of the packs it extends, followed by its own
+/// source directory. Later directories take precedence.
+#[derive(Debug, Clone)]
+pub struct PackSource {
+    pub dirs: Vec<PathBuf>,
+    /// What the scripts of the config returned, as TOML, in the order they ran.
+    pub script_overlays: Vec<String>,
+}
+
+impl PackSource {
+    /// Join `path` onto every directory, in order of increasing precedence.
+    pub fn join_all(&self, path: &str) -> Vec<PathBuf> {
+        self.dirs.iter().map(|d| d.join(path)).collect()
+    }
+}
+
+/// A cell of a pack's build matrix, declared in the `targets` table of its config.
+#[derive(Debug)]
+pub struct PackTarget {
+    pub name: String,
+    pub config: PackConfig<ConfigModContainer>,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigLoadError {
+    #[error("I/O Error on {0}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("TOML Parse Error in {0}: {1}")]
+    TomlParse(String, #[source] toml::de::Error),
+    #[error("TOML Parse Error in {0}: {1}")]
+    TomlEditParse(String, #[source] toml_edit::TomlError),
+    #[error("Target {1} in {0} must be a table")]
+    InvalidTarget(String, String),
+    #[error("Pack {0} is part of an `extends` cycle")]
+    ExtendsCycle(String),
+    #[error("Invalid `override_dirs` in {0}: {1}")]
+    InvalidOverrideDirs(String, String),
+    #[error("Invalid `secondary_loaders` in {0}: {1}")]
+    InvalidSecondaryLoaders(String, String),
+    #[error("Invalid `curseforge_manifest` in {0}: {1}")]
+    InvalidCurseForgeManifest(String, String),
+    #[error("`java_version` {1} in {0} is lower than the Java {2} that Minecraft {3} needs")]
+    JavaVersionTooLow(String, u32, u32, String),
+    #[error("Error running script {0}: {1}")]
+    Script(String, String),
+    #[error("Error comparing {0} with git revision {1}: {2}")]
+    Git(String, String, #[source] std::io::Error),
+}
+
+/// The config keys of the mods whose entries changed since a git revision, by site.
+#[derive(Debug, Default)]
+pub struct ChangedMods {
+    pub curseforge: HashSet<String>,
+    pub modrinth: HashSet<String>,
+}
+
+impl ChangedMods {
+    pub fn count(&self) -> usize {
+        self.curseforge.len() + self.modrinth.len()
+    }
+}
+
+/// Load the `config.toml` of the given pack source directory, merging in the configs of the packs
+/// it extends.
+pub fn load_pack_config(
+    source_dir: &Path,
+) -> Result<(PackConfig<ConfigModContainer>, PackSource), ConfigLoadError> {
+    let (base, _, source) = load_pack_tables(source_dir)?;
+    Ok((deserialize_config(source_dir, base)?, source))
+}
+
+/// Load the config of every build matrix target of the given pack source directory, i.e. the base
+/// config with each target's overrides applied to it.
+pub fn load_pack_targets(
+    source_dir: &Path,
+) -> Result<(Vec<PackTarget>, PackSource), ConfigLoadError> {
+    let config_name = source_dir.join("config.toml").display().to_string();
+    let (base, targets, source) = load_pack_tables(source_dir)?;
+    let mut configs = Vec::with_capacity(targets.len());
+    for (name, target) in targets {
+        let toml::Value::Table(target) = target else {
+            return Err(ConfigLoadError::InvalidTarget(config_name, name));
+        };
+        let mut config = base.clone();
+        apply_overlay(&mut config, target, &config_name)?;
+        configs.push(PackTarget {
+            name,
+            config: deserialize_config(source_dir, config)?,
+        });
+    }
+    Ok((configs, source))
+}
+
+/// Load the merged config table and the build matrix targets of the given pack source directory.
+fn load_pack_tables(
+    source_dir: &Path,
+) -> Result<(toml::Table, toml::Table, PackSource), ConfigLoadError> {
+    let read_config = |dir: &Path| std::fs::read_to_string(dir.join("config.toml"));
+    load_pack_tables_with(source_dir, &read_config)
+}
+
+fn load_pack_tables_with(
+    source_dir: &Path,
+    read_config: &ReadConfig<'_>,
+) -> Result<(toml::Table, toml::Table, PackSource), ConfigLoadError> {
+    let mut dirs = Vec::new();
+    let mut merged = load_merged_config(source_dir, read_config, &mut Vec::new(), &mut dirs)?;
+    let targets = match merged.remove("targets") {
+        Some(toml::Value::Table(targets)) => targets,
+        Some(_) => {
+            return Err(ConfigLoadError::InvalidTarget(
+                source_dir.join("config.toml").display().to_string(),
+                "targets".to_string(),
+            ))
+        }
+        None => toml::Table::new(),
+    };
+    let script_overlays = run_scripts(source_dir, &mut merged)?;
+    Ok((
+        merged,
+        targets,
+        PackSource {
+            dirs,
+            script_overlays,
+        },
+    ))
+}
+
+/// Run the `scripts` of a merged config in order, applying what each returns to the config.
+/// Returns what they returned, as TOML.
+fn run_scripts(
+    source_dir: &Path,
+    config: &mut toml::Table,
+) -> Result<Vec<String>, ConfigLoadError> {
+    let config_name = source_dir.join("config.toml").display().to_string();
+    let scripts: Vec<String> = match config.remove("scripts") {
+        Some(v) => v
+            .try_into()
+            .map_err(|e| ConfigLoadError::TomlParse(config_name, e))?,
+        None => return Ok(Vec::new()),
+    };
+    let mut overlays = Vec::with_capacity(scripts.len());
+    for script in scripts {
+        let overlay = run_script(Path::new(&script), config)
+            .map_err(|e| ConfigLoadError::Script(script.clone(), e))?;
+        overlays.push(toml::to_string(&overlay).expect("script output is serializable"));
+        apply_overlay(config, overlay, &script)?;
+    }
+    Ok(overlays)
+}
+
+/// The settings of a pack that affect the verification of every mod.
+const VERIFICATION_SETTINGS: [&str; 6] = [
+    "minecraft_version",
+    "project_lists",
+    "policy",
+    "strict",
+    "minecraft_version_check",
+    "defaults",
+];
+
+/// Find the mods whose entries in the config of the given pack source directory differ from the
+/// config at the git revision `rev`, including the packs it extends. Returns `None` if anything
+/// else that affects the verification of every mod changed: the other settings, the policy, or
+/// local project lists.
+pub fn changed_mods_since(
+    source_dir: &Path,
+    rev: &str,
+) -> Result<Option<ChangedMods>, ConfigLoadError> {
+    let config_name = source_dir.join("config.toml").display().to_string();
+    let git_error = |e| ConfigLoadError::Git(config_name.clone(), rev.to_string(), e);
+    let (current, _, source) = load_pack_tables(source_dir)?;
+    let read_config = |dir: &Path| git::show_file(dir, rev, "config.toml");
+    let (baseline, _, _) = load_pack_tables_with(source_dir, &read_config)?;
+
+    if VERIFICATION_SETTINGS
+        .iter()
+        .any(|key| current.get(*key) != baseline.get(*key))
+    {
+        return Ok(None);
+    }
+    let current_mods = current.get("mods").cloned();
+    let baseline_mods = baseline.get("mods").cloned();
+    let config = deserialize_config(source_dir, current)?;
+    let mut inputs = config
+        .project_lists
+        .iter()
+        .filter(|l| !l.starts_with("http://") && !l.starts_with("https://"))
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    match &config.policy {
+        Some(policy) if !policy.starts_with("http://") && !policy.starts_with("https://") => {
+            inputs.push(PathBuf::from(policy))
+        }
+        Some(_) => {}
+        None => inputs.extend(source.dirs.last().map(|d| d.join(POLICY_FILE))),
+    }
+    if !inputs.is_empty() && git::paths_changed(source_dir, rev, &inputs).map_err(git_error)? {
+        return Ok(None);
+    }
+
+    let site_mods = |mods: &Option<toml::Value>, site: &str| {
+        mods.as_ref()
+            .and_then(|m| m.get(site))
+            .and_then(toml::Value::as_table)
+            .cloned()
+            .unwrap_or_default()
+    };
+    let changed = |site: &str| {
+        let baseline = site_mods(&baseline_mods, site);
+        site_mods(&current_mods, site)
+            .into_iter()
+            .filter(|(k, m)| baseline.get(k) != Some(m))
+            .map(|(k, _)| k)
+            .collect()
+    };
+    Ok(Some(ChangedMods {
+        curseforge: changed("curseforge"),
+        modrinth: changed("modrinth"),
+    }))
+}
+
+fn deserialize_config(
+    source_dir: &Path,
+    config: toml::Table,
+) -> Result<PackConfig<ConfigModContainer>, ConfigLoadError> {
+    let config_name = source_dir.join("config.toml").display().to_string();
+    let config: PackConfig<ConfigModContainer> = toml::Value::Table(config)
+        .try_into()
+        .map_err(|e| ConfigLoadError::TomlParse(config_name.clone(), e))?;
+    config
+        .override_dirs
+        .validate()
+        .map_err(|e| ConfigLoadError::InvalidOverrideDirs(config_name.clone(), e))?;
+    validate_secondary_loaders(&config.mod_loader, &config.secondary_loaders)
+        .map_err(|e| ConfigLoadError::InvalidSecondaryLoaders(config_name.clone(), e))?;
+    config
+        .curseforge_manifest
+        .validate()
+        .map_err(|e| ConfigLoadError::InvalidCurseForgeManifest(config_name.clone(), e))?;
+    if let (Some(java), Some(required)) = (
+        config.java_version,
+        required_java_version(&config.minecraft_version),
+    ) {
+        if java < required {
+            return Err(ConfigLoadError::JavaVersionTooLow(
+                config_name,
+                java,
+                required,
+                config.minecraft_version,
+            ));
+        }
+    }
+    Ok(config)
+}
+
+/// Reads the `config.toml` of a pack source directory.
+type ReadConfig<'a> = dyn Fn(&Path) -> Result<String, std::io::Error> + 'a;
+
+fn load_merged_config(
+    dir: &Path,
+    read_config: &ReadConfig<'_>,
+    visiting: &mut Vec<PathBuf>,
+    dirs: &mut Vec<PathBuf>,
+) -> Result<toml::Table, ConfigLoadError> {
+    let config_file = dir.join("config.toml");
+    let config_name = config_file.display().to_string();
+    let canonical = dir
+        .canonicalize()
+        .map_err(|e| ConfigLoadError::Io(config_name.clone(), e))?;
+    if visiting.contains(&canonical) {
+        return Err(ConfigLoadError::ExtendsCycle(dir.display().to_string()));
+    }
+    visiting.push(canonical.clone());
+
+    let text = read_config(dir).map_err(|e| ConfigLoadError::Io(config_name.clone(), e))?;
+    let mut table = toml::from_str::<toml::Table>(&text)
+        .map_err(|e| ConfigLoadError::TomlParse(config_name.clone(), e))?;
+    resolve_paths(&canonical, &mut table);
+
+    let extends = match table.remove("extends") {
+        Some(toml::Value::String(extends)) => Some(extends),
+        Some(_) => {
+            return Err(ConfigLoadError::TomlParse(
+                config_name,
+                serde::de::Error::custom("`extends` must be a path"),
+            ))
+        }
+        None => None,
+    };
+
+    let mut merged = match extends {
+        Some(base) => load_merged_config(&dir.join(base), read_config, visiting, dirs)?,
+        None => toml::Table::new(),
+    };
+    apply_overlay(&mut merged, table, &config_name)?;
+
+    dirs.push(dir.to_path_buf());
+    Ok(merged)
+}
+
+/// Apply an extending config or a build matrix target to `base`, removing the mods listed in its
+/// `remove_mods` first.
+fn apply_overlay(
+    base: &mut toml::Table,
+    mut overlay: toml::Table,
+    config_name: &str,
+) -> Result<(), ConfigLoadError> {
+    let remove_mods: Vec<String> = match overlay.remove("remove_mods") {
+        Some(v) => v
+            .try_into()
+            .map_err(|e| ConfigLoadError::TomlParse(config_name.to_string(), e))?,
+        None => Vec::new(),
+    };
+    if let Some(toml::Value::Table(mods)) = base.get_mut("mods") {
+        for (_, site_mods) in mods.iter_mut() {
+            if let toml::Value::Table(site_mods) = site_mods {
+                for key in &remove_mods {
+                    site_mods.remove(key);
+                }
+            }
+        }
+    }
+    merge_tables(base, overlay);
+    Ok(())
+}
+
+/// Resolve local project list, script and policy paths against the directory of the config they're declared in,
+/// so they still work when inherited.
+fn resolve_paths(dir: &Path, table: &mut toml::Table) {
+    let resolve = |path: &mut String| {
+        if !path.starts_with("http://") && !path.starts_with("https://") {
+            *path = dir.join(&*path).display().to_string();
+        }
+    };
+    for key in ["project_lists", "scripts"] {
+        if let Some(toml::Value::Array(paths)) = table.get_mut(key) {
+            for path in paths {
+                if let toml::Value::String(path) = path {
+                    resolve(path);
+                }
+            }
+        }
+    }
+    if let Some(toml::Value::String(policy)) = table.get_mut("policy") {
+        resolve(policy);
+    }
+}
+
+/// Merge `overlay` into `base`. Tables are merged recursively, other values are replaced.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
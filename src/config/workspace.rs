@@ -0,0 +1,34 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+pub const WORKSPACE_FILE: &str = "netherfire-workspace.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// Pack source directories, relative to the workspace directory.
+    pub packs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Error)]
+pub enum WorkspaceLoadError {
+    #[error("I/O Error on {WORKSPACE_FILE}: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("TOML Parse Error: {0}")]
+    TomlParse(#[from] toml::de::Error),
+}
+
+/// Load the workspace in the given directory, if there is one. Returns the pack source directories
+/// of the workspace.
+pub fn load_workspace(dir: &Path) -> Result<Option<Vec<PathBuf>>, WorkspaceLoadError> {
+    let workspace_file = dir.join(WORKSPACE_FILE);
+    if !workspace_file.exists() {
+        return Ok(None);
+    }
+    let config = toml::from_str::<WorkspaceConfig>(&std::fs::read_to_string(workspace_file)?)?;
+    Ok(Some(
+        config.packs.into_iter().map(|p| dir.join(p)).collect(),
+    ))
+}
@@ -0,0 +1,276 @@
+//! Longer descriptions of the error codes shown with `--json`, and how to resolve the errors.
+
+/// Explanations by error code. Codes of failed runs and of failures of single mods share one
+/// namespace.
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "pack_config_load",
+        "\
+The pack's `config.toml`, or a config it extends, couldn't be read or parsed.
+
+- Check that the path points at the pack source directory, which contains `config.toml`.
+- The message names the file and the TOML problem. Unknown keys are errors, so check for typos.
+- If the pack extends another one, check the path in `extends`.",
+    ),
+    (
+        "project_list_load",
+        "\
+A project list in `project_lists` couldn't be loaded.
+
+- Paths are relative to the pack source directory. Check that the file exists.
+- For URLs, check that they're reachable, e.g. with `netherfire doctor`.
+- A project list has an optional `blocked` and `allowed` list per site.",
+    ),
+    (
+        "policy_load",
+        "\
+The policy file couldn't be loaded. It's `policy` in `config.toml`, or `netherfire-policy.toml` in the pack
+source directory if that exists.
+
+- Check that the file or URL exists and is valid TOML.
+- Unknown keys are errors, so check for typos.",
+    ),
+    (
+        "mod_verification",
+        "\
+One or more mods failed verification. Each failed mod is listed with its own code, and a summary groups
+them by kind with hints. Run `netherfire explain <code>` with the code of a failure for details, e.g.
+`netherfire explain minecraft_version_mismatch`. With `--json`, the codes are in `failures`.",
+    ),
+    (
+        "mod_id_check",
+        "\
+Multiple mods declare the same mod ID in their loader metadata. Usually one is a fork or a renamed
+version of the other, and the game would crash on launch.
+
+- Remove all but one of the listed mods from the config.
+- If the check itself can't run, e.g. because downloads fail, pass `--skip-mod-id-check`.",
+    ),
+    (
+        "overrides_check",
+        "\
+Some overrides couldn't be extracted on every OS: files that only differ by case once the override
+directories are merged, or names Windows doesn't allow, e.g. `con.txt`, names ending in a dot or
+space, or names with characters like `:` or `?`.
+
+- Rename or remove the listed files.",
+    ),
+    (
+        "create_curseforge_zip",
+        "\
+Writing the CurseForge ZIP failed.
+
+- For I/O errors, check that the output directory is writable and the disk isn't full.
+- For mods that aren't distributable, their files are downloaded into the ZIP. If that fails, see
+  `netherfire explain download`.",
+    ),
+    (
+        "create_modrinth_pack",
+        "\
+Writing the Modrinth pack failed.
+
+- For I/O errors, check that the output directory is writable and the disk isn't full.
+- For override problems, the message names the file, e.g. a symlink or a name that isn't UTF-8. See
+  `symlinks` and `non_utf8_paths` in the README.",
+    ),
+    (
+        "create_server_base",
+        "\
+Creating the server base failed.
+
+- If mods failed to download, they're listed with their own codes, e.g. `download`.
+- For I/O errors, check that the output directory is writable and the disk isn't full.
+- For patches, check that the patch and the file it's merged into are valid TOML, JSON or properties.
+- For symlinks pointing outside the pack, copy what they point to into the overrides instead.",
+    ),
+    (
+        "lock",
+        "\
+Another netherfire run holds the lock on the source or output directory, or the lock file couldn't be
+created.
+
+- Wait for the other run to finish.
+- If no other run is active, the lock file `.netherfire-run.lock` is left over from a crash and can be
+  deleted.",
+    ),
+    (
+        "lockfile",
+        "\
+The lockfile `netherfire.lock` (or `netherfire-<target>.lock` for a build matrix target) couldn't be
+read or written, or `generate --locked` or `verify --locked` found it missing or out of date.
+
+- Run `netherfire generate` without `--locked`, or `netherfire verify`, to write it again, and commit it.
+- It's out of date whenever `config.toml`, the project lists or the policy changed since it was written.
+- If it's invalid, e.g. after a merge conflict, run `netherfire generate` without `--locked` to write it again.",
+    ),
+    (
+        "build_info",
+        "\
+Collecting the build information failed, e.g. the Git commit of the pack source.
+
+- Check that the pack source directory is readable, and that `git` works in it if it's a repository.",
+    ),
+    (
+        "workspace_load",
+        "\
+The workspace file `netherfire-workspace.toml` couldn't be loaded.
+
+- Check that it's valid TOML, and that every directory in `packs` contains a pack.",
+    ),
+    (
+        "invalid_artifact",
+        "\
+The checked Modrinth pack or CurseForge ZIP doesn't follow its format. The problems are listed above
+the error.
+
+- For a pack generated by another tool, fix the problems there and generate it again.",
+    ),
+    (
+        "broken_downloads",
+        "\
+Some download URLs of the pack's mods don't work anymore, or serve a file of another size than
+the site reported. They're listed above the error with the mod's config key.
+
+- If the file was deleted from the site, pick another version of the mod.
+- For a mirror set with `download_url`, check that it still serves the file.
+- For network errors, run `netherfire doctor` to check access to the CDNs.",
+    ),
+    (
+        "locked_files_changed",
+        "\
+`verify --locked` found mod files whose hashes on their sites differ from the ones in
+`netherfire.lock`, or that can't be loaded anymore. They're listed above the error with the mod's
+config key and the old and new hashes.
+
+- A changed hash means the file was replaced on its site after it was locked. Don't publish the
+  pack until you know why, as the new file may be malicious.
+- If the new file is legitimate, run `netherfire verify` to lock it.",
+    ),
+    (
+        "loading",
+        "\
+The mod, or its file, couldn't be loaded from its site.
+
+- Check that `project_id` and `version_id` exist on the site, and that the version belongs to the
+  project. Modrinth needs IDs, not slugs.
+- Run `netherfire doctor` to check the API key and access to the sites.",
+    ),
+    (
+        "distribution_denied",
+        "\
+The mod's author doesn't allow third-party distribution on CurseForge, so it can't be in a CurseForge
+pack's manifest or downloaded by netherfire.
+
+- Download the file yourself and put it in `overrides/mods/`, if its license allows that.
+- Or use the mod's version from Modrinth, if there is one.",
+    ),
+    (
+        "missing_required_dependencies",
+        "\
+The mod requires other mods that aren't in the config.
+
+- Add them, e.g. with the `add-mods` command line printed in the summary.
+- If the site lists a dependency the mod doesn't actually need, add it to the mod's `ignored_deps`.",
+    ),
+    (
+        "minecraft_version_mismatch",
+        "\
+The site doesn't list the mod's file for the pack's Minecraft version.
+
+- Pick a version of the mod for the pack's Minecraft version.
+- If the file is known to work anyway, set `game_version` on the mod to a version the site lists.
+- For packs on a version authors haven't added to their files yet, set `minecraft_version_check` to
+  `warn` or `off` in `config.toml`.",
+    ),
+    (
+        "dependency_loading",
+        "\
+A dependency the site lists for the mod couldn't be loaded, e.g. because it was deleted.
+
+- If the mod doesn't need it, add it to the mod's `ignored_deps`.",
+    ),
+    (
+        "project_list",
+        "\
+The mod is blocked by a project list, or isn't on its allow list.
+
+- Remove the mod, or change the project lists in `project_lists`.",
+    ),
+    (
+        "site_not_allowed",
+        "\
+The policy doesn't allow mods from the mod's site.
+
+- Use the mod from a site the policy allows, or change `allowed_sites` in the policy.",
+    ),
+    (
+        "license_not_allowed",
+        "\
+The mod's license isn't in the policy's `allowed_licenses`.
+
+- Replace the mod, or add its license to the policy, if that's acceptable.",
+    ),
+    (
+        "unknown_license",
+        "\
+The site doesn't report the mod's license, which strict mode doesn't allow when the policy restricts
+licenses. CurseForge never reports licenses.
+
+- Check the license by hand and turn off strict mode, or use the mod from Modrinth.",
+    ),
+    (
+        "strict_warnings",
+        "\
+The mod had warnings, which fail verification in strict mode, e.g. the config marks it as required
+on a side its site says it doesn't support.
+
+- Set `client` and `server` of the mod to match the site, or turn off strict mode.",
+    ),
+    (
+        "never_installed",
+        "\
+The mod is unsupported on both the client and the server, so no output would include it. This
+happens when `client` and `server` are both set to `unsupported`, or when the config marks one side
+unsupported and the site or the pack's `defaults` the other.
+
+- Set `client` or `server` of the mod to `required` or `optional`.
+- Or remove the mod, if it isn't needed anymore.",
+    ),
+    (
+        "no_version_for_included_dependency",
+        "\
+An optional dependency of the mod is listed in `include_optional_deps` of the pack, but the site has no
+version of it for the pack's Minecraft version and mod loader, so it couldn't be included.
+
+- Add a version of the dependency to the config yourself, e.g. one known to work with the pack.
+- Or remove it from `include_optional_deps`.",
+    ),
+    (
+        "download",
+        "\
+The mod's file couldn't be downloaded, or didn't match the hash or size the site reported.
+
+- Run `netherfire doctor` to check access to the CDNs.
+- If the CDN is blocked, set `download_url` on the mod to a mirror of the file.",
+    ),
+    (
+        "io",
+        "\
+Writing the mod's file failed.
+
+- Check that the output directory is writable and the disk isn't full.",
+    ),
+];
+
+/// The explanation of an error code, if it has one.
+pub fn explanation(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, text)| *text)
+}
+
+/// All codes with an explanation.
+pub fn explained_codes() -> impl Iterator<Item = &'static str> {
+    EXPLANATIONS.iter().map(|(code, _)| *code)
+}
@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Get the full hash of the commit checked out in the repository containing `dir`, if it's in one
+/// and `git` is available.
+pub fn head_commit(dir: &Path) -> Option<String> {
+    rev_parse(dir, &["HEAD"])
+}
+
+/// Get the abbreviated hash of the commit checked out in the repository containing `dir`.
+pub fn short_head_commit(dir: &Path) -> Option<String> {
+    rev_parse(dir, &["--short", "HEAD"])
+}
+
+/// Get the name of the branch checked out in the repository containing `dir`. This is `HEAD` if
+/// no branch is checked out.
+pub fn head_branch(dir: &Path) -> Option<String> {
+    rev_parse(dir, &["--abbrev-ref", "HEAD"])
+}
+
+fn rev_parse(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("rev-parse")
+        .args(args)
+        .output()
+        .map_err(|e| log::debug!("Failed to run git in {}: {}", dir.display(), e))
+        .ok()?;
+    if !output.status.success() {
+        log::debug!(
+            "git rev-parse failed in {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Read `file`, relative to `dir`, as it is at `rev` in the repository containing `dir`.
+pub fn show_file(dir: &Path, rev: &str, file: &str) -> Result<String, std::io::Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("{}:./{}", rev, file))
+        .output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(std::io::Error::other)
+}
+
+/// Whether any of `paths` differs between `rev` and the working tree of the repository
+/// containing `dir`, including untracked files.
+pub fn paths_changed(dir: &Path, rev: &str, paths: &[PathBuf]) -> Result<bool, std::io::Error> {
+    let git_error = |output: &Output| {
+        std::io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    };
+    let diff = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["diff", "--quiet", rev, "--"])
+        .args(paths)
+        .output()?;
+    match diff.status.code() {
+        Some(0) => {}
+        Some(1) => return Ok(true),
+        _ => return Err(git_error(&diff)),
+    }
+    let untracked = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-files", "--others", "--exclude-standard", "--"])
+        .args(paths)
+        .output()?;
+    if !untracked.status.success() {
+        return Err(git_error(&untracked));
+    }
+    Ok(!untracked.stdout.is_empty())
+}
@@ -0,0 +1,218 @@
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use thiserror::Error;
+use zip::ZipArchive;
+
+use crate::add_mods::AddModRequest;
+use crate::config::global::{furse, FERINTH, IMPORT_LIMITS};
+use crate::config::mods::KnownEnvRequirement;
+use crate::import::mrpack::{read_mrpack, Mrpack};
+use crate::mod_site::ModLoadingError;
+use crate::output::curseforge_manifest::CurseForgeManifest;
+use crate::output::is_safe_path;
+use crate::retry;
+use crate::units;
+use crate::warnings::{warn, Warning, WarningKind};
+
+pub(crate) mod mrpack;
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("Error loading pack: {0}")]
+    Loading(#[from] ModLoadingError),
+    #[error("The pack has no files")]
+    NoFiles,
+    #[error("The pack does not allow third-party distribution, so it can't be downloaded")]
+    DistributionDenied,
+    #[error("Error downloading pack: {0}")]
+    Download(#[from] reqwest::Error),
+    #[error("ZIP error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Json error: {0}")]
+    Json(#[from] serde_json::error::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0} is larger than the limit of {1}, see `import_limits` in the global config")]
+    TooLarge(String, String),
+    #[error(
+        "The pack has {0} entries, more than the limit of {1}, see `import_limits` in the global config"
+    )]
+    TooManyEntries(usize, usize),
+    #[error("The pack contains the path '{0}', which points outside the pack")]
+    UnsafePath(String),
+}
+
+/// Limits on the packs that are imported, which may come from anywhere, so a malicious or broken
+/// one can't exhaust memory or disk.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ImportLimits {
+    /// The largest pack archive in MiB.
+    pub max_archive_mib: u64,
+    /// The most entries in a pack archive.
+    pub max_entries: usize,
+    /// The largest manifest in MiB, after decompressing it.
+    pub max_manifest_mib: u64,
+}
+
+impl Default for ImportLimits {
+    fn default() -> Self {
+        Self {
+            max_archive_mib: 1024,
+            max_entries: 65536,
+            max_manifest_mib: 16,
+        }
+    }
+}
+
+/// Check that an archive of `size` bytes is within [ImportLimits::max_archive_mib].
+fn check_archive_size(what: impl FnOnce() -> String, size: u64) -> Result<(), ImportError> {
+    let limit = IMPORT_LIMITS.max_archive_mib.saturating_mul(1024 * 1024);
+    if size > limit {
+        return Err(ImportError::TooLarge(what(), units::size(limit)));
+    }
+    Ok(())
+}
+
+/// Open a pack archive, checking the number and the paths of its entries, so no entry can be
+/// read from or written to outside the pack.
+pub(crate) fn open_pack_archive<R: Read + Seek>(reader: R) -> Result<ZipArchive<R>, ImportError> {
+    let zip = ZipArchive::new(reader)?;
+    if zip.len() > IMPORT_LIMITS.max_entries {
+        return Err(ImportError::TooManyEntries(
+            zip.len(),
+            IMPORT_LIMITS.max_entries,
+        ));
+    }
+    if let Some(name) = zip.file_names().find(|name| !is_safe_path(name)) {
+        return Err(ImportError::UnsafePath(name.to_string()));
+    }
+    Ok(zip)
+}
+
+/// Read a JSON entry of a pack archive. At most [ImportLimits::max_manifest_mib] are
+/// decompressed, whatever size the entry claims to have.
+pub(crate) fn read_json_entry<R: Read + Seek, T: DeserializeOwned>(
+    zip: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<T, ImportError> {
+    let limit = IMPORT_LIMITS.max_manifest_mib.saturating_mul(1024 * 1024);
+    let entry = zip.by_name(name)?;
+    let too_large = || ImportError::TooLarge(format!("'{}'", name), units::size(limit));
+    if entry.size() > limit {
+        return Err(too_large());
+    }
+    let mut content = Vec::new();
+    entry
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut content)?;
+    if content.len() as u64 > limit {
+        return Err(too_large());
+    }
+    Ok(serde_json::from_slice(&content)?)
+}
+
+/// Download the latest file of a CurseForge modpack project and read its manifest.
+pub async fn load_curseforge_pack_manifest(
+    project_id: i32,
+) -> Result<CurseForgeManifest, ImportError> {
+    let files = furse()
+        .map_err(ModLoadingError::from)?
+        .get_mod_files(project_id)
+        .await
+        .map_err(ModLoadingError::from)?;
+    let latest = files
+        .into_iter()
+        .filter(|f| f.is_available && !f.is_server_pack.unwrap_or(false))
+        .max_by_key(|f| f.file_date)
+        .ok_or(ImportError::NoFiles)?;
+    let what = || format!("The pack file {}", latest.display_name);
+    check_archive_size(what, latest.file_length as u64)?;
+    log::info!("Downloading pack file {}...", latest.display_name);
+    let url = latest
+        .download_url
+        .clone()
+        .ok_or(ImportError::DistributionDenied)?;
+    let content = retry::download(url.as_str()).await?;
+    // The reported size may be wrong.
+    check_archive_size(what, content.len() as u64)?;
+
+    tokio::task::block_in_place(|| read_curseforge_manifest(Cursor::new(content)))
+}
+
+/// Read the manifest of a CurseForge modpack ZIP.
+pub fn read_curseforge_manifest<R: Read + Seek>(
+    reader: R,
+) -> Result<CurseForgeManifest, ImportError> {
+    let mut zip = open_pack_archive(reader)?;
+    let manifest: CurseForgeManifest = read_json_entry(&mut zip, "manifest.json")?;
+    if !is_safe_path(&manifest.overrides) {
+        return Err(ImportError::UnsafePath(manifest.overrides));
+    }
+    Ok(manifest)
+}
+
+/// Read a Modrinth pack and find the Modrinth versions of its files by hash, to add them with
+/// those exact versions. Files that aren't on Modrinth are skipped with a warning.
+pub async fn load_modrinth_pack_mods(
+    path: &Path,
+) -> Result<(Mrpack, Vec<AddModRequest<String>>), ImportError> {
+    let pack = tokio::task::block_in_place(|| {
+        let file = File::open(path)?;
+        check_archive_size(|| format!("'{}'", path.display()), file.metadata()?.len())?;
+        read_mrpack(file)
+    })?;
+    if let Some(file) = pack.manifest.files.iter().find(|f| !is_safe_path(&f.path)) {
+        return Err(ImportError::UnsafePath(file.path.clone()));
+    }
+    let versions = FERINTH
+        .get_versions_from_hashes(
+            pack.manifest
+                .files
+                .iter()
+                .map(|f| f.hashes.sha1.clone())
+                .collect(),
+        )
+        .await
+        .map_err(ModLoadingError::from)?;
+    let mut requests = Vec::with_capacity(pack.manifest.files.len());
+    for file in &pack.manifest.files {
+        let Some(version) = versions.get(&file.hashes.sha1) else {
+            warn(Warning::new(
+                WarningKind::SkippedFile,
+                format!("'{}' isn't on Modrinth, so it's skipped", file.path),
+            ));
+            continue;
+        };
+        requests.push(AddModRequest {
+            project_id: version.project_id.clone(),
+            version_id: Some(version.id.clone()),
+            client_optional: file
+                .env
+                .as_ref()
+                .is_some_and(|e| e.client == KnownEnvRequirement::Optional),
+        });
+    }
+    Ok((pack, requests))
+}
+
+/// The CurseForge mods netherfire embedded in the overrides of a Modrinth pack, with the exact
+/// versions and the sides it recorded for them, so they can be added as CurseForge mods again.
+pub fn embedded_curseforge_mods(pack: &Mrpack) -> Vec<AddModRequest<i32>> {
+    let Some(embedded) = &pack.embedded else {
+        return Vec::new();
+    };
+    embedded
+        .files
+        .iter()
+        .map(|f| AddModRequest {
+            project_id: f.project_id,
+            version_id: Some(f.file_id),
+            client_optional: f.env.client == KnownEnvRequirement::Optional,
+        })
+        .collect()
+}
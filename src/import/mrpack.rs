@@ -0,0 +1,52 @@
+use std::io::{Read, Seek};
+
+use serde::de::DeserializeOwned;
+use zip::ZipArchive;
+
+use crate::import::{open_pack_archive, read_json_entry, ImportError};
+use crate::output::build_metadata::{BuildMetadata, BUILD_METADATA_FILE};
+use crate::output::modrinth_manifest::{EmbeddedManifest, ModrinthManifest, EMBEDDED_MANIFEST};
+
+pub const MODRINTH_MANIFEST: &str = "modrinth.index.json";
+
+/// The contents of a Modrinth pack that matter for importing and checking it.
+#[derive(Debug)]
+pub struct Mrpack {
+    pub manifest: ModrinthManifest,
+    /// The manifest as it's written in the pack, to tell whether the format lost anything.
+    pub raw_manifest: serde_json::Value,
+    /// How the pack was built, if netherfire generated it.
+    pub metadata: Option<BuildMetadata>,
+    /// The CurseForge mods embedded in the overrides, if netherfire generated the pack.
+    pub embedded: Option<EmbeddedManifest>,
+    /// The names of all entries in the archive.
+    pub entries: Vec<String>,
+}
+
+/// Read the manifest and the entries of a Modrinth pack.
+pub fn read_mrpack<R: Read + Seek>(reader: R) -> Result<Mrpack, ImportError> {
+    let mut zip = open_pack_archive(reader)?;
+    let entries = zip.file_names().map(str::to_string).collect::<Vec<_>>();
+    let raw_manifest: serde_json::Value = read_json_entry(&mut zip, MODRINTH_MANIFEST)?;
+    let manifest = serde_json::from_value(raw_manifest.clone())?;
+    let metadata = read_optional_json(&mut zip, BUILD_METADATA_FILE)?;
+    let embedded = read_optional_json(&mut zip, EMBEDDED_MANIFEST)?;
+    Ok(Mrpack {
+        manifest,
+        raw_manifest,
+        metadata,
+        embedded,
+        entries,
+    })
+}
+
+fn read_optional_json<R: Read + Seek, T: DeserializeOwned>(
+    zip: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Option<T>, ImportError> {
+    match read_json_entry(zip, name) {
+        Ok(value) => Ok(Some(value)),
+        Err(ImportError::Zip(zip::result::ZipError::FileNotFound)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,143 @@
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use thiserror::Error;
+use toml_edit::{value, Document, InlineTable, Item, Table};
+
+use crate::config::pack::ModLoaderType;
+use crate::output::{LIT_CLIENT_OVERRIDES, LIT_OVERRIDES, LIT_SERVER_OVERRIDES};
+use crate::uwu_colors::{ErrStyle, FILE_STYLE};
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// The version a new pack starts at.
+const INITIAL_VERSION: &str = "1.0.0";
+
+#[derive(Debug, Error)]
+pub enum InitError {
+    #[error("I/O error on {0}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("{0} already exists, not overwriting it")]
+    AlreadyExists(String),
+    #[error("No {0} given. Pass --{0}, or run in a terminal to be asked for it")]
+    Missing(&'static str),
+}
+
+/// The properties of a new pack. Those that are `None` are asked for.
+pub struct NewPack {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub minecraft_version: Option<String>,
+    pub mod_loader: Option<ModLoaderType>,
+    pub mod_loader_version: Option<String>,
+}
+
+/// Create a pack source in `source_dir`: a `config.toml` with the properties of the pack, and the
+/// override directories.
+pub fn init_pack(source_dir: &Path, pack: NewPack) -> Result<(), InitError> {
+    let config_file = source_dir.join(CONFIG_FILE);
+    if config_file.exists() {
+        return Err(InitError::AlreadyExists(config_file.display().to_string()));
+    }
+    let interactive = std::io::stdin().is_terminal();
+    let ask = |given: Option<String>, flag: &'static str, question: &str, default: Option<&str>| {
+        match given {
+            Some(given) => Ok(given),
+            None if interactive => prompt(question, default).ok_or(InitError::Missing(flag)),
+            None => default.map(str::to_string).ok_or(InitError::Missing(flag)),
+        }
+    };
+
+    let default_name = source_dir
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(source_dir))
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(str::to_string);
+    let name = ask(pack.name, "name", "Name", default_name.as_deref())?;
+    let author = ask(pack.author, "author", "Author", None)?;
+    let minecraft_version = ask(
+        pack.minecraft_version,
+        "minecraft-version",
+        "Minecraft version",
+        None,
+    )?;
+    let mod_loader = match pack.mod_loader {
+        Some(mod_loader) => mod_loader,
+        None if interactive => prompt_mod_loader().ok_or(InitError::Missing("mod-loader"))?,
+        None => return Err(InitError::Missing("mod-loader")),
+    };
+    let mod_loader_version = ask(
+        pack.mod_loader_version,
+        "mod-loader-version",
+        &format!("Version of {}", mod_loader),
+        None,
+    )?;
+
+    let mut document = Document::new();
+    document["name"] = value(name);
+    document["description"] = value("");
+    document["author"] = value(author);
+    document["version"] = value(INITIAL_VERSION);
+    document["minecraft_version"] = value(minecraft_version);
+    let mut loader = InlineTable::new();
+    loader.insert("id", mod_loader.to_string().into());
+    loader.insert("version", mod_loader_version.into());
+    document["mod_loader"] = value(loader);
+    let mut mods = Table::new();
+    mods.set_implicit(true);
+    mods["curseforge"] = Item::Table(Table::new());
+    mods["modrinth"] = Item::Table(Table::new());
+    document["mods"] = Item::Table(mods);
+
+    let io_error = |path: &Path| {
+        let path = path.display().to_string();
+        move |e| InitError::Io(path, e)
+    };
+    for dir in [LIT_OVERRIDES, LIT_CLIENT_OVERRIDES, LIT_SERVER_OVERRIDES] {
+        let dir = source_dir.join(dir);
+        std::fs::create_dir_all(&dir).map_err(io_error(&dir))?;
+    }
+    std::fs::write(&config_file, document.to_string()).map_err(io_error(&config_file))?;
+    log::info!(
+        "Created a pack in '{}'. Add mods with `netherfire add-mods`.",
+        source_dir.display().errstyle(FILE_STYLE)
+    );
+    Ok(())
+}
+
+/// Ask for a value on stderr until one is given, or use `default` for an empty answer. Returns
+/// `None` if stdin is closed.
+fn prompt(question: &str, default: Option<&str>) -> Option<String> {
+    loop {
+        match default {
+            Some(default) => eprint!("{} [{}]: ", question, default),
+            None => eprint!("{}: ", question),
+        }
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+            return None;
+        }
+        match (answer.trim(), default) {
+            ("", Some(default)) => return Some(default.to_string()),
+            ("", None) => eprintln!("Please enter a value."),
+            (answer, _) => return Some(answer.to_string()),
+        }
+    }
+}
+
+fn prompt_mod_loader() -> Option<ModLoaderType> {
+    let choices = ModLoaderType::value_variants()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    loop {
+        let answer = prompt(&format!("Mod loader ({})", choices), None)?;
+        match ModLoaderType::from_str(&answer, true) {
+            Ok(mod_loader) => return Some(mod_loader),
+            Err(_) => eprintln!("Please answer one of {}.", choices),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::output::summary::ArtifactSummary;
+use crate::warnings::Warning;
+
+/// Machine-readable description of a failed run, printed with `--json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorReport {
+    pub code: &'static str,
+    pub message: String,
+    /// Failures of individual mods, if the error is about several mods.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<FailureReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+/// Machine-readable description of a successful run that had warnings or generated artifacts,
+/// printed with `--json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuccessReport {
+    pub warnings: Vec<Warning>,
+    /// The artifacts the run generated, or found up to date.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<ArtifactSummary>,
+    /// How long the run took, if it generated artifacts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+}
+
+/// A failure of a single mod, identified by its site table and key in the config, i.e.
+/// `mods.<site>.<config_key>`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureReport {
+    pub code: &'static str,
+    pub site: &'static str,
+    pub config_key: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+}
+
+/// Build reports for a map of failures by site and mod config key, sorted like their display.
+pub fn failure_reports<E: Error>(
+    failures: &HashMap<(&'static str, String), E>,
+    code: impl Fn(&E) -> &'static str,
+) -> Vec<FailureReport> {
+    failures
+        .iter()
+        .sorted_by_key(|(k, _)| (*k).clone())
+        .map(|((site, config_key), error)| FailureReport {
+            code: code(error),
+            site,
+            config_key: config_key.clone(),
+            message: error.to_string(),
+            cause: error.source().map(|c| c.to_string()),
+        })
+        .collect()
+}
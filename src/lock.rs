@@ -0,0 +1,51 @@
+use std::fs::{File, TryLockError};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::uwu_colors::{ErrStyle, FILE_STYLE};
+
+/// Name of the file used to lock a directory.
+pub const LOCK_FILE: &str = ".netherfire-run.lock";
+
+/// An advisory lock on a directory, held until dropped.
+pub struct DirLock {
+    _file: File,
+}
+
+#[derive(Debug, Error)]
+#[error("Error locking {0}: {1}")]
+pub struct LockError(String, #[source] std::io::Error);
+
+/// Lock the given directory, which must exist, so that other netherfire runs don't write to it at
+/// the same time. Waits for other runs holding the lock to finish.
+pub fn lock_dir(dir: &Path) -> Result<DirLock, LockError> {
+    lock_dir_impl(dir).map_err(|e| LockError(dir.display().to_string(), e))
+}
+
+/// Like [lock_dir], but creates the directory first if needed, for output directories.
+pub fn lock_output_dir(dir: &Path) -> Result<DirLock, LockError> {
+    std::fs::create_dir_all(dir)
+        .and_then(|()| lock_dir_impl(dir))
+        .map_err(|e| LockError(dir.display().to_string(), e))
+}
+
+fn lock_dir_impl(dir: &Path) -> Result<DirLock, std::io::Error> {
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(LOCK_FILE))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            log::info!(
+                "Waiting for another netherfire run using '{}' to finish...",
+                dir.display().errstyle(FILE_STYLE)
+            );
+            tokio::task::block_in_place(|| file.lock())?;
+        }
+        Err(TryLockError::Error(e)) => return Err(e),
+    }
+    Ok(DirLock { _file: file })
+}
@@ -0,0 +1,164 @@
+//! The lockfile `netherfire.lock`, recording the verified mods of a pack with their files, download
+//! URLs, hashes and sides. Committed with the pack, it lets `generate` build the pack without
+//! asking the mod sites again, as long as the inputs of the verification are unchanged.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::checks::verify_mods::{VerifiedMod, VerifiedModContainer};
+use crate::mod_site::{CurseForge, ModSite, Modrinth};
+use crate::output::atomic_file::write_atomic;
+use crate::output::is_safe_path;
+use crate::uwu_colors::{ErrStyle, FILE_STYLE};
+
+/// Name of the lockfile in the pack source directory.
+pub const LOCKFILE: &str = "netherfire.lock";
+
+/// The version of the lockfile format, increased on incompatible changes.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum LockfileError {
+    #[error("I/O error on {0}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("{0} is invalid: {1}")]
+    Json(String, #[source] serde_json::Error),
+    #[error("{0} has format version {1}, which this version of netherfire can't read")]
+    UnsupportedVersion(String, u32),
+    #[error(
+        "{0} doesn't exist. Run `netherfire verify`, or `netherfire generate` without `--locked`, \
+        to create it"
+    )]
+    Missing(String),
+    #[error(
+        "{0} is out of date, as the config, project lists or policy changed. Run `netherfire \
+        verify`, or `netherfire generate` without `--locked`, to update it"
+    )]
+    OutOfDate(String),
+    #[error("{0} has the file name '{2}' for mod {1}, which isn't a plain file name")]
+    UnsafeFileName(String, String, String),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Lockfile {
+    format_version: u32,
+    /// A hash of the inputs of the verification, to tell whether the lockfile is up to date.
+    inputs_hash: String,
+    // Sorted, so the lockfile only changes where the mods do.
+    curseforge: BTreeMap<String, VerifiedMod<CurseForge>>,
+    modrinth: BTreeMap<String, VerifiedMod<Modrinth>>,
+}
+
+/// The state of the lockfile of a pack.
+pub enum LockedMods {
+    Missing,
+    /// The lockfile was made from other inputs.
+    OutOfDate,
+    UpToDate(VerifiedModContainer),
+}
+
+/// The path of the lockfile of a pack, or of one of its build matrix targets.
+pub fn lockfile_path(source_dir: &Path, target: Option<&str>) -> PathBuf {
+    match target {
+        Some(target) => source_dir.join(format!("netherfire-{}.lock", target)),
+        None => source_dir.join(LOCKFILE),
+    }
+}
+
+/// Read the lockfile at `path`, if it's there and was made from the inputs with the hash
+/// `inputs_hash`.
+pub fn read_lockfile(path: &Path, inputs_hash: &str) -> Result<LockedMods, LockfileError> {
+    let name = path.display().to_string();
+    let content = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(LockedMods::Missing),
+        Err(e) => return Err(LockfileError::Io(name, e)),
+    };
+    // Check the version first, as other versions may not parse.
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Version {
+        format_version: u32,
+    }
+    let version = serde_json::from_slice::<Version>(&content)
+        .map_err(|e| LockfileError::Json(name.clone(), e))?;
+    if version.format_version != FORMAT_VERSION {
+        return Err(LockfileError::UnsupportedVersion(
+            name,
+            version.format_version,
+        ));
+    }
+    let lockfile = serde_json::from_slice::<Lockfile>(&content)
+        .map_err(|e| LockfileError::Json(name.clone(), e))?;
+    if lockfile.inputs_hash != inputs_hash {
+        return Ok(LockedMods::OutOfDate);
+    }
+    // The file names are used as paths in the outputs, so a crafted lockfile must not be able to
+    // write outside of them.
+    let unsafe_file_name = lockfile
+        .curseforge
+        .iter()
+        .flat_map(|(k, m)| file_names(m).map(move |f| (k, f)))
+        .chain(
+            lockfile
+                .modrinth
+                .iter()
+                .flat_map(|(k, m)| file_names(m).map(move |f| (k, f))),
+        )
+        .find(|(_, f)| !is_safe_path(f) || f.contains('/'));
+    if let Some((key, file_name)) = unsafe_file_name {
+        return Err(LockfileError::UnsafeFileName(
+            name,
+            key.clone(),
+            file_name.clone(),
+        ));
+    }
+    Ok(LockedMods::UpToDate(VerifiedModContainer {
+        curseforge: HashMap::from_iter(lockfile.curseforge),
+        modrinth: HashMap::from_iter(lockfile.modrinth),
+    }))
+}
+
+/// The names of the files of a mod, for the packs and the server base.
+fn file_names<S: ModSite>(m: &VerifiedMod<S>) -> impl Iterator<Item = &String> {
+    std::iter::once(&m.info.filename).chain(m.server_info.as_ref().map(|i| &i.filename))
+}
+
+/// Write the verified mods to the lockfile at `path`, unless it's up to date already.
+pub fn write_lockfile(
+    path: &Path,
+    inputs_hash: &str,
+    mods: &VerifiedModContainer,
+) -> Result<(), LockfileError> {
+    let name = path.display().to_string();
+    let lockfile = Lockfile {
+        format_version: FORMAT_VERSION,
+        inputs_hash: inputs_hash.to_string(),
+        curseforge: mods
+            .curseforge
+            .iter()
+            .map(|(k, m)| (k.clone(), m.clone()))
+            .collect(),
+        modrinth: mods
+            .modrinth
+            .iter()
+            .map(|(k, m)| (k.clone(), m.clone()))
+            .collect(),
+    };
+    let mut content = serde_json::to_string_pretty(&lockfile)
+        .map_err(|e| LockfileError::Json(name.clone(), e))?;
+    content.push('\n');
+    if std::fs::read(path).is_ok_and(|existing| existing == content.as_bytes()) {
+        return Ok(());
+    }
+    write_atomic(path, content.as_bytes()).map_err(|e| LockfileError::Io(name, e))?;
+    log::info!(
+        "Wrote the verified mods to '{}'.",
+        path.display().errstyle(FILE_STYLE)
+    );
+    Ok(())
+}
@@ -1,25 +1,106 @@
+use std::collections::BTreeSet;
+use std::ffi::OsString;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::process::Termination;
+use std::time::{Duration, Instant};
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use itertools::Itertools;
 use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
 use thiserror::Error;
 
-use crate::checks::verify_mods::{verify_mods, ModsVerificationError};
+use crate::add_mods::{
+    add_mods_from_site, write_config, AddModRequest, AddModsOptions, KeyStrategy, ModsAddError,
+};
+use crate::api::ModrinthError;
+use crate::backups::{backup_config, restore_latest_backup, BackupError};
+use crate::cache::{
+    cache_dir, cache_stats, clean_cache, load_verification, parse_age, parse_size,
+    store_verification, CacheError,
+};
+use crate::checks::artifact::{check_artifact, CheckArtifactError};
+use crate::checks::cdn::check_cdn;
+use crate::checks::client_size::log_client_size;
+use crate::checks::dependency_closure::{print_dependency_closure, DependencyClosureError};
+use crate::checks::diff::{diff_packs, DiffError};
+use crate::checks::doctor::doctor;
+use crate::checks::install_plan::{print_install_plan, InstallPlan};
+use crate::checks::locked_files::check_locked_files;
+use crate::checks::mod_ids::{check_mod_ids, ModIdCheckError};
+use crate::checks::news::{list_news, NewsError};
+use crate::checks::overrides::{check_overrides, OverridesCheckError};
+use crate::checks::round_trip::{check_mrpack_round_trip, RoundTripError};
+use crate::checks::sync_instance::{sync_instance, SyncError};
+use crate::checks::verify_mods::{
+    verify_mods, ModsVerificationError, VerifiedMod, VerifiedModContainer,
+};
+use crate::config::global::OPTIONAL_CONFIG;
 use crate::config::mods::ConfigModContainer;
-use crate::config::pack::PackConfig;
+use crate::config::pack::{ModLoaderType, PackConfig, VersionType};
+use crate::config::policy::{load_policy, LoadedPolicy, PolicyLoadError};
+use crate::config::project_lists::{load_project_lists, ProjectListLoadError, ProjectLists};
+use crate::config::source::{
+    changed_mods_since, load_pack_config, load_pack_targets, ConfigLoadError, PackSource,
+};
+use crate::config::workspace::{load_workspace, WorkspaceLoadError};
+use crate::explain::{explained_codes, explanation};
+use crate::import::{
+    embedded_curseforge_mods, load_curseforge_pack_manifest, load_modrinth_pack_mods, ImportError,
+};
+use crate::init::{init_pack, InitError, NewPack};
+use crate::json_report::{ErrorReport, SuccessReport};
+use crate::lock::{lock_dir, lock_output_dir, LockError};
+use crate::lockfile::{lockfile_path, read_lockfile, write_lockfile, LockedMods, LockfileError};
+use crate::metrics::log_report as log_metrics_report;
+use crate::mod_site::{CurseForge, ModLoadingError, ModSite, Modrinth};
+use crate::output::build_metadata::{config_hash, BuildInfo};
+use crate::output::generated::{clean_generated, CleanError};
+use crate::output::left_out::log_left_out;
+use crate::output::mod_download::{
+    fill_known_sha256, log_reused_downloads, refresh_all_downloads, refresh_downloads,
+    remove_spooled_downloads, LinkMode,
+};
+use crate::output::summary::{log_summary as log_artifact_summary, take_artifacts};
+use crate::output::wiki::{load_modrinth_authors, wiki_markdown};
 use crate::output::{
-    create_curseforge_zip, create_modrinth_pack, create_server_base, CreateCurseForgeZipError,
-    CreateModrinthPackError, CreateServerBaseError,
+    create_curseforge_zip, create_modrinth_pack, create_server_base,
+    rebuild_curseforge_zip_overrides, rebuild_modrinth_pack_overrides,
+    rebuild_server_base_overrides, CreateCurseForgeZipError, CreateModrinthPackError,
+    CreateServerBaseError,
 };
+use crate::plugins::{list_plugins, run_plugin, PluginError, ResolvedPack};
+use crate::progress::{in_phase, set_progress_listener, Phase, ProgressEvent};
+use crate::update_mods::{update_mods, ModsUpdateError};
+use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, FILE_STYLE, SITE_NAME_STYLE, SITE_VAL_STYLE};
+use crate::warnings::{all_warnings, log_summary as log_warning_summary, Warning};
 
+mod add_mods;
+mod api;
+mod backups;
+mod cache;
 mod checks;
 mod config;
+mod explain;
+mod git;
+mod import;
+mod init;
+mod json_report;
+mod lock;
+mod lockfile;
+mod metrics;
 mod mod_site;
 mod output;
+mod plugins;
+mod progress;
+mod retry;
+mod units;
+mod update_mods;
 mod uwu_colors;
+mod warnings;
 
 /// Handles files for a Minecraft modpack.
 ///
@@ -32,7 +113,157 @@ mod uwu_colors;
 #[derive(Parser)]
 #[clap(verbatim_doc_comment)]
 pub struct Netherfire {
-    /// Modpack source folder.
+    /// Verbosity level, repeat to increase.
+    #[clap(short, global = true, action = clap::ArgAction::Count)]
+    pub verbosity: u8,
+    /// On failure, print a JSON description of the error to stdout, for use by other tools. On
+    /// success, print the warnings, if there are any.
+    #[clap(long, global = true)]
+    pub json: bool,
+    /// Print progress events (phases, verified mods, downloaded bytes, copied mod files, written
+    /// artifacts) to stderr as JSON lines, for front-ends rendering their own progress. Log lines
+    /// start with `[` instead, and stdout is left to the results.
+    #[clap(long, global = true)]
+    pub progress_json: bool,
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create a new pack source folder: a `config.toml` with the given properties, asking for
+    /// the missing ones, and the override folders.
+    Init(InitArgs),
+    /// Verify that the modpack configuration loads and is valid.
+    Verify(VerifyArgs),
+    /// Verify the modpack and create distributions from it.
+    Generate(GenerateArgs),
+    /// Remove the artifacts and server bases that `generate` wrote into the given output
+    /// directories. Anything netherfire didn't generate, like the worlds of a server, is kept, and
+    /// so are generated files that were changed since.
+    Clean(CleanArgs),
+    /// Add mods to the modpack configuration, using their latest version for the pack.
+    AddMods(AddModsArgs),
+    /// Set the configured mods to their latest version for the pack. Mods from a pack the config
+    /// extends are left alone.
+    UpdateMods(UpdateModsArgs),
+    /// Compare the configured mods with the mods folder of a launcher instance (Prism, MultiMC,
+    /// CurseForge), by hash.
+    Sync(SyncArgs),
+    /// Compare two states of a pack, each either a pack source folder or an archive generated by
+    /// netherfire, and list the added, removed and changed mods and overrides. Useful for reviewing
+    /// what a release will change.
+    Diff(DiffArgs),
+    /// List the versions of the configured mods that were published for the pack's Minecraft
+    /// version and mod loader after the configured versions, oldest first, with their changelogs.
+    /// Useful for reviewing updates before picking them.
+    News(NewsArgs),
+    /// Print the configured mods and everything they depend on, including optional
+    /// dependencies, as JSON. Each mod lists its dependencies and the mods depending on it.
+    Deps(DepsArgs),
+    /// Write a Markdown overview of the pack, for publishing as a wiki page or project
+    /// description: the mods by category with their summaries, the client-only and server-only
+    /// mods, and the optional mods.
+    ExportWiki(ExportWikiArgs),
+    /// Print how much a client downloads when installing the Modrinth pack, per mod and in total,
+    /// to keep first installs quick.
+    ClientSize(ClientSizeArgs),
+    /// Check that an existing Modrinth pack or CurseForge ZIP follows its format: the manifest's
+    /// structure, the hashes of the listed files, and the layout of the overrides. Useful before
+    /// redistributing third-party or older packs.
+    CheckArtifact(CheckArtifactArgs),
+    /// Check that the download URLs of all mod files of the pack still work and serve files of the
+    /// size their site reported, with HEAD requests. Files are sometimes deleted from their sites
+    /// after a pack was verified, so this is worth running before a release.
+    CheckCdn(CheckCdnArgs),
+    /// Check that the global config, API access, network and cache directory are set up
+    /// correctly.
+    Doctor,
+    /// Print a longer description of an error code and how to resolve the error, or list the
+    /// codes that have one.
+    Explain(ExplainArgs),
+    /// Restore the modpack configuration from before the last command that edited it. Can be
+    /// repeated to go further back.
+    Undo(UndoArgs),
+    /// Manage the download cache.
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+    /// Print the verified pack as JSON, for plugins and other tools.
+    Resolve(ResolveArgs),
+    /// List the plugins on the PATH, i.e. the `netherfire-<name>` executables that can be run as
+    /// `netherfire <name>`.
+    Plugins,
+    #[clap(external_subcommand)]
+    Plugin(Vec<OsString>),
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Show how many files the cache holds and how much space they use.
+    Stats,
+    /// Delete cached files.
+    Clean {
+        /// Only delete files that haven't been used for this long, e.g. `30d`. Supports the units
+        /// `s`, `m`, `h`, `d` and `w`.
+        #[clap(long, value_parser = parse_age)]
+        older_than: Option<Duration>,
+        /// Delete the least recently used files until the cache takes up at most this much space,
+        /// e.g. `10G`. Supports the units `K`, `M`, `G` and `T`, in multiples of 1024.
+        #[clap(long, value_parser = parse_size)]
+        max_size: Option<u64>,
+    },
+    /// Print the path of the cache directory.
+    Path,
+}
+
+#[derive(Args)]
+pub struct InitArgs {
+    /// Folder to create the pack source in. Created if it doesn't exist.
+    pub source: PathBuf,
+    /// Name of the pack. Defaults to the name of the folder.
+    #[clap(long)]
+    pub name: Option<String>,
+    /// Author of the pack.
+    #[clap(long)]
+    pub author: Option<String>,
+    /// Minecraft version of the pack, e.g. `1.20.1`.
+    #[clap(long)]
+    pub minecraft_version: Option<String>,
+    /// Mod loader of the pack.
+    #[clap(long, value_enum)]
+    pub mod_loader: Option<ModLoaderType>,
+    /// Version of the mod loader, e.g. `0.15.0` for Fabric.
+    #[clap(long)]
+    pub mod_loader_version: Option<String>,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Modpack source folder, or a workspace folder to verify all of its packs.
+    pub source: PathBuf,
+    /// Only verify the mods whose entries in `config.toml` changed since the given git revision,
+    /// e.g. `origin/main`, as the others were verified there. All mods are verified if other
+    /// settings, the policy or local project lists changed.
+    #[clap(long, value_name = "REV")]
+    pub changed_only: Option<String>,
+    /// Print what would be installed on the client and the server as JSON: every mod file with
+    /// its destination in the game directory, and whether it's optional.
+    #[clap(long, conflicts_with = "changed_only")]
+    pub plan: bool,
+    /// Instead of verifying the mods, load the files of the mods in `netherfire.lock` from their
+    /// sites again, and fail if any of them changed since they were locked, e.g. because a file
+    /// was replaced on its site. Fails if the lockfile is missing or out of date.
+    #[clap(long, conflicts_with_all = ["changed_only", "plan"])]
+    pub locked: bool,
+}
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Modpack source folder, or a workspace folder to generate all of its packs.
+    /// Each pack of a workspace is written into a sub-directory of the output paths named after
+    /// the pack's folder.
     pub source: PathBuf,
     /// Write a CurseForge-format client modpack ZIP to the given path.
     /// The path should be a directory, the ZIP will be written under it.
@@ -56,6 +287,11 @@ pub struct Netherfire {
     /// Should CurseForge optional mods be included in the Modrinth pack?
     #[clap(long, requires("create_modrinth_pack"))]
     pub no_mrpack_include_optional: bool,
+    /// Only write the manifest and overrides into the Modrinth pack, leaving out CurseForge mods
+    /// instead of downloading them. Useful for quickly checking the manifest, and for packs that
+    /// only use Modrinth mods.
+    #[clap(long, requires("create_modrinth_pack"))]
+    pub mrpack_manifest_only: bool,
     /// Produce a server base folder by downloading mods if needed.
     ///
     /// Optional mods will be included by default. To disable this, pass
@@ -65,31 +301,352 @@ pub struct Netherfire {
     /// Should optional mods be included in the server base?
     #[clap(long, requires("create_server_base"))]
     pub no_server_base_include_optional: bool,
-    /// Verbosity level, repeat to increase.
-    #[clap(short, action = clap::ArgAction::Count)]
-    pub verbosity: u8,
+    /// How mods are put into the server base from the download cache. `hardlink` avoids
+    /// duplicating them on filesystems without reflink support, but editing a mod in the server
+    /// base then also changes the cached file.
+    #[clap(long, value_enum, default_value_t, requires("create_server_base"))]
+    pub link_mode: LinkMode,
+    /// Generate every target of the pack's build matrix instead of the base config.
+    /// Artifacts get the target name added to their version, and server bases are written into a
+    /// sub-directory named after the target.
+    #[clap(long)]
+    pub all_targets: bool,
+    /// Only generate this part of the outputs, taking the rest from the previous build, e.g. to
+    /// quickly rebuild after only changing overrides.
+    #[clap(long, value_enum)]
+    pub only: Option<GeneratePart>,
+    /// Verify the mods even if `config.toml` and the project lists are unchanged since the last
+    /// verification, e.g. to notice mods that were removed from a mod site.
+    #[clap(long)]
+    pub force_verify: bool,
+    /// Verify the pack again and download the mod with this config key again, even if it's in the
+    /// download cache, replacing the cached file. Can be repeated.
+    #[clap(long, value_name = "KEY")]
+    pub refresh: Vec<String>,
+    /// Like `--refresh`, for every mod.
+    #[clap(long, conflicts_with = "refresh")]
+    pub refresh_all: bool,
+    /// Fail if `netherfire.lock` is missing or out of date, instead of verifying the mods, e.g. in
+    /// CI, to make sure the build uses exactly the mods of the lockfile.
+    #[clap(long, conflicts_with_all = ["force_verify", "refresh", "refresh_all"])]
+    pub locked: bool,
+    /// Don't check that no two mods declare the same mod ID in their jars. The check needs every
+    /// mod file, so it downloads mods that the outputs wouldn't.
+    #[clap(long)]
+    pub skip_mod_id_check: bool,
+    /// Read the generated Modrinth pack back and check that it matches the verified pack, to catch
+    /// format regressions.
+    #[clap(long, hide = true)]
+    pub check_round_trip: bool,
+}
+
+#[derive(Args)]
+pub struct AddModsArgs {
+    /// Modpack source folder.
+    pub source: PathBuf,
+    /// How to choose the config keys of the added mods. Defaults to `key_strategy` from the global
+    /// config, or `name`.
+    #[clap(long, value_enum)]
+    pub key_strategy: Option<KeyStrategy>,
+    /// Use the latest version regardless of the pack's mod loader, e.g. for datapack-style mods.
+    /// It's recorded as `ignore_mod_loader` in the config.
+    #[clap(long)]
+    pub ignore_mod_loader: bool,
+    /// Use the latest version for this Minecraft version instead of the pack's, e.g. a build for
+    /// 1.20 that is known to work on 1.20.1. It's recorded as `game_version` in the config.
+    #[clap(long)]
+    pub game_version: Option<String>,
+    /// Only consider versions of this type for the latest version, e.g. `release`, instead of the
+    /// pack's `version_types`. Can be repeated.
+    #[clap(long, value_enum)]
+    pub version_type: Vec<VersionType>,
+    /// Don't add the required dependencies of the added mods. Verification fails until they're
+    /// added.
+    #[clap(long)]
+    pub no_deps: bool,
+    #[clap(subcommand)]
+    pub site: AddModsSite,
+}
+
+#[derive(Args)]
+pub struct UpdateModsArgs {
+    /// Modpack source folder, or a workspace folder to update the mods of all of its packs.
+    pub source: PathBuf,
+    /// Only print which mods would be updated, without changing the config.
+    #[clap(long)]
+    pub dry_run: bool,
+    /// Only consider versions of this type for the latest version, e.g. `release`, instead of the
+    /// pack's `version_types`. Can be repeated.
+    #[clap(long, value_enum)]
+    pub version_type: Vec<VersionType>,
+    /// Use the latest version of every mod regardless of the pack's mod loader. Mods added with
+    /// `--ignore-mod-loader` are always updated this way.
+    #[clap(long)]
+    pub ignore_mod_loader: bool,
+}
+
+/// A part of the outputs that can be generated on its own.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum GeneratePart {
+    /// The mods and manifests, which are verified and downloaded as usual.
+    Mods,
+    /// The overrides. The mods aren't verified or downloaded.
+    Overrides,
+}
+
+#[derive(Args)]
+pub struct UndoArgs {
+    /// Modpack source folder.
+    pub source: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ExplainArgs {
+    /// The error code, as shown with `--json`, e.g. `minecraft_version_mismatch`.
+    pub code: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DepsArgs {
+    /// Modpack source folder.
+    pub source: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ResolveArgs {
+    /// Modpack source folder.
+    pub source: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ClientSizeArgs {
+    /// Modpack source folder.
+    pub source: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CheckCdnArgs {
+    /// Modpack source folder.
+    pub source: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ExportWikiArgs {
+    /// Modpack source folder.
+    pub source: PathBuf,
+    /// File to write the overview to, instead of printing it.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct NewsArgs {
+    /// Modpack source folder.
+    pub source: PathBuf,
+}
+
+#[derive(Args)]
+pub struct SyncArgs {
+    /// Modpack source folder.
+    pub source: PathBuf,
+    /// Launcher instance folder, or its mods folder.
+    pub instance: PathBuf,
+}
+
+#[derive(Subcommand)]
+pub enum AddModsSite {
+    /// Add mods from CurseForge.
+    Curseforge {
+        /// Project IDs of the mods to add.
+        project_ids: Vec<i32>,
+        /// Add the mods of the latest file of the given CurseForge modpack, with the exact
+        /// versions it uses.
+        #[clap(long)]
+        from_pack: Option<i32>,
+    },
+    /// Add mods from Modrinth.
+    Modrinth {
+        /// Project IDs or slugs of the mods to add.
+        project_ids: Vec<String>,
+        /// Add all projects from the given collection. May be repeated.
+        #[clap(long)]
+        collection: Vec<String>,
+        /// Add the mods of the given `.mrpack`, with the exact versions it uses. Files that
+        /// aren't on Modrinth are skipped, except for the CurseForge mods netherfire embedded in
+        /// it, which are added from CurseForge.
+        #[clap(long)]
+        from_mrpack: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// The old state: a modpack source folder, or a generated CurseForge ZIP or Modrinth pack.
+    pub old: PathBuf,
+    /// The new state: a modpack source folder, or a generated CurseForge ZIP or Modrinth pack.
+    pub new: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CleanArgs {
+    /// Output directories to clean, as passed to `generate`, e.g. the server base directory.
+    #[clap(required = true)]
+    pub outputs: Vec<PathBuf>,
+    /// Also remove generated files that were changed since they were generated.
+    #[clap(long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct CheckArtifactArgs {
+    /// The `.mrpack` or CurseForge ZIP to check.
+    pub artifact: PathBuf,
 }
 
 #[derive(Debug, Error)]
 enum NetherfireError {
     #[error("Modpack configuration load error: {0}")]
     PackConfigLoad(#[from] ConfigLoadError),
+    #[error("Project list load error: {0}")]
+    ProjectListLoad(#[from] ProjectListLoadError),
+    #[error("Policy load error: {0}")]
+    PolicyLoad(#[from] PolicyLoadError),
     #[error("Mod verification errors: {0}")]
     ModVerification(#[from] ModsVerificationError),
+    #[error("Mod ID check error: {0}")]
+    ModIdCheck(#[from] ModIdCheckError),
+    #[error("Overrides check error: {0}")]
+    OverridesCheck(#[from] OverridesCheckError),
     #[error("Create CurseForge ZIP error: {0}")]
     CreateCurseForgeZip(#[from] CreateCurseForgeZipError),
     #[error("Create Modrinth Pack error: {0}")]
     CreateModrinthPack(#[from] CreateModrinthPackError),
     #[error("Create server base error: {0}")]
     CreateServerBase(#[from] CreateServerBaseError),
+    #[error("Error loading Modrinth collection {0}: {1}")]
+    LoadCollection(String, #[source] ModLoadingError),
+    #[error("Workspace load error: {0}")]
+    WorkspaceLoad(#[from] WorkspaceLoadError),
+    #[error("Pack {0} has no build matrix targets")]
+    NoTargets(String),
+    #[error("Packs in the workspace failed: {0:?}")]
+    WorkspacePacksFailed(Vec<String>),
+    #[error("Sync error: {0}")]
+    Sync(#[from] SyncError),
+    #[error("Diff error: {0}")]
+    Diff(#[from] DiffError),
+    #[error("News error: {0}")]
+    News(#[from] NewsError),
+    #[error("Dependency closure error: {0}")]
+    DependencyClosure(#[from] DependencyClosureError),
+    #[error("Round trip check error: {0}")]
+    RoundTrip(#[from] RoundTripError),
+    #[error("Check artifact error: {0}")]
+    CheckArtifact(#[from] CheckArtifactError),
+    #[error("The artifact has {0} problems")]
+    InvalidArtifact(usize),
+    #[error("{0} download URLs are broken")]
+    BrokenDownloads(usize),
+    #[error("The files of {0} locked mods changed or can't be loaded anymore")]
+    LockedFilesChanged(usize),
+    #[error("Import error: {0}")]
+    Import(#[from] ImportError),
+    #[error("Add mods errors: {0}")]
+    AddMods(#[from] ModsAddError),
+    #[error("Update mods errors: {0}")]
+    UpdateMods(#[from] ModsUpdateError),
+    #[error("Cache error: {0}")]
+    Cache(#[from] CacheError),
+    #[error("Clean error: {0}")]
+    Clean(#[from] CleanError),
+    #[error("Init error: {0}")]
+    Init(#[from] InitError),
+    #[error("{0} checks failed")]
+    DoctorFailed(usize),
+    #[error("Error collecting build information: {0}")]
+    BuildInfo(#[source] std::io::Error),
+    #[error("{0}")]
+    Lock(#[from] LockError),
+    #[error("Lockfile error: {0}")]
+    Lockfile(#[from] LockfileError),
+    #[error("Error writing config.toml: {0}")]
+    WriteConfig(#[source] std::io::Error),
+    #[error("Error writing the wiki: {0}")]
+    WriteWiki(#[source] std::io::Error),
+    #[error("Error loading the authors of the Modrinth mods: {0}")]
+    LoadAuthors(#[source] ModrinthError),
+    #[error("Backup error: {0}")]
+    Backup(#[from] BackupError),
+    #[error("No explanation for error code '{0}', run `netherfire explain` to list the codes")]
+    UnknownErrorCode(String),
+    #[error("Mods to refresh are not in the config: {}", .0.join(", "))]
+    UnknownRefreshKeys(Vec<String>),
+    #[error("{0}")]
+    Plugin(#[from] PluginError),
 }
 
-#[derive(Debug, Error)]
-enum ConfigLoadError {
-    #[error("I/O Error on config.toml: {0}")]
-    Io(#[from] std::io::Error),
-    #[error("TOML Parse Error: {0}")]
-    TomlParse(#[from] toml::de::Error),
+impl NetherfireError {
+    /// The stable code of the error, for `--json` output and `netherfire explain`.
+    fn code(&self) -> &'static str {
+        match self {
+            NetherfireError::PackConfigLoad(_) => "pack_config_load",
+            NetherfireError::ProjectListLoad(_) => "project_list_load",
+            NetherfireError::PolicyLoad(_) => "policy_load",
+            NetherfireError::ModVerification(_) => "mod_verification",
+            NetherfireError::ModIdCheck(_) => "mod_id_check",
+            NetherfireError::OverridesCheck(_) => "overrides_check",
+            NetherfireError::CreateCurseForgeZip(_) => "create_curseforge_zip",
+            NetherfireError::CreateModrinthPack(_) => "create_modrinth_pack",
+            NetherfireError::CreateServerBase(_) => "create_server_base",
+            NetherfireError::LoadCollection(..) => "load_collection",
+            NetherfireError::WorkspaceLoad(_) => "workspace_load",
+            NetherfireError::NoTargets(_) => "no_targets",
+            NetherfireError::WorkspacePacksFailed(_) => "workspace_packs_failed",
+            NetherfireError::Sync(_) => "sync",
+            NetherfireError::Diff(_) => "diff",
+            NetherfireError::News(_) => "news",
+            NetherfireError::DependencyClosure(_) => "dependency_closure",
+            NetherfireError::RoundTrip(_) => "round_trip",
+            NetherfireError::CheckArtifact(_) => "check_artifact",
+            NetherfireError::InvalidArtifact(_) => "invalid_artifact",
+            NetherfireError::BrokenDownloads(_) => "broken_downloads",
+            NetherfireError::LockedFilesChanged(_) => "locked_files_changed",
+            NetherfireError::Import(_) => "import",
+            NetherfireError::AddMods(_) => "add_mods",
+            NetherfireError::UpdateMods(_) => "update_mods",
+            NetherfireError::Cache(_) => "cache",
+            NetherfireError::Clean(_) => "clean",
+            NetherfireError::Init(_) => "init",
+            NetherfireError::DoctorFailed(_) => "doctor_failed",
+            NetherfireError::BuildInfo(_) => "build_info",
+            NetherfireError::Lock(_) => "lock",
+            NetherfireError::Lockfile(_) => "lockfile",
+            NetherfireError::WriteConfig(_) => "write_config",
+            NetherfireError::WriteWiki(_) => "write_wiki",
+            NetherfireError::LoadAuthors(_) => "load_authors",
+            NetherfireError::Backup(_) => "backup",
+            NetherfireError::UnknownErrorCode(_) => "unknown_error_code",
+            NetherfireError::UnknownRefreshKeys(_) => "unknown_refresh_keys",
+            NetherfireError::Plugin(_) => "plugin",
+        }
+    }
+
+    /// Describe the error for `--json` output.
+    fn json_report(&self) -> ErrorReport {
+        let failures = match self {
+            NetherfireError::ModVerification(e) => e.failure_reports(),
+            NetherfireError::CreateServerBase(CreateServerBaseError::ModDownload(e)) => {
+                e.failure_reports()
+            }
+            _ => Vec::new(),
+        };
+        ErrorReport {
+            code: self.code(),
+            message: self.to_string(),
+            failures,
+            warnings: all_warnings(),
+        }
+    }
 }
 
 impl Termination for NetherfireError {
@@ -103,6 +660,7 @@ impl Termination for NetherfireError {
 async fn main() -> ExitCode {
     let args: Netherfire = Netherfire::parse();
     let verbosity = args.verbosity;
+    let json = args.json;
     env_logger::Builder::new()
         .filter_level(match verbosity {
             0 => LevelFilter::Info,
@@ -124,53 +682,919 @@ async fn main() -> ExitCode {
             writeln!(buf, "{}", record.args())
         })
         .init();
+    if args.progress_json {
+        set_progress_listener(|event: &ProgressEvent| {
+            eprintln!(
+                "{}",
+                serde_json::to_string(event).expect("progress event is serializable")
+            );
+        });
+    }
 
-    match main_for_result(args).await {
-        Ok(_) => ExitCode::SUCCESS,
+    let started = Instant::now();
+    let result = main_for_result(args).await;
+    remove_spooled_downloads();
+    let elapsed = started.elapsed();
+    let artifacts = take_artifacts();
+    log_artifact_summary(&artifacts, elapsed);
+    log_metrics_report(elapsed);
+    log_warning_summary();
+    match result {
+        Ok(_) => {
+            let warnings = all_warnings();
+            if json && !(warnings.is_empty() && artifacts.is_empty()) {
+                let elapsed_ms = (!artifacts.is_empty())
+                    .then(|| elapsed.as_millis().try_into().unwrap_or(u64::MAX));
+                println!(
+                    "{}",
+                    serde_json::to_string(&SuccessReport {
+                        warnings,
+                        artifacts,
+                        elapsed_ms,
+                    })
+                    .expect("success report is serializable")
+                );
+            }
+            ExitCode::SUCCESS
+        }
         Err(e) => {
             log::error!("{:#}", e);
+            if explanation(e.code()).is_some() {
+                log::info!(
+                    "For more information about this error, run `netherfire explain {}`",
+                    e.code()
+                );
+            }
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string(&e.json_report()).expect("error report is serializable")
+                );
+            }
             e.report()
         }
     }
 }
 
 async fn main_for_result(args: Netherfire) -> Result<(), NetherfireError> {
-    let path = args.source.join("config.toml");
-    let s = std::fs::read_to_string(path).map_err(ConfigLoadError::from)?;
-    let pack_config =
-        toml::from_str::<PackConfig<ConfigModContainer>>(&s).map_err(ConfigLoadError::from)?;
+    match args.command {
+        Command::Init(args) => Ok(init_pack(
+            &args.source,
+            NewPack {
+                name: args.name,
+                author: args.author,
+                minecraft_version: args.minecraft_version,
+                mod_loader: args.mod_loader,
+                mod_loader_version: args.mod_loader_version,
+            },
+        )?),
+        Command::Verify(args) => verify(args).await,
+        Command::Generate(args) => generate(args).await,
+        Command::Clean(args) => Ok(clean(args)?),
+        Command::AddMods(args) => add_mods(args).await,
+        Command::UpdateMods(args) => update_mods_command(args).await,
+        Command::Sync(args) => {
+            let (pack_config, _) = load_pack_config(&args.source)?;
+            Ok(sync_instance(&pack_config, &args.instance).await?)
+        }
+        Command::Diff(args) => {
+            diff_packs(&args.old, &args.new)?;
+            Ok(())
+        }
+        Command::News(args) => {
+            let (pack_config, _) = load_pack_config(&args.source)?;
+            list_news(&pack_config).await?;
+            Ok(())
+        }
+        Command::Deps(args) => {
+            let (pack_config, _) = load_pack_config(&args.source)?;
+            Ok(print_dependency_closure(&pack_config).await?)
+        }
+        Command::ClientSize(args) => {
+            let (pack_config, _) = load_pack_config(&args.source)?;
+            log_client_size(&verify_pack(&args.source, pack_config).await?);
+            Ok(())
+        }
+        Command::ExportWiki(args) => {
+            let (pack_config, _) = load_pack_config(&args.source)?;
+            let mut pack_config = verify_pack(&args.source, pack_config).await?;
+            load_modrinth_authors(&mut pack_config)
+                .await
+                .map_err(NetherfireError::LoadAuthors)?;
+            let wiki = wiki_markdown(&pack_config);
+            match &args.output {
+                Some(output) => std::fs::write(output, wiki).map_err(NetherfireError::WriteWiki),
+                None => {
+                    print!("{}", wiki);
+                    Ok(())
+                }
+            }
+        }
+        Command::Resolve(args) => {
+            let (pack_config, _) = load_pack_config(&args.source)?;
+            let pack_config = verify_pack(&args.source, pack_config).await?;
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&ResolvedPack::new(&pack_config))
+                    .expect("resolved pack is serializable")
+            );
+            Ok(())
+        }
+        Command::Plugins => {
+            list_plugins();
+            Ok(())
+        }
+        Command::Plugin(args) => Ok(run_plugin(args)?),
+        Command::CheckArtifact(args) => match check_artifact(&args.artifact).await? {
+            0 => Ok(()),
+            problems => Err(NetherfireError::InvalidArtifact(problems)),
+        },
+        Command::CheckCdn(args) => {
+            let (pack_config, _) = load_pack_config(&args.source)?;
+            match check_cdn(&verify_pack(&args.source, pack_config).await?).await {
+                0 => Ok(()),
+                broken => Err(NetherfireError::BrokenDownloads(broken)),
+            }
+        }
+        Command::Cache { command } => Ok(cache(command)?),
+        Command::Undo(args) => {
+            let _lock = lock_dir(&args.source)?;
+            Ok(restore_latest_backup(&args.source)?)
+        }
+        Command::Doctor => match doctor().await {
+            0 => Ok(()),
+            failed => Err(NetherfireError::DoctorFailed(failed)),
+        },
+        Command::Explain(args) => match args.code {
+            Some(code) => match explanation(&code) {
+                Some(text) => {
+                    println!("{}", text);
+                    Ok(())
+                }
+                None => Err(NetherfireError::UnknownErrorCode(code)),
+            },
+            None => {
+                for code in explained_codes() {
+                    println!("{}", code);
+                }
+                Ok(())
+            }
+        },
+    }
+}
+
+fn cache(command: CacheCommand) -> Result<(), CacheError> {
+    match command {
+        CacheCommand::Stats => {
+            let sections = cache_stats()?;
+            if sections.is_empty() {
+                log::info!("The cache is empty.");
+            }
+            for (section, stats) in sections {
+                log::info!(
+                    "{}: {}, {}",
+                    section.errstyle(FILE_STYLE),
+                    units::count(stats.files, "file"),
+                    units::size(stats.bytes)
+                );
+            }
+        }
+        CacheCommand::Clean {
+            older_than,
+            max_size,
+        } => {
+            let removed = clean_cache(older_than, max_size)?;
+            log::info!(
+                "Removed {}, freeing {}.",
+                units::count(removed.files, "file"),
+                units::size(removed.bytes)
+            );
+        }
+        CacheCommand::Path => println!("{}", cache_dir().display()),
+    }
+    Ok(())
+}
+
+fn clean(args: CleanArgs) -> Result<(), CleanError> {
+    for output in &args.outputs {
+        let cleaned = clean_generated(output, args.force)?;
+        log::info!(
+            "'{}': removed {}, freeing {}.",
+            output.display().errstyle(FILE_STYLE),
+            units::count(cleaned.removed_files, "file"),
+            units::size(cleaned.removed_bytes)
+        );
+        if !cleaned.kept.is_empty() {
+            log::warn!(
+                "Kept {} in '{}' that changed since they were generated, pass --force to remove them: {}",
+                units::count(cleaned.kept.len() as u64, "file"),
+                output.display().errstyle(FILE_STYLE),
+                cleaned.kept.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
 
-    let pack_config = verify_mods(pack_config).await?;
+/// Check the overrides and verify the mods of a pack, or only the mods that changed since
+/// `changed_since`. With `plan`, the install plan of the pack is printed.
+async fn verify_source(
+    source: &Path,
+    changed_since: Option<&str>,
+    plan: bool,
+    locked: bool,
+) -> Result<(), NetherfireError> {
+    if locked {
+        return verify_locked(source).await;
+    }
+    let Some(rev) = changed_since else {
+        let pack_config = load_and_verify(source).await?;
+        if plan {
+            print_install_plan(&InstallPlan::new(&pack_config.mods));
+        }
+        return Ok(());
+    };
+    let Some(changed) = changed_mods_since(source, rev)? else {
+        log::info!(
+            "Settings, the policy or project lists changed since {}, verifying all mods.",
+            rev.errstyle(CONFIG_VAL_STYLE)
+        );
+        load_and_verify(source).await?;
+        return Ok(());
+    };
+    let (pack_config, pack_source) = load_pack_config(source)?;
+    check_overrides(&pack_source, &pack_config)?;
+    let total = pack_config.mods.curseforge.len() + pack_config.mods.modrinth.len();
+    log::info!(
+        "Verifying {} mods that changed since {}, skipping {} unchanged ones.",
+        changed.count(),
+        rev.errstyle(CONFIG_VAL_STYLE),
+        total - changed.count()
+    );
+    let (project_lists, policy) = load_verification_inputs(source, &pack_config).await?;
+    verify_mods(pack_config, project_lists, policy.as_ref(), Some(&changed)).await?;
+    Ok(())
+}
 
-    if let Some(cf_zip) = args.create_curseforge_zip {
-        create_curseforge_zip(
-            &pack_config,
+/// Check that the files of the mods in the pack's lockfile are unchanged on their sites. Fails if
+/// the lockfile is missing or out of date.
+async fn verify_locked(source: &Path) -> Result<(), NetherfireError> {
+    let (pack_config, pack_source) = load_pack_config(source)?;
+    let (project_lists, policy) = load_verification_inputs(source, &pack_config).await?;
+    let config_hash = config_hash(&pack_source).map_err(NetherfireError::BuildInfo)?;
+    let inputs_hash = verification_inputs_hash(&config_hash, None, &project_lists, policy.as_ref());
+    let lockfile = lockfile_path(source, None);
+    let lockfile_name = lockfile.display().to_string();
+    let locked = match read_lockfile(&lockfile, &inputs_hash)? {
+        LockedMods::UpToDate(mods) => mods,
+        LockedMods::Missing => return Err(LockfileError::Missing(lockfile_name).into()),
+        LockedMods::OutOfDate => return Err(LockfileError::OutOfDate(lockfile_name).into()),
+    };
+    match check_locked_files(&pack_config.mods, &locked).await {
+        0 => Ok(()),
+        changed => Err(NetherfireError::LockedFilesChanged(changed)),
+    }
+}
+
+/// Load, check and verify a pack, and write its verified mods to its lockfile.
+async fn load_and_verify(
+    source: &Path,
+) -> Result<PackConfig<VerifiedModContainer>, NetherfireError> {
+    let (pack_config, pack_source) = load_pack_config(source)?;
+    check_overrides(&pack_source, &pack_config)?;
+    let (project_lists, policy) = load_verification_inputs(source, &pack_config).await?;
+    let config_hash = config_hash(&pack_source).map_err(NetherfireError::BuildInfo)?;
+    let inputs_hash = verification_inputs_hash(&config_hash, None, &project_lists, policy.as_ref());
+    let mut pack_config = verify_mods(pack_config, project_lists, policy.as_ref(), None).await?;
+    fill_known_sha256(&mut pack_config.mods);
+    write_lockfile(
+        &lockfile_path(source, None),
+        &inputs_hash,
+        &pack_config.mods,
+    )?;
+    Ok(pack_config)
+}
+
+async fn verify_pack(
+    source: &Path,
+    pack_config: PackConfig<ConfigModContainer>,
+) -> Result<PackConfig<VerifiedModContainer>, NetherfireError> {
+    let (project_lists, policy) = load_verification_inputs(source, &pack_config).await?;
+    Ok(verify_mods(pack_config, project_lists, policy.as_ref(), None).await?)
+}
+
+/// Load the project lists and policy of a pack, with the banned projects of the policy blocked.
+async fn load_verification_inputs(
+    source: &Path,
+    pack_config: &PackConfig<ConfigModContainer>,
+) -> Result<(ProjectLists, Option<LoadedPolicy>), NetherfireError> {
+    let mut project_lists = load_project_lists(source, &pack_config.project_lists).await?;
+    let policy = load_policy(source, pack_config.policy.as_deref()).await?;
+    if let Some(policy) = &policy {
+        policy.block_banned_projects(&mut project_lists);
+    }
+    Ok((project_lists, policy))
+}
+
+/// A hash of what verifying a pack depends on: its config, the build matrix target, and the
+/// project lists and policy it's verified against.
+fn verification_inputs_hash(
+    config_hash: &str,
+    target: Option<&str>,
+    project_lists: &ProjectLists,
+    policy: Option<&LoadedPolicy>,
+) -> String {
+    let mut hasher = sha2::Sha256::new();
+    for input in [
+        target.unwrap_or_default(),
+        config_hash,
+        &project_lists.content_hash,
+        policy.map_or("", |p| &p.content_hash),
+    ] {
+        hasher.update(input.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// A verification result in the cache, with the warnings it had, to report them again.
+#[derive(Serialize, Deserialize)]
+struct CachedVerification {
+    mods: VerifiedModContainer,
+    warnings: Vec<Warning>,
+}
+
+/// Like [verify_pack], but takes the mods from the pack's lockfile, or reuses the result of a
+/// previous verification, if `config.toml` and the project lists and policy haven't changed since,
+/// unless `force` is set. With `locked`, fails if the lockfile is missing or out of date instead of
+/// verifying. The lockfile is updated after verifying.
+async fn verify_pack_cached(
+    source: &Path,
+    pack_config: PackConfig<ConfigModContainer>,
+    build_info: &BuildInfo,
+    force: bool,
+    locked: bool,
+) -> Result<PackConfig<VerifiedModContainer>, NetherfireError> {
+    let (project_lists, policy) = load_verification_inputs(source, &pack_config).await?;
+    let inputs_hash = verification_inputs_hash(
+        &build_info.config_hash,
+        build_info.target.as_deref(),
+        &project_lists,
+        policy.as_ref(),
+    );
+    let lockfile = lockfile_path(source, build_info.target.as_deref());
+
+    if !force {
+        let lockfile_name = lockfile.display().to_string();
+        let locked_mods = match read_lockfile(&lockfile, &inputs_hash) {
+            // A lockfile cut short, e.g. by a crash while writing it, is written again like a
+            // missing one.
+            Err(LockfileError::Json(_, e)) if !locked => {
+                log::warn!(
+                    "'{}' is invalid, updating it: {}",
+                    lockfile_name.errstyle(FILE_STYLE),
+                    e
+                );
+                LockedMods::Missing
+            }
+            result => result?,
+        };
+        match locked_mods {
+            LockedMods::UpToDate(mut mods) => {
+                log::info!(
+                    "Taking the mods from '{}', as the inputs are unchanged since it was written.",
+                    lockfile_name.errstyle(FILE_STYLE)
+                );
+                fill_known_sha256(&mut mods);
+                return Ok(pack_config.with_mods(mods));
+            }
+            LockedMods::Missing if locked => {
+                return Err(LockfileError::Missing(lockfile_name).into())
+            }
+            LockedMods::OutOfDate if locked => {
+                return Err(LockfileError::OutOfDate(lockfile_name).into())
+            }
+            LockedMods::Missing => {}
+            LockedMods::OutOfDate => log::info!(
+                "'{}' is out of date, updating it.",
+                lockfile_name.errstyle(FILE_STYLE)
+            ),
+        }
+    }
+    let key = format!(
+        "{:x}",
+        sha2::Sha256::digest(format!("{}\0{}", env!("CARGO_PKG_VERSION"), inputs_hash))
+    );
+    let cached = if force {
+        None
+    } else {
+        load_verification::<CachedVerification>(&key)
+    };
+    let pack_config = match cached {
+        Some(cached) => {
+            log::info!("Inputs are unchanged since the last verification, skipping it.");
+            warnings::restore(cached.warnings);
+            let mut mods = cached.mods;
+            fill_known_sha256(&mut mods);
+            pack_config.with_mods(mods)
+        }
+        None => {
+            let warnings_mark = warnings::mark();
+            let mut pack_config =
+                verify_mods(pack_config, project_lists, policy.as_ref(), None).await?;
+            fill_known_sha256(&mut pack_config.mods);
+            let cached = CachedVerification {
+                mods: pack_config.mods.clone(),
+                warnings: warnings::warnings_since(warnings_mark),
+            };
+            if let Err(e) = store_verification(&key, &cached) {
+                log::warn!("Failed to cache the verification result: {}", e);
+            }
+            pack_config
+        }
+    };
+    write_lockfile(&lockfile, &inputs_hash, &pack_config.mods)?;
+    Ok(pack_config)
+}
+
+async fn verify(args: VerifyArgs) -> Result<(), NetherfireError> {
+    let Some(packs) = load_workspace(&args.source)? else {
+        return verify_source(
             &args.source,
-            cf_zip,
-            !args.no_cf_zip_include_optional,
+            args.changed_only.as_deref(),
+            args.plan,
+            args.locked,
+        )
+        .await;
+    };
+
+    // Verify every pack, so all failures are reported at once.
+    let mut failed = Vec::new();
+    for pack in packs {
+        log::info!(
+            "Verifying pack '{}'...",
+            pack.display().errstyle(FILE_STYLE)
+        );
+        if let Err(e) =
+            verify_source(&pack, args.changed_only.as_deref(), args.plan, args.locked).await
+        {
+            log::error!("{:#}", e);
+            failed.push(pack.display().to_string());
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(NetherfireError::WorkspacePacksFailed(failed))
+    }
+}
+
+async fn generate(args: GenerateArgs) -> Result<(), NetherfireError> {
+    let Some(packs) = load_workspace(&args.source)? else {
+        generate_pack(&args, &args.source, None).await?;
+        log_reused_downloads();
+        return Ok(());
+    };
+
+    for pack in packs {
+        log::info!(
+            "Generating pack '{}'...",
+            pack.display().errstyle(FILE_STYLE)
+        );
+        // Give each pack its own output directories, so server bases don't collide.
+        let output_subdir = pack.file_name().map(PathBuf::from);
+        generate_pack(&args, &pack, output_subdir.as_deref()).await?;
+    }
+    log_reused_downloads();
+    Ok(())
+}
+
+/// The URLs of the files of the mods with the given config keys, from either site. With `strict`,
+/// the keys that aren't in the config are returned as an error.
+fn refreshed_urls(
+    mods: &VerifiedModContainer,
+    keys: &[String],
+    strict: bool,
+) -> Result<Vec<String>, Vec<String>> {
+    fn urls<S: ModSite>(m: &VerifiedMod<S>) -> impl Iterator<Item = String> + '_ {
+        [Some(&m.info), m.server_info.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(|info| info.url.clone())
+    }
+
+    let mut result = Vec::new();
+    let mut unknown = Vec::new();
+    for key in keys {
+        let curseforge = mods.curseforge.get(key).into_iter().flat_map(urls);
+        let modrinth = mods.modrinth.get(key).into_iter().flat_map(urls);
+        let found = curseforge.chain(modrinth).collect::<Vec<_>>();
+        if found.is_empty() {
+            unknown.push(key.clone());
+        }
+        result.extend(found);
+    }
+    if strict && !unknown.is_empty() {
+        return Err(unknown);
+    }
+    Ok(result)
+}
+
+async fn generate_pack(
+    args: &GenerateArgs,
+    source: &Path,
+    output_subdir: Option<&Path>,
+) -> Result<(), NetherfireError> {
+    // Hold locks on the source and output directories, so concurrent runs don't interleave writes.
+    // Each directory is only locked once, as locking it again would wait for ourselves. Only the
+    // output directories are created, so a mistyped source fails instead of being created empty.
+    let output_dirs = [
+        &args.create_curseforge_zip,
+        &args.create_modrinth_pack,
+        &args.create_server_base,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|output| match output_subdir {
+        Some(subdir) => output.join(subdir),
+        None => output.clone(),
+    })
+    .filter(|dir| dir != source)
+    .collect::<BTreeSet<_>>();
+    let _source_lock = lock_dir(source)?;
+    let _output_locks = output_dirs
+        .iter()
+        .map(|dir| lock_output_dir(dir))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !args.all_targets {
+        let (pack_config, pack_source) = load_pack_config(source)?;
+        return generate_target(args, source, &pack_source, pack_config, None, output_subdir).await;
+    }
+
+    let (targets, pack_source) = load_pack_targets(source)?;
+    if targets.is_empty() {
+        return Err(NetherfireError::NoTargets(source.display().to_string()));
+    }
+    for target in targets {
+        log::info!(
+            "Generating target {}...",
+            target.name.errstyle(CONFIG_VAL_STYLE)
+        );
+        generate_target(
+            args,
+            source,
+            &pack_source,
+            target.config,
+            Some(&target.name),
+            output_subdir,
         )
         .await?;
     }
+    Ok(())
+}
 
-    if let Some(mrpack) = args.create_modrinth_pack {
-        create_modrinth_pack(
-            &pack_config,
-            &args.source,
-            mrpack,
-            !args.no_mrpack_include_optional,
+async fn generate_target(
+    args: &GenerateArgs,
+    source: &Path,
+    pack_source: &PackSource,
+    mut pack_config: PackConfig<ConfigModContainer>,
+    target: Option<&str>,
+    output_subdir: Option<&Path>,
+) -> Result<(), NetherfireError> {
+    let output_dir = |dir: &PathBuf| match output_subdir {
+        Some(subdir) => dir.join(subdir),
+        None => dir.clone(),
+    };
+    check_overrides(pack_source, &pack_config)?;
+    let build_info = BuildInfo::new(&pack_config, pack_source, target.map(str::to_string))
+        .map_err(NetherfireError::BuildInfo)?;
+    pack_config.version = build_info.expand_git_placeholders(&pack_config.version);
+    let server_base_dir = args.create_server_base.as_ref().map(|dir| {
+        let mut dir = output_dir(dir);
+        if let Some(target) = target {
+            dir.push(target);
+        }
+        dir
+    });
+
+    if args.only == Some(GeneratePart::Overrides) {
+        if let Some(cf_zip) = &args.create_curseforge_zip {
+            rebuild_curseforge_zip_overrides(
+                &pack_config,
+                &build_info,
+                pack_source,
+                output_dir(cf_zip),
+            )
+            .await?;
+        }
+        if let Some(mrpack) = &args.create_modrinth_pack {
+            rebuild_modrinth_pack_overrides(
+                &pack_config,
+                &build_info,
+                pack_source,
+                output_dir(mrpack),
+            )
+            .await?;
+        }
+        if let Some(server_base_dir) = server_base_dir {
+            rebuild_server_base_overrides(&pack_config, &build_info, pack_source, server_base_dir)
+                .await?;
+        }
+        return Ok(());
+    }
+
+    let reuse_overrides = args.only == Some(GeneratePart::Mods);
+    let refresh = args.refresh_all || !args.refresh.is_empty();
+    let mut pack_config = verify_pack_cached(
+        source,
+        pack_config,
+        &build_info,
+        args.force_verify || refresh,
+        args.locked,
+    )
+    .await?;
+    if args.refresh_all {
+        refresh_all_downloads();
+    } else if refresh {
+        // In a workspace, a mod may only be in some of the packs.
+        let urls = refreshed_urls(&pack_config.mods, &args.refresh, output_subdir.is_none())
+            .map_err(NetherfireError::UnknownRefreshKeys)?;
+        refresh_downloads(urls);
+    }
+    if !args.skip_mod_id_check {
+        check_mod_ids(&pack_config.mods).await?;
+        // The check downloaded every mod, so their SHA-256 hashes are known now.
+        fill_known_sha256(&mut pack_config.mods);
+    }
+    let plan = InstallPlan::new(&pack_config.mods);
+
+    if let Some(cf_zip) = &args.create_curseforge_zip {
+        in_phase(
+            Phase::CurseForgeZip,
+            create_curseforge_zip(
+                &pack_config,
+                &build_info,
+                pack_source,
+                output_dir(cf_zip),
+                plan.for_artifact(!args.no_cf_zip_include_optional),
+                reuse_overrides,
+            ),
         )
         .await?;
     }
 
-    if let Some(server_base_dir) = args.create_server_base {
-        create_server_base(
-            &pack_config,
-            &args.source,
-            server_base_dir,
-            !args.no_server_base_include_optional,
+    if let Some(mrpack) = &args.create_modrinth_pack {
+        let mrpack_file = in_phase(
+            Phase::ModrinthPack,
+            create_modrinth_pack(
+                &pack_config,
+                &build_info,
+                pack_source,
+                output_dir(mrpack),
+                plan.for_artifact(!args.no_mrpack_include_optional),
+                reuse_overrides,
+                args.mrpack_manifest_only,
+            ),
+        )
+        .await?;
+        if args.check_round_trip {
+            tokio::task::block_in_place(|| check_mrpack_round_trip(&pack_config, &mrpack_file))?;
+        }
+    }
+
+    if let Some(server_base_dir) = server_base_dir {
+        in_phase(
+            Phase::ServerBase,
+            create_server_base(
+                &pack_config,
+                &build_info,
+                pack_source,
+                server_base_dir,
+                plan.for_artifact(!args.no_server_base_include_optional),
+                reuse_overrides,
+                args.link_mode,
+            ),
         )
         .await?;
     }
 
+    log_left_out();
     Ok(())
 }
+
+async fn add_mods(args: AddModsArgs) -> Result<(), NetherfireError> {
+    let _lock = lock_dir(&args.source)?;
+    let (pack_config, _) = load_pack_config(&args.source)?;
+    // Refuse what verification would reject, e.g. mods the policy bans.
+    let (project_lists, policy) = load_verification_inputs(&args.source, &pack_config).await?;
+    // Only edit this pack's own config, even if it extends another pack.
+    let config_file = args.source.join("config.toml");
+    let config_name = config_file.display().to_string();
+    let mut document = std::fs::read_to_string(&config_file)
+        .map_err(|e| ConfigLoadError::Io(config_name.clone(), e))?
+        .parse::<toml_edit::Document>()
+        .map_err(|e| ConfigLoadError::TomlEditParse(config_name, e))?;
+
+    let options = AddModsOptions {
+        key_strategy: args.key_strategy.unwrap_or_else(|| {
+            OPTIONAL_CONFIG
+                .as_ref()
+                .map(|c| c.key_strategy)
+                .unwrap_or_default()
+        }),
+        ignore_mod_loader: args.ignore_mod_loader,
+        game_version: args.game_version,
+        version_types: (!args.version_type.is_empty()).then_some(args.version_type),
+        no_deps: args.no_deps,
+        policy,
+    };
+
+    let result = match args.site {
+        AddModsSite::Curseforge {
+            project_ids,
+            from_pack,
+        } => {
+            let mut requests = project_ids
+                .into_iter()
+                .map(AddModRequest::latest)
+                .collect::<Vec<_>>();
+            if let Some(pack_id) = from_pack {
+                let manifest = load_curseforge_pack_manifest(pack_id).await?;
+                log::info!(
+                    "Pack {} ({}) contains {} mods.",
+                    manifest.name.errstyle(SITE_VAL_STYLE),
+                    manifest.version,
+                    manifest.files.len()
+                );
+                requests.extend(manifest.files.into_iter().map(|f| AddModRequest {
+                    project_id: f.project_id,
+                    version_id: Some(f.file_id),
+                    client_optional: !f.required,
+                }));
+            }
+            add_mods_from_site(
+                &pack_config,
+                &pack_config.mods.curseforge,
+                &project_lists.curseforge,
+                &mut document,
+                CurseForge,
+                requests,
+                &options,
+            )
+            .await
+        }
+        AddModsSite::Modrinth {
+            mut project_ids,
+            collection,
+            from_mrpack,
+        } => {
+            let mut requests = Vec::new();
+            let mut embedded_requests = Vec::new();
+            if let Some(path) = from_mrpack {
+                let (pack, pack_requests) = load_modrinth_pack_mods(&path).await?;
+                log::info!(
+                    "Pack {} ({}) contains {} mods.",
+                    pack.manifest.name.errstyle(SITE_VAL_STYLE),
+                    pack.manifest.version_id,
+                    pack.manifest.files.len()
+                );
+                requests.extend(pack_requests);
+                embedded_requests = embedded_curseforge_mods(&pack);
+                if !embedded_requests.is_empty() {
+                    log::info!(
+                        "It embeds {} {} mods, which are added from there.",
+                        embedded_requests.len(),
+                        "CurseForge".errstyle(SITE_NAME_STYLE)
+                    );
+                }
+            }
+            for collection_id in collection {
+                let projects = Modrinth
+                    .get_collection_projects(&collection_id)
+                    .await
+                    .map_err(|e| NetherfireError::LoadCollection(collection_id.clone(), e))?;
+                log::info!(
+                    "Collection {} contains {} projects.",
+                    collection_id.errstyle(SITE_VAL_STYLE),
+                    projects.len()
+                );
+                project_ids.extend(projects);
+            }
+            let result = add_mods_from_site(
+                &pack_config,
+                &pack_config.mods.modrinth,
+                &project_lists.modrinth,
+                &mut document,
+                Modrinth,
+                requests
+                    .into_iter()
+                    .chain(project_ids.into_iter().unique().map(AddModRequest::latest))
+                    .collect(),
+                &options,
+            )
+            .await;
+            if embedded_requests.is_empty() {
+                result
+            } else {
+                let embedded_result = add_mods_from_site(
+                    &pack_config,
+                    &pack_config.mods.curseforge,
+                    &project_lists.curseforge,
+                    &mut document,
+                    CurseForge,
+                    embedded_requests,
+                    &options,
+                )
+                .await;
+                ModsAddError::combine(result, embedded_result)
+            }
+        }
+    };
+
+    // Write whatever was added, even if some mods failed.
+    let added = match &result {
+        Ok(added) => *added,
+        Err(e) => e.added,
+    };
+    if added > 0 {
+        backup_config(&args.source)?;
+        write_config(&args.source, &document).map_err(NetherfireError::WriteConfig)?;
+        log::info!(
+            "Added {} mods to {}.",
+            added,
+            "config.toml".errstyle(FILE_STYLE)
+        );
+    }
+
+    result.map(|_| ()).map_err(NetherfireError::from)
+}
+
+async fn update_mods_command(args: UpdateModsArgs) -> Result<(), NetherfireError> {
+    let Some(packs) = load_workspace(&args.source)? else {
+        return update_pack_mods(&args, &args.source).await;
+    };
+
+    // Update every pack, so all failures are reported at once.
+    let mut failed = Vec::new();
+    for pack in packs {
+        log::info!(
+            "Updating the mods of pack '{}'...",
+            pack.display().errstyle(FILE_STYLE)
+        );
+        if let Err(e) = update_pack_mods(&args, &pack).await {
+            log::error!("{:#}", e);
+            failed.push(pack.display().to_string());
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(NetherfireError::WorkspacePacksFailed(failed))
+    }
+}
+
+async fn update_pack_mods(args: &UpdateModsArgs, source: &Path) -> Result<(), NetherfireError> {
+    let _lock = lock_dir(source)?;
+    let (pack_config, _) = load_pack_config(source)?;
+    // Only edit this pack's own config, even if it extends another pack.
+    let config_file = source.join("config.toml");
+    let config_name = config_file.display().to_string();
+    let mut document = std::fs::read_to_string(&config_file)
+        .map_err(|e| ConfigLoadError::Io(config_name.clone(), e))?
+        .parse::<toml_edit::Document>()
+        .map_err(|e| ConfigLoadError::TomlEditParse(config_name, e))?;
+
+    let version_types = (!args.version_type.is_empty()).then_some(args.version_type.as_slice());
+    let result = update_mods(
+        &pack_config,
+        &mut document,
+        version_types,
+        args.ignore_mod_loader,
+        args.dry_run,
+    )
+    .await;
+
+    // Write whatever was updated, even if some mods failed.
+    let updated = match &result {
+        Ok(updated) => *updated,
+        Err(e) => e.updated,
+    };
+    if updated == 0 {
+        if result.is_ok() {
+            log::info!("All mods are at their latest version.");
+        }
+    } else if args.dry_run {
+        log::info!("{} mods would be updated.", updated);
+    } else {
+        backup_config(source)?;
+        write_config(source, &document).map_err(NetherfireError::WriteConfig)?;
+        log::info!(
+            "Updated {} mods in {}.",
+            updated,
+            "config.toml".errstyle(FILE_STYLE)
+        );
+    }
+
+    result.map(|_| ()).map_err(NetherfireError::from)
+}
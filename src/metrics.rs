@@ -0,0 +1,117 @@
+//! Counts of the API requests made during a run and the rate limits the sites reported, so the
+//! report at the end of the run shows how close a pack gets to the quotas.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+
+use crate::units;
+use crate::uwu_colors::{ErrStyle, SITE_NAME_STYLE};
+
+#[derive(Debug, Copy, Clone)]
+pub enum ApiSite {
+    CurseForge,
+    Modrinth,
+}
+
+/// The requests made to the API of a site.
+#[derive(Debug, Copy, Clone)]
+struct SiteMetrics {
+    requests: u64,
+    rate_limited: u64,
+    /// The time spent waiting for responses, summed over concurrent requests.
+    time: Duration,
+    /// The fewest requests the site reported to be left before reaching its rate limit.
+    lowest_remaining: Option<u64>,
+    limit: Option<u64>,
+}
+
+static CURSEFORGE: Mutex<SiteMetrics> = Mutex::new(SiteMetrics::new());
+static MODRINTH: Mutex<SiteMetrics> = Mutex::new(SiteMetrics::new());
+
+impl SiteMetrics {
+    const fn new() -> Self {
+        Self {
+            requests: 0,
+            rate_limited: 0,
+            time: Duration::ZERO,
+            lowest_remaining: None,
+            limit: None,
+        }
+    }
+}
+
+fn metrics(site: ApiSite) -> &'static Mutex<SiteMetrics> {
+    match site {
+        ApiSite::CurseForge => &CURSEFORGE,
+        ApiSite::Modrinth => &MODRINTH,
+    }
+}
+
+/// Record a request to the API of `site` that took `time`, with the status and headers of its
+/// response, if there is one.
+pub fn record_request(site: ApiSite, time: Duration, response: Option<(StatusCode, &HeaderMap)>) {
+    let header = |headers: &HeaderMap, name: &str| -> Option<u64> {
+        headers.get(name)?.to_str().ok()?.parse().ok()
+    };
+    let mut metrics = metrics(site).lock().expect("metrics lock");
+    metrics.requests += 1;
+    metrics.time += time;
+    let Some((status, headers)) = response else {
+        return;
+    };
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        metrics.rate_limited += 1;
+    }
+    if let Some(remaining) = header(headers, "x-ratelimit-remaining") {
+        metrics.lowest_remaining = Some(
+            metrics
+                .lowest_remaining
+                .map_or(remaining, |r| r.min(remaining)),
+        );
+    }
+    if let Some(limit) = header(headers, "x-ratelimit-limit") {
+        metrics.limit = Some(limit);
+    }
+}
+
+/// Log how long the run took and the requests made to each site, if there were any.
+pub fn log_report(elapsed: Duration) {
+    let sites = [
+        ("CurseForge", *CURSEFORGE.lock().expect("metrics lock")),
+        ("Modrinth", *MODRINTH.lock().expect("metrics lock")),
+    ];
+    if sites.iter().all(|(_, m)| m.requests == 0) {
+        return;
+    }
+    log::info!("Finished in {}.", units::duration(elapsed));
+    for (name, metrics) in sites {
+        if metrics.requests == 0 {
+            continue;
+        }
+        let quota = match (metrics.lowest_remaining, metrics.limit) {
+            (Some(remaining), Some(limit)) => {
+                format!(
+                    ", at least {} of {} left in the rate limit",
+                    units::number(remaining),
+                    units::number(limit)
+                )
+            }
+            (Some(remaining), None) => format!(
+                ", at least {} left in the rate limit",
+                units::number(remaining)
+            ),
+            _ => String::new(),
+        };
+        log::info!(
+            "[{}] {} taking {} in total, {} rate limited{}.",
+            name.errstyle(SITE_NAME_STYLE),
+            units::count(metrics.requests, "API request"),
+            units::duration(metrics.time),
+            units::number(metrics.rate_limited),
+            quota
+        );
+    }
+}
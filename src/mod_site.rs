@@ -1,28 +1,81 @@
-use std::fmt::Debug;
-use std::future::Future;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::io::{Read, Write};
+use std::sync::Mutex;
 
 use digest::Digest;
 use ferinth::structures::project::{ProjectSupportRange, ProjectType};
 use ferinth::structures::version::DependencyType;
-use furse::structures::file_structs::{FileRelationType, HashAlgo};
+use ferinth::structures::UtcTime;
+use furse::structures::file_structs::{FileRelationType, FileReleaseType, HashAlgo};
 use itertools::Itertools;
-use serde::Deserialize;
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::config::global::{FERINTH, FURSE};
+use crate::api::ModrinthError;
+use crate::config::global::{furse, NoCurseForgeApi, FERINTH, HTTP_CLIENT, OPTIONAL_CONFIG};
+use crate::config::globs::NamePattern;
 use crate::config::mods::EnvRequirement;
+use crate::config::pack::{ModLoaderType, VersionType};
+use crate::warnings::{warn, Warning, WarningKind};
 
-pub trait ModIdValue: Clone + Debug + Eq + std::hash::Hash + Send + Sync + 'static {}
+pub trait ModIdValue:
+    Clone
+    + Debug
+    + Display
+    + Eq
+    + std::hash::Hash
+    + Serialize
+    + DeserializeOwned
+    + Send
+    + Sync
+    + 'static
+{
+}
 
-impl<T> ModIdValue for T where T: Clone + Debug + Eq + std::hash::Hash + Send + Sync + 'static {}
+impl<T> ModIdValue for T where
+    T: Clone
+        + Debug
+        + Display
+        + Eq
+        + std::hash::Hash
+        + Serialize
+        + DeserializeOwned
+        + Send
+        + Sync
+        + 'static
+{
+}
 
-pub trait ModHash: Clone + Send + Sync + 'static {
+pub trait ModHash: Clone + Serialize + DeserializeOwned + Send + Sync + 'static {
     /// Use the strongest available hash to check the content, if possible.
     /// Returns `None` if no hash is available.
     fn check_hash_if_possible(&self, content: &[u8]) -> Option<bool>;
+
+    /// Like [ModHash::check_hash_if_possible], for content too large to keep in memory.
+    fn check_reader_hash_if_possible(&self, reader: &mut dyn Read)
+        -> std::io::Result<Option<bool>>;
+
+    /// The SHA-1 hash of the content, if known.
+    fn sha1(&self) -> Option<&digest::Output<sha1::Sha1>>;
+
+    /// The SHA-256 hash of the content, if it was downloaded before. Sites don't report it.
+    fn sha256(&self) -> Option<&digest::Output<sha2::Sha256>>;
+
+    fn set_sha256(&mut self, sha256: digest::Output<sha2::Sha256>);
+
+    /// The strongest hash the site reports, as `<algorithm>-<hex>`, to find the download in the
+    /// cache by. `None` if the site reports no hash.
+    fn site_hash_key(&self) -> Option<String>;
+
+    /// Every hash the site reports, as `(algorithm, hex)` pairs.
+    fn site_hashes(&self) -> Vec<(&'static str, String)>;
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct ModId<K: ModIdValue> {
     pub project_id: K,
     pub version_id: K,
@@ -32,35 +85,110 @@ pub struct ModId<K: ModIdValue> {
 pub trait ModSite: Copy + Clone + Send + Sync + 'static {
     const NAME: &'static str;
 
+    /// The key of this site's table under `mods` in the config.
+    const CONFIG_KEY: &'static str;
+
     type Id: ModIdValue;
 
     type ModHash: ModHash;
 
     async fn load_metadata(&self, project_id: Self::Id) -> ModLoadingResult;
 
+    /// Find the latest version of the project for the given Minecraft version and mod loader,
+    /// among the versions of the given types, or all if `version_types` is empty. If
+    /// `prefer_featured` is set, the latest version the author featured is picked instead, if the
+    /// site supports it and there is one. Returns `None` if there is no such version.
+    async fn get_latest_version_for_pack(
+        &self,
+        project_id: Self::Id,
+        minecraft_version: &str,
+        mod_loader: &ModLoaderType,
+        ignore_mod_loader: bool,
+        prefer_featured: bool,
+        version_types: &[VersionType],
+    ) -> Result<Option<ModId<Self::Id>>, ModLoadingError>;
+
+    /// List the versions of the project for the given Minecraft version and mod loader that were
+    /// published after the version in `current`, with their changelogs, in no particular order.
+    async fn get_newer_versions_for_pack(
+        &self,
+        current: ModId<Self::Id>,
+        minecraft_version: &str,
+        mod_loader: &ModLoaderType,
+    ) -> Result<Vec<PublishedVersion<Self::Id>>, ModLoadingError>;
+
     async fn load_metadata_by_version(&self, version_id: Self::Id) -> Option<ModLoadingResult>;
 
+    /// The ID of the project, on sites that also accept other references to it, e.g. Modrinth
+    /// slugs. Sites that only accept IDs return it as is.
+    async fn canonical_project_id(
+        &self,
+        project_id: Self::Id,
+    ) -> Result<Self::Id, ModLoadingError> {
+        Ok(project_id)
+    }
+
     async fn load_file(&self, id: ModId<Self::Id>)
         -> ModFileLoadingResult<Self::Id, Self::ModHash>;
+
+    /// Load the file of the version whose name matches `pattern`, among the files the site lists
+    /// for the version. Also returns the ID to load the file with, which differs from `id` on
+    /// sites that give every file its own ID.
+    async fn load_matching_file(
+        &self,
+        id: ModId<Self::Id>,
+        pattern: &NamePattern,
+    ) -> MatchingFileLoadingResult<Self::Id, Self::ModHash> {
+        let info = self.load_file(id.clone()).await?;
+        if !pattern.is_match(&info.filename) {
+            return Err(ModLoadingError::NoMatchingFile(
+                pattern.to_string(),
+                vec![info.filename],
+            ));
+        }
+        Ok((id, info))
+    }
+
+    /// Start a request for downloading a file of this site from `url`, adding the authentication
+    /// the site needs for it, if any. Files on public CDNs need none.
+    fn download_request(&self, url: &str) -> reqwest::RequestBuilder {
+        HTTP_CLIENT.get(url)
+    }
 }
 
+/// Files that were already loaded in this run, so packs of a workspace that share mods only load
+/// them once.
+type FileCache<K, H> = Lazy<Mutex<HashMap<ModId<K>, ModFileInfo<K, H>>>>;
+
 #[derive(Debug, Copy, Clone)]
 pub struct CurseForge;
 
+static CURSEFORGE_FILES: FileCache<i32, CFHash> = Lazy::new(Default::default);
+
 #[async_trait::async_trait]
 impl ModSite for CurseForge {
     const NAME: &'static str = "CurseForge";
 
+    const CONFIG_KEY: &'static str = "curseforge";
+
     type Id = i32;
 
     type ModHash = CFHash;
 
     async fn load_metadata(&self, project_id: Self::Id) -> ModLoadingResult {
-        let furse_mod = FURSE.get_mod(project_id).await?;
+        let furse_mod = furse()?.get_mod(project_id).await?;
 
         Ok(ModInfo {
             name: furse_mod.name,
+            slug: furse_mod.slug,
             distribution_allowed: furse_mod.allow_mod_distribution.unwrap_or(true),
+            // CurseForge doesn't expose licenses in its API.
+            license: None,
+            authors: furse_mod.authors.into_iter().map(|a| a.name).collect(),
+            team: None,
+            url: Some(furse_mod.links.website_url.to_string()),
+            summary: Some(furse_mod.summary),
+            categories: furse_mod.categories.into_iter().map(|c| c.name).collect(),
             side_info: SideInfo {
                 client: EnvRequirement::Unknown,
                 server: EnvRequirement::Unknown,
@@ -68,6 +196,75 @@ impl ModSite for CurseForge {
         })
     }
 
+    async fn get_latest_version_for_pack(
+        &self,
+        project_id: Self::Id,
+        minecraft_version: &str,
+        mod_loader: &ModLoaderType,
+        ignore_mod_loader: bool,
+        // CurseForge has no featured versions.
+        _prefer_featured: bool,
+        version_types: &[VersionType],
+    ) -> Result<Option<ModId<Self::Id>>, ModLoadingError> {
+        let loader_version = curseforge_loader_version(mod_loader);
+        let files = furse()?.get_mod_files(project_id).await?;
+        Ok(files
+            .into_iter()
+            .filter(|f| {
+                f.is_available
+                    && curseforge_version_type(&f.release_type).is_allowed(version_types)
+                    && f.game_versions
+                        .iter()
+                        .any(|v| is_for_minecraft_version(v, minecraft_version))
+            })
+            .filter(|f| ignore_mod_loader || f.game_versions.iter().any(|v| v == loader_version))
+            .max_by_key(|f| f.file_date)
+            .map(|f| ModId {
+                project_id: f.mod_id,
+                version_id: f.id,
+            }))
+    }
+
+    async fn get_newer_versions_for_pack(
+        &self,
+        current: ModId<Self::Id>,
+        minecraft_version: &str,
+        mod_loader: &ModLoaderType,
+    ) -> Result<Vec<PublishedVersion<Self::Id>>, ModLoadingError> {
+        let loader_version = curseforge_loader_version(mod_loader);
+        let files = furse()?.get_mod_files(current.project_id).await?;
+        let current_date = match files.iter().find(|f| f.id == current.version_id) {
+            Some(f) => f.file_date,
+            None => {
+                furse()?
+                    .get_mod_file(current.project_id, current.version_id)
+                    .await?
+                    .file_date
+            }
+        };
+        let mut versions = Vec::new();
+        for file in files.into_iter().filter(|f| {
+            f.is_available
+                && f.file_date > current_date
+                && f.game_versions
+                    .iter()
+                    .any(|v| is_for_minecraft_version(v, minecraft_version))
+                && f.game_versions.iter().any(|v| v == loader_version)
+        }) {
+            // The file list doesn't include changelogs.
+            let changelog = furse()?
+                .get_mod_file_changelog(file.mod_id, file.id)
+                .await?;
+            versions.push(PublishedVersion {
+                version_id: file.id,
+                name: file.display_name,
+                date_published: file.file_date,
+                changelog: Some(changelog).filter(|c| !c.trim().is_empty()),
+            });
+        }
+        Ok(versions)
+    }
+
     async fn load_metadata_by_version(&self, _: Self::Id) -> Option<ModLoadingResult> {
         None
     }
@@ -76,8 +273,11 @@ impl ModSite for CurseForge {
         &self,
         id: ModId<Self::Id>,
     ) -> ModFileLoadingResult<Self::Id, Self::ModHash> {
+        if let Some(cached) = CURSEFORGE_FILES.lock().unwrap().get(&id) {
+            return Ok(cached.clone());
+        }
         let project_info = self.load_metadata(id.project_id).await?;
-        let file = FURSE.get_mod_file(id.project_id, id.version_id).await?;
+        let file = furse()?.get_mod_file(id.project_id, id.version_id).await?;
 
         let mut sha1 = None;
         let mut md5 = None;
@@ -89,7 +289,7 @@ impl ModSite for CurseForge {
             }
         }
 
-        Ok(ModFileInfo {
+        let info = ModFileInfo {
             project_info,
             filename: file.file_name,
             url: file.download_url.expect("verified earlier").to_string(),
@@ -103,23 +303,98 @@ impl ModSite for CurseForge {
                     kind: match d.relation_type {
                         FileRelationType::RequiredDependency => ModDependencyKind::Required,
                         FileRelationType::OptionalDependency => ModDependencyKind::Optional,
+                        FileRelationType::EmbeddedLibrary => ModDependencyKind::Embedded,
                         _ => ModDependencyKind::Other,
                     },
                 })
                 .collect(),
-            hash: CFHash { sha1, md5 },
-        })
+            hash: CFHash {
+                sha1,
+                md5,
+                sha256: None,
+            },
+        };
+        CURSEFORGE_FILES.lock().unwrap().insert(id, info.clone());
+        Ok(info)
+    }
+
+    async fn load_matching_file(
+        &self,
+        id: ModId<Self::Id>,
+        pattern: &NamePattern,
+    ) -> MatchingFileLoadingResult<Self::Id, Self::ModHash> {
+        // A file can have an alternate file, e.g. a version for another mod loader, and
+        // additional files, e.g. API or source jars, which all have their own IDs.
+        let file = furse()?.get_mod_file(id.project_id, id.version_id).await?;
+        let mut candidates = vec![(file.id, file.file_name)];
+        if let Some(alternate_id) = file.alternate_file_id.filter(|a| *a != 0) {
+            let alternate = furse()?.get_mod_file(id.project_id, alternate_id).await?;
+            candidates.push((alternate.id, alternate.file_name));
+        }
+        candidates.extend(
+            furse()?
+                .get_mod_files(id.project_id)
+                .await?
+                .into_iter()
+                .filter(|f| f.parent_project_file_id == Some(id.version_id))
+                .map(|f| (f.id, f.file_name)),
+        );
+        let Some((file_id, _)) = candidates.iter().find(|(_, name)| pattern.is_match(name)) else {
+            return Err(ModLoadingError::NoMatchingFile(
+                pattern.to_string(),
+                candidates.into_iter().map(|(_, name)| name).collect(),
+            ));
+        };
+        let id = ModId {
+            project_id: id.project_id,
+            version_id: *file_id,
+        };
+        let info = self.load_file(id).await?;
+        Ok((id, info))
     }
 }
 
-#[derive(Debug, Clone)]
+/// CurseForge lists the mod loaders as game versions.
+fn curseforge_loader_version(mod_loader: &ModLoaderType) -> &'static str {
+    match mod_loader {
+        ModLoaderType::Forge => "Forge",
+        ModLoaderType::Neoforge => "NeoForge",
+        ModLoaderType::Fabric => "Fabric",
+        ModLoaderType::Quilt => "Quilt",
+    }
+}
+
+fn curseforge_version_type(release_type: &FileReleaseType) -> VersionType {
+    match release_type {
+        FileReleaseType::Release => VersionType::Release,
+        FileReleaseType::Beta => VersionType::Beta,
+        FileReleaseType::Alpha => VersionType::Alpha,
+    }
+}
+
+fn modrinth_version_type(version_type: &ferinth::structures::version::VersionType) -> VersionType {
+    match version_type {
+        ferinth::structures::version::VersionType::Release => VersionType::Release,
+        ferinth::structures::version::VersionType::Beta => VersionType::Beta,
+        ferinth::structures::version::VersionType::Alpha => VersionType::Alpha,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CFHash {
+    #[serde(with = "hex_hash_opt")]
     pub sha1: Option<digest::Output<sha1::Sha1>>,
+    #[serde(with = "hex_hash_opt")]
     pub md5: Option<digest::Output<md5::Md5>>,
+    #[serde(default, with = "hex_hash_opt")]
+    pub sha256: Option<digest::Output<sha2::Sha256>>,
 }
 
 impl ModHash for CFHash {
     fn check_hash_if_possible(&self, content: &[u8]) -> Option<bool> {
+        if let Some(sha256) = self.sha256 {
+            return Some(check_hash::<sha2::Sha256>(&sha256, content));
+        }
         if let Some(sha1) = self.sha1 {
             return Some(check_hash::<sha1::Sha1>(&sha1, content));
         }
@@ -128,28 +403,89 @@ impl ModHash for CFHash {
         }
         None
     }
+
+    fn check_reader_hash_if_possible(
+        &self,
+        reader: &mut dyn Read,
+    ) -> std::io::Result<Option<bool>> {
+        if let Some(sha256) = self.sha256 {
+            return check_reader_hash::<sha2::Sha256>(&sha256, reader).map(Some);
+        }
+        if let Some(sha1) = self.sha1 {
+            return check_reader_hash::<sha1::Sha1>(&sha1, reader).map(Some);
+        }
+        if let Some(md5) = self.md5 {
+            return check_reader_hash::<md5::Md5>(&md5, reader).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn sha1(&self) -> Option<&digest::Output<sha1::Sha1>> {
+        self.sha1.as_ref()
+    }
+
+    fn sha256(&self) -> Option<&digest::Output<sha2::Sha256>> {
+        self.sha256.as_ref()
+    }
+
+    fn set_sha256(&mut self, sha256: digest::Output<sha2::Sha256>) {
+        self.sha256 = Some(sha256);
+    }
+
+    fn site_hash_key(&self) -> Option<String> {
+        match (self.sha1, self.md5) {
+            (Some(sha1), _) => Some(format!("sha1-{:x}", sha1)),
+            (None, Some(md5)) => Some(format!("md5-{:x}", md5)),
+            (None, None) => None,
+        }
+    }
+
+    fn site_hashes(&self) -> Vec<(&'static str, String)> {
+        let sha1 = self.sha1.map(|h| ("sha1", format!("{:x}", h)));
+        let md5 = self.md5.map(|h| ("md5", format!("{:x}", h)));
+        sha1.into_iter().chain(md5).collect()
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
 pub struct Modrinth;
 
+/// The host Modrinth serves files from.
+const MODRINTH_CDN_HOST: &str = "cdn.modrinth.com";
+
+static MODRINTH_FILES: FileCache<String, ModrinthHash> = Lazy::new(Default::default);
+
 #[async_trait::async_trait]
 impl ModSite for Modrinth {
     const NAME: &'static str = "Modrinth";
 
+    const CONFIG_KEY: &'static str = "modrinth";
+
     type Id = String;
 
     type ModHash = ModrinthHash;
 
     async fn load_metadata(&self, project_id: Self::Id) -> ModLoadingResult {
-        let ferinth_mod = ferinth_with_retry(|| FERINTH.get_project(&project_id)).await?;
+        let ferinth_mod = FERINTH.get_project(&project_id).await?;
         if ferinth_mod.project_type != ProjectType::Mod {
             return Err(ModLoadingError::NotAMod);
         }
-
         Ok(ModInfo {
             name: ferinth_mod.title,
+            url: Some(format!("https://modrinth.com/mod/{}", ferinth_mod.slug)),
+            slug: ferinth_mod.slug,
             distribution_allowed: true,
+            license: Some(ferinth_mod.license.id),
+            // Projects only reference their team, which lists the authors.
+            authors: Vec::new(),
+            team: Some(ferinth_mod.team),
+            summary: Some(ferinth_mod.description),
+            categories: ferinth_mod
+                .categories
+                .iter()
+                .filter(|c| !MODRINTH_LOADER_CATEGORIES.contains(&c.as_str()))
+                .map(|c| modrinth_category_name(c))
+                .collect(),
             side_info: SideInfo {
                 client: ferinth_mod.client_side.into(),
                 server: ferinth_mod.server_side.into(),
@@ -157,8 +493,73 @@ impl ModSite for Modrinth {
         })
     }
 
+    async fn get_latest_version_for_pack(
+        &self,
+        project_id: Self::Id,
+        minecraft_version: &str,
+        mod_loader: &ModLoaderType,
+        ignore_mod_loader: bool,
+        prefer_featured: bool,
+        version_types: &[VersionType],
+    ) -> Result<Option<ModId<Self::Id>>, ModLoadingError> {
+        let loader = mod_loader.to_string();
+        let loaders = [loader.as_str()];
+        let game_versions = [minecraft_version];
+        let versions = FERINTH
+            .list_versions_filtered(
+                &project_id,
+                (!ignore_mod_loader).then_some(&loaders[..]),
+                Some(&game_versions),
+                None,
+            )
+            .await?
+            .into_iter()
+            .filter(|v| modrinth_version_type(&v.version_type).is_allowed(version_types))
+            .collect::<Vec<_>>();
+        let featured = prefer_featured && versions.iter().any(|v| v.featured);
+        Ok(versions
+            .into_iter()
+            .filter(|v| !featured || v.featured)
+            .max_by_key(|v| v.date_published)
+            .map(|v| ModId {
+                project_id: v.project_id,
+                version_id: v.id,
+            }))
+    }
+
+    async fn get_newer_versions_for_pack(
+        &self,
+        current: ModId<Self::Id>,
+        minecraft_version: &str,
+        mod_loader: &ModLoaderType,
+    ) -> Result<Vec<PublishedVersion<Self::Id>>, ModLoadingError> {
+        let loader = mod_loader.to_string();
+        let current_date = FERINTH
+            .get_version(&current.version_id)
+            .await?
+            .date_published;
+        let versions = FERINTH
+            .list_versions_filtered(
+                &current.project_id,
+                Some(&[loader.as_str()]),
+                Some(&[minecraft_version]),
+                None,
+            )
+            .await?;
+        Ok(versions
+            .into_iter()
+            .filter(|v| v.date_published > current_date)
+            .map(|v| PublishedVersion {
+                version_id: v.id,
+                name: v.name,
+                date_published: v.date_published,
+                changelog: v.changelog.filter(|c| !c.trim().is_empty()),
+            })
+            .collect())
+    }
+
     async fn load_metadata_by_version(&self, version_id: Self::Id) -> Option<ModLoadingResult> {
-        let version_info = match ferinth_with_retry(|| FERINTH.get_version(&version_id)).await {
+        let version_info = match FERINTH.get_version(&version_id).await {
             Ok(v) => v,
             Err(e) => return Some(Err(e.into())),
         };
@@ -166,17 +567,92 @@ impl ModSite for Modrinth {
         Some(self.load_metadata(version_info.project_id).await)
     }
 
+    async fn canonical_project_id(
+        &self,
+        project_id: Self::Id,
+    ) -> Result<Self::Id, ModLoadingError> {
+        Ok(FERINTH.get_project(&project_id).await?.id)
+    }
+
     async fn load_file(
         &self,
         id: ModId<Self::Id>,
     ) -> ModFileLoadingResult<Self::Id, Self::ModHash> {
-        let project_info = self.load_metadata(id.project_id).await?;
-        let version = ferinth_with_retry(|| FERINTH.get_version(&id.version_id)).await?;
-        let file_meta = version
-            .files
-            .into_iter()
-            .find_or_first(|f| f.primary)
-            .ok_or(ModLoadingError::NoFiles)?;
+        if let Some(cached) = MODRINTH_FILES.lock().unwrap().get(&id) {
+            return Ok(cached.clone());
+        }
+        let info = self.load_version_file(&id, None).await?;
+        MODRINTH_FILES.lock().unwrap().insert(id, info.clone());
+        Ok(info)
+    }
+
+    async fn load_matching_file(
+        &self,
+        id: ModId<Self::Id>,
+        pattern: &NamePattern,
+    ) -> MatchingFileLoadingResult<Self::Id, Self::ModHash> {
+        // All files of a version share its ID.
+        let info = self.load_version_file(&id, Some(pattern)).await?;
+        Ok((id, info))
+    }
+
+    fn download_request(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = HTTP_CLIENT.get(url);
+        // Files of private projects need the token, but it must not leak to other hosts.
+        let token = OPTIONAL_CONFIG
+            .as_ref()
+            .and_then(|c| c.modrinth_token.as_deref())
+            .filter(|_| {
+                reqwest::Url::parse(url).is_ok_and(|u| u.host_str() == Some(MODRINTH_CDN_HOST))
+            });
+        match token {
+            Some(token) => request.header(reqwest::header::AUTHORIZATION, token),
+            None => request,
+        }
+    }
+}
+
+impl Modrinth {
+    /// Load a file of a version: the one matching `pattern` if it's set, otherwise the primary
+    /// file, or the first one if none is primary.
+    async fn load_version_file(
+        &self,
+        id: &ModId<String>,
+        pattern: Option<&NamePattern>,
+    ) -> ModFileLoadingResult<String, ModrinthHash> {
+        let project_info = self.load_metadata(id.project_id.clone()).await?;
+        let version = FERINTH.get_version(&id.version_id).await?;
+        let file_meta = match pattern {
+            Some(pattern) => {
+                let names = version.files.iter().map(|f| f.filename.clone()).collect();
+                version
+                    .files
+                    .into_iter()
+                    .find(|f| pattern.is_match(&f.filename))
+                    .ok_or_else(|| ModLoadingError::NoMatchingFile(pattern.to_string(), names))?
+            }
+            None => {
+                if version.files.len() > 1 && !version.files.iter().any(|f| f.primary) {
+                    warn(Warning::new(
+                        WarningKind::FileSelection,
+                        format!(
+                            "Modrinth version {} of {} has {} files, but none is marked as \
+                            primary, so the first one ({}) is used. Set `file` on the mod to pick \
+                            one.",
+                            id.version_id,
+                            project_info.name,
+                            version.files.len(),
+                            version.files[0].filename,
+                        ),
+                    ));
+                }
+                version
+                    .files
+                    .into_iter()
+                    .find_or_first(|f| f.primary)
+                    .ok_or(ModLoadingError::NoFiles)?
+            }
+        };
 
         let dependencies = version
             .dependencies
@@ -194,6 +670,7 @@ impl ModSite for Modrinth {
                     kind: match d.dependency_type {
                         DependencyType::Required => ModDependencyKind::Required,
                         DependencyType::Optional => ModDependencyKind::Optional,
+                        DependencyType::Embedded => ModDependencyKind::Embedded,
                         _ => ModDependencyKind::Other,
                     },
                 }
@@ -211,9 +688,18 @@ impl ModSite for Modrinth {
                     .expect("invalid sha1 hash"),
                 sha512: hex_to_hash_output::<sha2::Sha512>(&file_meta.hashes.sha512)
                     .expect("invalid sha512 hash"),
+                sha256: None,
             },
         })
     }
+
+    /// Load the IDs of the projects in a collection.
+    pub async fn get_collection_projects(
+        &self,
+        collection_id: &str,
+    ) -> Result<Vec<String>, ModLoadingError> {
+        Ok(FERINTH.get_collection_projects(collection_id).await?)
+    }
 }
 
 impl From<ProjectSupportRange> for EnvRequirement {
@@ -227,42 +713,73 @@ impl From<ProjectSupportRange> for EnvRequirement {
     }
 }
 
-async fn ferinth_with_retry<T, Fut>(request: impl Fn() -> Fut) -> ferinth::Result<T>
-where
-    Fut: Future<Output = ferinth::Result<T>>,
-{
-    let mut retries = 0;
-    loop {
-        match request().await {
-            Ok(v) => return Ok(v),
-            Err(ferinth::Error::RateLimitExceeded(delay_sec)) => {
-                let adjusted_delay = std::cmp::max(delay_sec as u64 + 1, retries + 1);
-                if retries >= 5 {
-                    return Err(ferinth::Error::RateLimitExceeded(delay_sec));
-                }
-                log::warn!(
-                    "Retrying request in {} (+ {}) sec due to rate limit",
-                    delay_sec,
-                    adjusted_delay - delay_sec as u64
-                );
-                tokio::time::sleep(tokio::time::Duration::from_secs(adjusted_delay)).await;
-                retries += 1;
-            }
-            Err(e) => return Err(e),
-        }
-    }
+/// Loaders Modrinth may list among the categories of a project.
+const MODRINTH_LOADER_CATEGORIES: [&str; 6] = [
+    "fabric",
+    "forge",
+    "neoforge",
+    "quilt",
+    "liteloader",
+    "modloader",
+];
+
+/// The display name of a Modrinth category, e.g. `Game Mechanics` for `game-mechanics`.
+fn modrinth_category_name(slug: &str) -> String {
+    slug.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .join(" ")
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModrinthHash {
+    #[serde(with = "hex_hash")]
     pub sha1: digest::Output<sha1::Sha1>,
+    #[serde(with = "hex_hash")]
     pub sha512: digest::Output<sha2::Sha512>,
+    #[serde(default, with = "hex_hash_opt")]
+    pub sha256: Option<digest::Output<sha2::Sha256>>,
 }
 
 impl ModHash for ModrinthHash {
     fn check_hash_if_possible(&self, content: &[u8]) -> Option<bool> {
         Some(check_hash::<sha2::Sha512>(&self.sha512, content))
     }
+
+    fn check_reader_hash_if_possible(
+        &self,
+        reader: &mut dyn Read,
+    ) -> std::io::Result<Option<bool>> {
+        check_reader_hash::<sha2::Sha512>(&self.sha512, reader).map(Some)
+    }
+
+    fn sha1(&self) -> Option<&digest::Output<sha1::Sha1>> {
+        Some(&self.sha1)
+    }
+
+    fn sha256(&self) -> Option<&digest::Output<sha2::Sha256>> {
+        self.sha256.as_ref()
+    }
+
+    fn set_sha256(&mut self, sha256: digest::Output<sha2::Sha256>) {
+        self.sha256 = Some(sha256);
+    }
+
+    fn site_hash_key(&self) -> Option<String> {
+        Some(format!("sha512-{:x}", self.sha512))
+    }
+
+    fn site_hashes(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("sha1", format!("{:x}", self.sha1)),
+            ("sha512", format!("{:x}", self.sha512)),
+        ]
+    }
 }
 
 #[derive(Debug, Error)]
@@ -271,16 +788,34 @@ pub enum ModLoadingError {
     NotAMod,
     #[error("The project and version exist, but they have no files")]
     NoFiles,
+    #[error("No file of the version matches '{0}', its files are: {}", .1.join(", "))]
+    NoMatchingFile(String, Vec<String>),
     #[error("CurseForge Error: {0}")]
     Furse(#[from] furse::Error),
     #[error("Modrinth Error: {0}")]
-    Ferinth(#[from] ferinth::Error),
+    Ferinth(#[from] ModrinthError),
+    #[error("HTTP Error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("{0}")]
+    NoCurseForgeApi(#[from] NoCurseForgeApi),
+}
+
+/// A version of a project, as published on its site.
+#[derive(Debug, Clone)]
+pub struct PublishedVersion<K> {
+    pub version_id: K,
+    pub name: String,
+    pub date_published: UtcTime,
+    /// The changelog, in Markdown on Modrinth and HTML on CurseForge.
+    pub changelog: Option<String>,
 }
 
 pub type ModLoadingResult = Result<ModInfo, ModLoadingError>;
 pub type ModFileLoadingResult<K, H> = Result<ModFileInfo<K, H>, ModLoadingError>;
+/// A loaded file, with the ID it was loaded with.
+pub type MatchingFileLoadingResult<K, H> = Result<(ModId<K>, ModFileInfo<K, H>), ModLoadingError>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModFileInfo<K, H> {
     pub project_info: ModInfo,
     pub filename: String,
@@ -291,6 +826,45 @@ pub struct ModFileInfo<K, H> {
     pub hash: H,
 }
 
+impl<K, H> ModFileInfo<K, H> {
+    /// Whether the site lists the file for the given Minecraft version.
+    pub fn is_for_minecraft_version(&self, minecraft_version: &str) -> bool {
+        self.minecraft_versions
+            .iter()
+            .any(|v| is_for_minecraft_version(v, minecraft_version))
+    }
+}
+
+/// Separators between the release and the rest of the name of the Minecraft versions leading up
+/// to a release, e.g. `1.21-pre1`, `1.21-rc1` or `26.1-snapshot-1`.
+const SNAPSHOT_SEPARATORS: [&str; 5] = [
+    "-pre",
+    "-rc",
+    "-snapshot-",
+    " Pre-Release ",
+    " Release Candidate ",
+];
+
+/// The release a snapshot, pre-release or release candidate leads up to, if its name says so.
+/// Weekly snapshots like `24w14a` don't.
+fn snapshot_release(minecraft_version: &str) -> Option<&str> {
+    SNAPSHOT_SEPARATORS
+        .iter()
+        .find_map(|sep| minecraft_version.split_once(sep))
+        .map(|(release, _)| release)
+}
+
+/// Whether `site_version`, a game version a site lists a file for, is `minecraft_version`.
+/// CurseForge doesn't list snapshots on their own, but lists their files for `<release>-Snapshot`.
+pub fn is_for_minecraft_version(site_version: &str, minecraft_version: &str) -> bool {
+    site_version == minecraft_version
+        || snapshot_release(minecraft_version).is_some_and(|release| {
+            site_version
+                .strip_suffix("-Snapshot")
+                .is_some_and(|v| v == release)
+        })
+}
+
 /// Tries to convert a hex representation of a hash into a hash output.
 /// Returns `None` if the hex string is invalid.
 pub fn hex_to_hash_output<D: Digest>(s: &str) -> Option<digest::Output<D>> {
@@ -303,26 +877,108 @@ pub fn hex_to_hash_output<D: Digest>(s: &str) -> Option<digest::Output<D>> {
     Some(array)
 }
 
+/// (De)serialize a hash output as a hex string.
+mod hex_hash {
+    use digest::generic_array::{ArrayLength, GenericArray};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<N, S>(value: &GenericArray<u8, N>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        N: ArrayLength<u8>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&hex::encode(value))
+    }
+
+    pub fn deserialize<'de, N, D>(deserializer: D) -> Result<GenericArray<u8, N>, D::Error>
+    where
+        N: ArrayLength<u8>,
+        D: Deserializer<'de>,
+    {
+        let mut array = GenericArray::default();
+        hex::decode_to_slice(String::deserialize(deserializer)?, &mut array)
+            .map_err(D::Error::custom)?;
+        Ok(array)
+    }
+}
+
+/// (De)serialize an optional hash output as a hex string.
+mod hex_hash_opt {
+    use digest::generic_array::{ArrayLength, GenericArray};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Hex<N: ArrayLength<u8>>(#[serde(with = "super::hex_hash")] GenericArray<u8, N>);
+
+    pub fn serialize<N, S>(
+        value: &Option<GenericArray<u8, N>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        N: ArrayLength<u8>,
+        S: Serializer,
+    {
+        value.clone().map(Hex).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, N, D>(deserializer: D) -> Result<Option<GenericArray<u8, N>>, D::Error>
+    where
+        N: ArrayLength<u8>,
+        D: Deserializer<'de>,
+    {
+        Ok(Option::<Hex<N>>::deserialize(deserializer)?.map(|h| h.0))
+    }
+}
+
 pub fn check_hash<D: Digest + Default>(value: &digest::Output<D>, content: &[u8]) -> bool {
     let mut hasher = D::default();
     hasher.update(content);
     &hasher.finalize() == value
 }
 
-#[derive(Debug, Clone)]
+pub fn check_reader_hash<D: Digest + Default + Write>(
+    value: &digest::Output<D>,
+    reader: &mut dyn Read,
+) -> std::io::Result<bool> {
+    let mut hasher = D::default();
+    std::io::copy(reader, &mut hasher)?;
+    Ok(&hasher.finalize() == value)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModInfo {
     pub name: String,
+    pub slug: String,
     pub distribution_allowed: bool,
+    /// SPDX ID of the mod's license, if the site reports it.
+    pub license: Option<String>,
+    /// Names of the mod's authors on the site. Empty for Modrinth mods until they're looked up by
+    /// their [team](Self::team), as only some outputs need them.
+    #[serde(default)]
+    pub authors: Vec<String>,
+    /// The Modrinth team of the mod, which lists its authors.
+    #[serde(default)]
+    pub team: Option<String>,
+    /// The mod's page on the site.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// A short description of the mod.
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// Names of the categories the site lists the mod in, e.g. `Technology`.
+    #[serde(default)]
+    pub categories: Vec<String>,
     pub side_info: SideInfo,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SideInfo {
     pub client: EnvRequirement,
     pub server: EnvRequirement,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModDependency<K> {
     pub id: DependencyId<K>,
     pub kind: ModDependencyKind,
@@ -335,13 +991,23 @@ pub enum DependencyId<K> {
     Version(K),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 enum ExplicitDependencyId<K> {
     Project { project_id: K },
     Version { version_id: K },
 }
 
+impl<K: Serialize> Serialize for DependencyId<K> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            DependencyId::Project(project_id) => ExplicitDependencyId::Project { project_id },
+            DependencyId::Version(version_id) => ExplicitDependencyId::Version { version_id },
+        }
+        .serialize(serializer)
+    }
+}
+
 impl<K> From<ExplicitDependencyId<K>> for DependencyId<K> {
     fn from(id: ExplicitDependencyId<K>) -> Self {
         match id {
@@ -351,9 +1017,12 @@ impl<K> From<ExplicitDependencyId<K>> for DependencyId<K> {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum ModDependencyKind {
     Required,
     Optional,
+    /// The dependency is bundled in the mod's file, e.g. a library in a jar-in-jar.
+    Embedded,
+    /// E.g. incompatible mods and tools.
     Other,
 }
@@ -0,0 +1,55 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// A file that is written under a temporary name next to its destination, and only renamed to it
+/// once complete. If it's dropped before being committed, the temporary file is removed, so a
+/// failed write never leaves a truncated file at the destination.
+pub struct AtomicFile {
+    temp_path: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> Result<(Self, File), std::io::Error> {
+        let file_name = path
+            .file_name()
+            .expect("atomic file path must have a file name")
+            .to_string_lossy();
+        let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+        let file = File::create(&temp_path)?;
+        Ok((
+            Self {
+                temp_path,
+                path: path.to_path_buf(),
+                committed: false,
+            },
+            file,
+        ))
+    }
+
+    /// Move the finished file to its destination. The file must be closed or flushed first.
+    pub fn commit(mut self) -> Result<(), std::io::Error> {
+        std::fs::rename(&self.temp_path, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            if let Err(e) = std::fs::remove_file(&self.temp_path) {
+                log::debug!("Failed to remove {}: {}", self.temp_path.display(), e);
+            }
+        }
+    }
+}
+
+/// Write `content` to `path` through an [AtomicFile].
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+    let (atomic, mut file) = AtomicFile::create(path)?;
+    std::io::Write::write_all(&mut file, content)?;
+    drop(file);
+    atomic.commit()
+}
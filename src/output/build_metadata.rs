@@ -0,0 +1,231 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use digest::Digest;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+use crate::checks::verify_mods::VerifiedModContainer;
+use crate::config::pack::{OverrideDirs, PackConfig};
+use crate::config::source::PackSource;
+use crate::mod_site::{CurseForge, ModId, ModIdValue, ModSite, Modrinth};
+use crate::output::{LIT_CLIENT_OVERRIDES, LIT_OVERRIDES, LIT_SERVER_OVERRIDES};
+
+/// Name of the file describing how an artifact was built, placed at its root.
+pub const BUILD_METADATA_FILE: &str = "netherfire-build.json";
+
+/// Information about a build, shared by all artifacts generated from a pack (or target).
+#[derive(Debug, Clone)]
+pub struct BuildInfo {
+    /// The name of the pack.
+    pub pack: String,
+    /// The Java version the pack needs at least, if known.
+    pub java_version: Option<u32>,
+    /// The build matrix target being built, if any.
+    pub target: Option<String>,
+    pub built_at: u64,
+    pub git_commit: Option<String>,
+    pub git_short_commit: Option<String>,
+    pub git_branch: Option<String>,
+    /// SHA-256 hash of the `config.toml` files the pack was loaded from, and what its scripts
+    /// returned.
+    pub config_hash: String,
+    /// SHA-256 hash of the paths and contents of the pack's override files.
+    pub overrides_hash: String,
+}
+
+/// SHA-256 hash of the `config.toml` files a pack was loaded from, and what its scripts returned.
+pub fn config_hash(source: &PackSource) -> Result<String, std::io::Error> {
+    let mut hasher = sha2::Sha256::new();
+    for config in source.join_all("config.toml") {
+        hasher.update(std::fs::read(config)?);
+    }
+    // Scripts may return something else every run, e.g. depending on the environment.
+    for overlay in &source.script_overlays {
+        hasher.update(overlay.as_bytes());
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+impl BuildInfo {
+    pub fn new<MC>(
+        pack: &PackConfig<MC>,
+        source: &PackSource,
+        target: Option<String>,
+    ) -> Result<Self, std::io::Error> {
+        let dir = source
+            .dirs
+            .last()
+            .expect("a pack has at least one directory");
+        Ok(Self {
+            pack: pack.name.clone(),
+            java_version: pack.min_java_version(),
+            target,
+            built_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("system time is before the epoch")
+                .as_secs(),
+            git_commit: crate::git::head_commit(dir),
+            git_short_commit: crate::git::short_head_commit(dir),
+            git_branch: crate::git::head_branch(dir),
+            config_hash: config_hash(source)?,
+            overrides_hash: hash_overrides(source, &pack.override_dirs)?,
+        })
+    }
+
+    /// Check whether `existing` describes an artifact built from the same inputs with the same
+    /// options, i.e. the artifact doesn't need to be generated again.
+    pub fn is_same_build(
+        &self,
+        existing: &BuildMetadata,
+        artifact: &str,
+        include_optional: bool,
+    ) -> bool {
+        let current = self.metadata("", include_optional);
+        existing.netherfire_version == current.netherfire_version
+            && existing.artifact == artifact
+            && existing.git_commit == current.git_commit
+            && existing.config_hash == current.config_hash
+            && existing.overrides_hash == current.overrides_hash
+            && existing.target == current.target
+            && existing.include_optional == include_optional
+    }
+
+    /// Replace the `{git_hash}` and `{git_branch}` placeholders in `template`.
+    pub fn expand_git_placeholders(&self, template: &str) -> String {
+        let mut result = template.to_string();
+        for (placeholder, value) in [
+            ("{git_hash}", &self.git_short_commit),
+            ("{git_branch}", &self.git_branch),
+        ] {
+            if !result.contains(placeholder) {
+                continue;
+            }
+            let value = value.as_deref().unwrap_or_else(|| {
+                log::warn!(
+                    "The pack is not in a git repository, using 'unknown' for {}",
+                    placeholder
+                );
+                "unknown"
+            });
+            result = result.replace(placeholder, value);
+        }
+        result
+    }
+
+    pub fn metadata(&self, artifact: &str, include_optional: bool) -> BuildMetadata {
+        BuildMetadata {
+            netherfire_version: env!("CARGO_PKG_VERSION").to_string(),
+            artifact: artifact.to_string(),
+            pack: Some(self.pack.clone()),
+            java_version: self.java_version,
+            built_at: self.built_at,
+            git_commit: self.git_commit.clone(),
+            config_hash: self.config_hash.clone(),
+            overrides_hash: self.overrides_hash.clone(),
+            target: self.target.clone(),
+            include_optional,
+            mod_files: None,
+            mods: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildMetadata {
+    pub netherfire_version: String,
+    /// The kind of artifact, `curseforge`, `modrinth`, `modrinth-manifest-only` or `server-base`.
+    pub artifact: String,
+    /// The name of the pack. Missing in artifacts from before this was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack: Option<String>,
+    /// The Java version the pack needs at least, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java_version: Option<u32>,
+    /// Seconds since the Unix epoch.
+    pub built_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    pub config_hash: String,
+    #[serde(default)]
+    pub overrides_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub include_optional: bool,
+    /// Paths of the mod files that were downloaded into the artifact, to tell them apart from
+    /// overrides. Missing in artifacts from before this was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mod_files: Option<Vec<String>>,
+    /// The configured mods the artifact was built from. Missing in artifacts from before this was
+    /// recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mods: Option<Vec<BuiltMod>>,
+}
+
+/// A configured mod, identified by its site and config key.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuiltMod {
+    /// The config key of the site, e.g. `modrinth`.
+    pub site: String,
+    pub key: String,
+    pub project_id: String,
+    pub version_id: String,
+}
+
+impl BuiltMod {
+    pub fn new<K: ModIdValue>(site: &str, key: &str, id: &ModId<K>) -> Self {
+        Self {
+            site: site.to_string(),
+            key: key.to_string(),
+            project_id: id.project_id.to_string(),
+            version_id: id.version_id.to_string(),
+        }
+    }
+}
+
+/// The mods of a verified pack, sorted by site and config key.
+pub fn built_mods(mods: &VerifiedModContainer) -> Vec<BuiltMod> {
+    let curseforge = mods
+        .curseforge
+        .iter()
+        .map(|(k, m)| BuiltMod::new(CurseForge::CONFIG_KEY, k, &m.source));
+    let modrinth = mods
+        .modrinth
+        .iter()
+        .map(|(k, m)| BuiltMod::new(Modrinth::CONFIG_KEY, k, &m.source));
+    curseforge.chain(modrinth).sorted().collect()
+}
+
+/// Read the build metadata of an existing archive, if it has any.
+pub fn read_archive_metadata(path: &Path) -> Option<BuildMetadata> {
+    let mut zip = ZipArchive::new(std::fs::File::open(path).ok()?).ok()?;
+    let metadata = zip.by_name(BUILD_METADATA_FILE).ok()?;
+    serde_json::from_reader(metadata).ok()
+}
+
+fn hash_overrides(
+    source: &PackSource,
+    override_dirs: &OverrideDirs,
+) -> Result<String, std::io::Error> {
+    let mut hasher = sha2::Sha256::new();
+    for overrides in [LIT_OVERRIDES, LIT_CLIENT_OVERRIDES, LIT_SERVER_OVERRIDES] {
+        for dir in source.join_all(override_dirs.of_kind(overrides)) {
+            if !dir.exists() {
+                continue;
+            }
+            for entry in WalkDir::new(&dir).sort_by_file_name() {
+                let entry = entry?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                hasher.update(entry.path().to_string_lossy().as_bytes());
+                hasher.update(std::fs::read(entry.path())?);
+            }
+        }
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
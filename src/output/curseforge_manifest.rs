@@ -1,6 +1,6 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CurseForgeManifest {
     pub minecraft: Minecraft,
@@ -11,28 +11,31 @@ pub struct CurseForgeManifest {
     pub author: String,
     pub files: Vec<ManifestFile>,
     pub overrides: String,
+    /// The pack's own project on CurseForge.
+    #[serde(rename = "projectID", default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<i32>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Minecraft {
     pub version: String,
     pub mod_loaders: Vec<ModLoader>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ModLoader {
     pub id: String,
     pub primary: bool,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum ManifestType {
     MinecraftModpack,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ManifestFile {
     #[serde(rename = "projectID")]
     pub project_id: i32,
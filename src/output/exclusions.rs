@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::config::globs::Globs;
+
+/// The file in a pack source directory that lists overrides to leave out of the outputs, in
+/// gitignore syntax, relative to the source directory.
+pub(crate) const IGNORE_FILE: &str = ".netherfireignore";
+
+/// Files operating systems create in directories on their own, which are always left out.
+const OS_METADATA_FILES: [&str; 3] = [".DS_Store", "Thumbs.db", "desktop.ini"];
+
+/// The files of an override directory that are left out of the outputs.
+pub(crate) struct Exclusions<'a> {
+    exclude: &'a Globs,
+    ignore: Gitignore,
+}
+
+impl<'a> Exclusions<'a> {
+    /// Load the exclusions for `override_dir`, i.e. the `exclude` globs of the pack and the ignore
+    /// file of the source directory it's in.
+    pub(crate) fn load(override_dir: &Path, exclude: &'a Globs) -> Result<Self, ignore::Error> {
+        let source_dir = override_dir.parent().unwrap_or(override_dir);
+        let ignore_file = source_dir.join(IGNORE_FILE);
+        let ignore = if ignore_file.exists() {
+            let mut builder = GitignoreBuilder::new(source_dir);
+            if let Some(e) = builder.add(&ignore_file) {
+                return Err(e);
+            }
+            builder.build()?
+        } else {
+            Gitignore::empty()
+        };
+        Ok(Self { exclude, ignore })
+    }
+
+    /// Whether `path`, at `relative` in the override directory, is left out. Excluded directories
+    /// are left out with everything in them.
+    pub(crate) fn is_excluded(&self, path: &Path, relative: &Path, is_dir: bool) -> bool {
+        let os_metadata = !is_dir
+            && path
+                .file_name()
+                .is_some_and(|name| OS_METADATA_FILES.iter().any(|f| name == *f));
+        os_metadata
+            || self.exclude.is_match(
+                &relative
+                    .to_string_lossy()
+                    .replace(std::path::MAIN_SEPARATOR, "/"),
+            )
+            || self.ignore.matched(path, is_dir).is_ignore()
+    }
+}
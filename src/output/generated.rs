@@ -0,0 +1,214 @@
+//! The files generated into each output directory, recorded so `netherfire clean` can remove them
+//! again without touching anything else, like the worlds next to a server base.
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use walkdir::WalkDir;
+
+use crate::lock::{lock_dir, LockError, LOCK_FILE};
+use crate::output::atomic_file::write_atomic;
+use crate::output::mod_download::file_sha256;
+
+/// Name of the file listing what was generated into an output directory, placed in it.
+pub const GENERATED_FILE: &str = ".netherfire-generated.json";
+
+/// The generated files of an output directory, by their path relative to it, with `/` separators.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GeneratedFiles {
+    files: BTreeMap<String, GeneratedFile>,
+}
+
+/// A generated file as it was written, to tell whether it was changed since.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct GeneratedFile {
+    size: u64,
+    sha256: String,
+}
+
+impl GeneratedFile {
+    fn read(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            size: std::fs::metadata(path)?.len(),
+            sha256: file_sha256(path)?,
+        })
+    }
+}
+
+fn read_generated(dir: &Path) -> std::io::Result<Option<GeneratedFiles>> {
+    match std::fs::read(dir.join(GENERATED_FILE)) {
+        Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_generated(dir: &Path, generated: &GeneratedFiles) -> std::io::Result<()> {
+    if generated.files.is_empty() {
+        return match std::fs::remove_file(dir.join(GENERATED_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    write_atomic(
+        &dir.join(GENERATED_FILE),
+        &serde_json::to_vec_pretty(generated)?,
+    )
+}
+
+/// The files recorded as generated into `dir`, relative to it, with their sizes.
+pub(crate) fn generated_files(dir: &Path) -> std::io::Result<Vec<(String, u64)>> {
+    Ok(read_generated(dir)?
+        .unwrap_or_default()
+        .files
+        .into_iter()
+        .map(|(file, generated)| (file, generated.size))
+        .collect())
+}
+
+/// Record that `files`, relative to `dir`, were generated into it. Recorded files that no longer
+/// exist are forgotten.
+pub(crate) fn record_generated<'a>(dir: &Path, files: impl IntoIterator<Item = &'a str>) {
+    let result = tokio::task::block_in_place(|| {
+        let mut generated = read_generated(dir)?.unwrap_or_default();
+        generated.files.retain(|file, _| dir.join(file).exists());
+        for file in files {
+            generated
+                .files
+                .insert(file.to_string(), GeneratedFile::read(&dir.join(file))?);
+        }
+        write_generated(dir, &generated)
+    });
+    log_record_error(dir, result);
+}
+
+/// Record that the file at `path` was generated into its directory.
+pub(crate) fn record_generated_file(path: &Path) {
+    let (Some(dir), Some(file)) = (path.parent(), path.file_name().and_then(|f| f.to_str())) else {
+        return;
+    };
+    record_generated(dir, [file]);
+}
+
+/// Record that everything in `dir` was generated, e.g. a server base right after it was built.
+/// What was recorded before is forgotten.
+pub(crate) fn record_generated_dir(dir: &Path) {
+    let result = tokio::task::block_in_place(|| {
+        let mut generated = GeneratedFiles::default();
+        let entries = WalkDir::new(dir)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|e| {
+                e.depth() > 1 || (e.file_name() != LOCK_FILE && e.file_name() != GENERATED_FILE)
+            });
+        for entry in entries {
+            let entry = entry?;
+            let Some(relative) = entry.path().strip_prefix(dir).ok().and_then(Path::to_str) else {
+                continue;
+            };
+            if entry.path().is_file() {
+                generated.files.insert(
+                    relative.replace(std::path::MAIN_SEPARATOR, "/"),
+                    GeneratedFile::read(entry.path())?,
+                );
+            }
+        }
+        write_generated(dir, &generated)
+    });
+    log_record_error(dir, result);
+}
+
+fn log_record_error(dir: &Path, result: std::io::Result<()>) {
+    if let Err(e) = result {
+        log::warn!(
+            "Failed to record what was generated into '{}', `netherfire clean` won't remove it: {}",
+            dir.display(),
+            e
+        );
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CleanError {
+    #[error("Nothing was generated into '{0}', or it was generated by an older netherfire")]
+    NothingGenerated(String),
+    #[error("I/O error cleaning '{0}': {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("{0}")]
+    Lock(#[from] LockError),
+}
+
+/// What [clean_generated] removed and kept.
+#[derive(Debug, Default)]
+pub struct Cleaned {
+    pub removed_files: u64,
+    pub removed_bytes: u64,
+    /// Generated files that were changed since, relative to the output directory.
+    pub kept: Vec<String>,
+}
+
+/// Remove the files that were generated into `dir`, and the directories they leave empty. Files
+/// that were changed since are kept, unless `force` is set.
+pub fn clean_generated(dir: &Path, force: bool) -> Result<Cleaned, CleanError> {
+    let io_error = |e| CleanError::Io(dir.display().to_string(), e);
+    if !dir.is_dir() {
+        return Err(CleanError::NothingGenerated(dir.display().to_string()));
+    }
+    let _lock = lock_dir(dir)?;
+    let generated = read_generated(dir)
+        .map_err(io_error)?
+        .ok_or_else(|| CleanError::NothingGenerated(dir.display().to_string()))?;
+    let files = generated.files.into_keys().collect();
+    remove_generated_impl(dir, files, force).map_err(io_error)
+}
+
+/// Remove the given `files`, relative to `dir`, if they were generated into it and weren't
+/// changed since. Files that weren't generated are left alone.
+pub(crate) fn remove_generated(dir: &Path, files: Vec<String>) -> std::io::Result<Cleaned> {
+    remove_generated_impl(dir, files, false)
+}
+
+fn remove_generated_impl(dir: &Path, files: Vec<String>, force: bool) -> std::io::Result<Cleaned> {
+    let mut generated = read_generated(dir)?.unwrap_or_default();
+    let mut cleaned = Cleaned::default();
+    for file in files {
+        let Some(recorded) = generated.files.get(&file) else {
+            continue;
+        };
+        // Never follow a tampered record out of the directory.
+        if !Path::new(&file)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            continue;
+        }
+        let path = dir.join(&file);
+        if path.exists() || path.is_symlink() {
+            if !force && GeneratedFile::read(&path).ok().as_ref() != Some(recorded) {
+                cleaned.kept.push(file);
+                continue;
+            }
+            std::fs::remove_file(&path)?;
+            cleaned.removed_files += 1;
+            cleaned.removed_bytes += recorded.size;
+            remove_empty_parents(dir, &path);
+        }
+        generated.files.remove(&file);
+    }
+    write_generated(dir, &generated)?;
+    Ok(cleaned)
+}
+
+/// Remove the empty directories above `path`, up to `dir`.
+fn remove_empty_parents(dir: &Path, path: &Path) {
+    let mut parent = path.parent().map(PathBuf::from);
+    while let Some(current) = parent {
+        // Fails once a directory isn't empty.
+        if current == dir || std::fs::remove_dir(&current).is_err() {
+            break;
+        }
+        parent = current.parent().map(PathBuf::from);
+    }
+}
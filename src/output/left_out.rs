@@ -0,0 +1,111 @@
+//! What configured content each artifact of a run left out and why, logged after generating, so
+//! authors aren't surprised by mods or overrides missing from an artifact.
+
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+use itertools::Itertools;
+
+use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, FILE_STYLE, SITE_NAME_STYLE};
+
+/// Why something was left out of an artifact.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum LeftOutReason {
+    /// The mod or override isn't used on the side the artifact is for.
+    Side(&'static str),
+    /// The mod is optional on the side the artifact is for, and optional mods weren't included.
+    Optional(&'static str),
+    /// The format of the artifact can't hold it.
+    Format(&'static str),
+}
+
+impl Display for LeftOutReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeftOutReason::Side(side) => write!(f, "not used on the {}", side),
+            LeftOutReason::Optional(side) => {
+                write!(
+                    f,
+                    "optional on the {}, and optional mods are left out",
+                    side
+                )
+            }
+            LeftOutReason::Format(why) => write!(f, "{}", why),
+        }
+    }
+}
+
+/// Something configured that an artifact left out.
+#[derive(Debug, Clone)]
+struct LeftOut {
+    artifact: &'static str,
+    reason: LeftOutReason,
+    /// The config key of the site of a mod, or `None` for an override.
+    site: Option<&'static str>,
+    item: String,
+}
+
+static LEFT_OUT: Mutex<Vec<LeftOut>> = Mutex::new(Vec::new());
+
+/// Record that the artifact left out the mod with config key `cfg_id` from `site`.
+pub(crate) fn left_out_mod(
+    artifact: &'static str,
+    site: &'static str,
+    cfg_id: &str,
+    reason: LeftOutReason,
+) {
+    record(LeftOut {
+        artifact,
+        reason,
+        site: Some(site),
+        item: cfg_id.to_string(),
+    });
+}
+
+/// Record that the artifact left out an override file or directory.
+pub(crate) fn left_out_override(artifact: &'static str, path: String, reason: LeftOutReason) {
+    record(LeftOut {
+        artifact,
+        reason,
+        site: None,
+        item: path,
+    });
+}
+
+fn record(left_out: LeftOut) {
+    log::debug!(
+        "Left {} out of the {}: {}",
+        left_out.item,
+        left_out.artifact,
+        left_out.reason
+    );
+    LEFT_OUT.lock().expect("left out lock").push(left_out);
+}
+
+/// Log what each artifact generated so far left out, grouped by reason, and forget it, so the next
+/// pack or target starts over.
+pub(crate) fn log_left_out() {
+    let left_out = std::mem::take(&mut *LEFT_OUT.lock().expect("left out lock"));
+    for (artifact, group) in &left_out
+        .into_iter()
+        .sorted_by(|a, b| {
+            (a.artifact, a.reason, a.site, &a.item).cmp(&(b.artifact, b.reason, b.site, &b.item))
+        })
+        .group_by(|l| l.artifact)
+    {
+        log::info!("Left out of the {}:", artifact);
+        for (reason, items) in &group.group_by(|l| l.reason) {
+            let items = items
+                .map(|l| match l.site {
+                    Some(site) => format!(
+                        "[{}] {}",
+                        site.errstyle(SITE_NAME_STYLE),
+                        l.item.errstyle(CONFIG_VAL_STYLE)
+                    ),
+                    None => l.item.errstyle(FILE_STYLE).to_string(),
+                })
+                .join(", ");
+            log::info!("- {}: {}", reason, items);
+        }
+    }
+}
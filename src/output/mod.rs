@@ -1,48 +1,87 @@
-use std::io::{Seek, Write};
-use std::ops::DerefMut;
-use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Cursor, Seek, Write};
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
+use itertools::Itertools;
 use once_cell::sync::Lazy;
 use reflink::reflink_or_copy;
 use thiserror::Error;
+use tokio::io::AsyncReadExt;
 use tokio::spawn;
 use tokio::sync::Mutex;
-use tokio_util::io::SyncIoBridge;
 use walkdir::WalkDir;
-use zip::{CompressionMethod, ZipWriter};
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
 
+use crate::checks::install_plan::{ArtifactPlan, Side};
 use crate::checks::verify_mods::{VerifiedMod, VerifiedModContainer};
-use crate::config::pack::ModLoaderType;
-use crate::mod_site::ModSite;
+use crate::config::globs::Globs;
+use crate::config::mods::KnownEnvRequirement;
+use crate::config::pack::{
+    ModLoaderType, NonUtf8Paths, OverrideDirs, OverrideSide, OverrideSideRule, Symlinks,
+};
+use crate::config::source::PackSource;
+use crate::lock::LOCK_FILE;
+use crate::mod_site::{CurseForge, ModSite, Modrinth};
+use crate::output::atomic_file::{write_atomic, AtomicFile};
+use crate::output::build_metadata::{
+    built_mods, read_archive_metadata, BuildInfo, BuildMetadata, BUILD_METADATA_FILE,
+};
 use crate::output::curseforge_manifest::{
     CurseForgeManifest, ManifestFile, ManifestType, Minecraft, ModLoader,
 };
+use crate::output::exclusions::{Exclusions, IGNORE_FILE};
+use crate::output::generated::{
+    record_generated, record_generated_dir, record_generated_file, GENERATED_FILE,
+};
+use crate::output::left_out::{left_out_mod, left_out_override, LeftOutReason};
 use crate::output::mod_download::{
-    download_mods, mod_download, ModDownloadError, ModsDownloadError,
+    cached_mod_download, check_size, download_mods, is_refreshing, LinkMode, ModDownloadError,
+    ModsDownloadError,
 };
 use crate::output::modrinth_manifest::ModrinthManifest;
+use crate::output::parallel::for_each_parallel;
+use crate::output::patches::{apply_patch, PatchError, PatchFormat};
+use crate::output::previous::{previous_server_base, PreviousArchive, PreviousArtifactError};
+use crate::output::retention::prune_artifacts;
+use crate::output::summary::{record_archive, record_server_base, ModCounts};
+use crate::progress::{emit, ProgressEvent};
 use crate::uwu_colors::{ErrStyle, FILE_STYLE, SITE_NAME_STYLE};
+use crate::warnings::{warn, Warning, WarningKind};
 use crate::PackConfig;
 
-mod curseforge_manifest;
-mod mod_download;
-mod modrinth_manifest;
+pub(crate) mod atomic_file;
+pub(crate) mod build_metadata;
+pub(crate) mod curseforge_manifest;
+pub(crate) mod exclusions;
+pub(crate) mod generated;
+pub(crate) mod left_out;
+pub(crate) mod mod_download;
+pub(crate) mod modrinth_manifest;
+mod parallel;
+mod patches;
+mod previous;
+mod retention;
+pub(crate) mod summary;
+pub(crate) mod wiki;
 
-const LIT_MODS: &str = "mods";
-const LIT_OVERRIDES: &str = "overrides";
-const LIT_SERVER_OVERRIDES: &str = "server-overrides";
-const LIT_CLIENT_OVERRIDES: &str = "client-overrides";
+pub(crate) const LIT_MODS: &str = "mods";
+pub(crate) const LIT_OVERRIDES: &str = "overrides";
+pub(crate) const LIT_SERVER_OVERRIDES: &str = "server-overrides";
+pub(crate) const LIT_CLIENT_OVERRIDES: &str = "client-overrides";
 
 #[derive(Debug, Error)]
 pub enum CreateCurseForgeZipError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("{0}")]
+    PreviousArtifact(#[from] PreviousArtifactError),
     #[error("Json error: {0}")]
     Json(#[from] serde_json::error::Error),
     #[error("ZIP error: {0}")]
     Zip(#[from] zip::result::ZipError),
-    #[error("Zipping directory {0} failed: {1}")]
+    #[error("Zipping overrides from {0} failed: {1}")]
     ZipDir(String, #[source] ZipDirError),
     #[error("Zipping mod {0} failed: {1}")]
     ZipMod(String, #[source] ZipModError),
@@ -52,13 +91,143 @@ static ZIP_OPTIONS: Lazy<zip::write::FileOptions> = Lazy::new(|| {
     zip::write::FileOptions::default().compression_method(CompressionMethod::Deflated)
 });
 
+/// Where overrides go in an archive, by the side they're for. `None` leaves them out.
+struct OverridesLayout<'a> {
+    /// The artifact, for reporting what it leaves out.
+    artifact: &'static str,
+    /// Why overrides the layout has no place for are left out.
+    left_out: LeftOutReason,
+    both: Option<&'a str>,
+    client: Option<&'a str>,
+    server: Option<&'a str>,
+}
+
+impl<'a> OverridesLayout<'a> {
+    fn dest(&self, side: OverrideSide) -> Option<&'a str> {
+        match side {
+            OverrideSide::Both => self.both,
+            OverrideSide::Client => self.client,
+            OverrideSide::Server => self.server,
+        }
+    }
+}
+
+/// The names of the artifacts in reports of what they left out.
+const CURSEFORGE_ZIP: &str = "CurseForge ZIP";
+const MODRINTH_PACK: &str = "Modrinth pack";
+const SERVER_BASE: &str = "server base";
+
+/// Where overrides go in a CurseForge ZIP, which is only for clients: the overrides directory
+/// named in its manifest.
+fn curseforge_overrides(overrides: &str) -> OverridesLayout<'_> {
+    OverridesLayout {
+        artifact: CURSEFORGE_ZIP,
+        left_out: LeftOutReason::Format("CurseForge packs are only for clients"),
+        both: Some(overrides),
+        client: Some(overrides),
+        server: None,
+    }
+}
+
+/// Where overrides go in a Modrinth pack.
+const MODRINTH_OVERRIDES: OverridesLayout<'static> = OverridesLayout {
+    artifact: MODRINTH_PACK,
+    left_out: LeftOutReason::Format("the Modrinth pack format has no place for them"),
+    both: Some(LIT_OVERRIDES),
+    client: Some(LIT_CLIENT_OVERRIDES),
+    server: Some(LIT_SERVER_OVERRIDES),
+};
+
+/// Whether a path in an archive, or recorded in one, stays inside the directory it's extracted to.
+pub(crate) fn is_safe_path(path: &str) -> bool {
+    !path.is_empty()
+        && !path.starts_with('/')
+        && !path.contains('\\')
+        && !path.contains(':')
+        && path.split('/').all(|part| part != "..")
+}
+
+/// The file name of the pack's artifacts, without extension. Without a template, build matrix
+/// targets get their name added to the version, so their artifacts don't overwrite each other.
+fn artifact_name<MC>(pack: &PackConfig<MC>, build: &BuildInfo) -> String {
+    let Some(template) = &pack.artifact_name else {
+        return match &build.target {
+            Some(target) => format!("{} ({}-{})", pack.name, pack.version, target),
+            None => format!("{} ({})", pack.name, pack.version),
+        };
+    };
+    build.expand_git_placeholders(
+        &template
+            .replace("{name}", &pack.name)
+            .replace("{version}", &pack.version)
+            .replace("{target}", build.target.as_deref().unwrap_or_default()),
+    )
+}
+
+/// The metadata of a generated artifact. If its overrides were taken from a previous build, so is
+/// their hash.
+fn artifact_metadata(
+    build: &BuildInfo,
+    artifact: &str,
+    include_optional: bool,
+    mods: &VerifiedModContainer,
+    mod_files: Vec<String>,
+    previous: Option<&BuildMetadata>,
+) -> BuildMetadata {
+    let metadata = build.metadata(artifact, include_optional);
+    BuildMetadata {
+        overrides_hash: previous.map_or(metadata.overrides_hash.clone(), |p| {
+            p.overrides_hash.clone()
+        }),
+        mod_files: Some(mod_files),
+        mods: Some(built_mods(mods)),
+        ..metadata
+    }
+}
+
+/// Check whether an archive from a previous run was built from the same inputs, so it doesn't
+/// need to be generated again.
+fn is_up_to_date(
+    output_file: &Path,
+    build: &BuildInfo,
+    artifact: &str,
+    include_optional: bool,
+) -> bool {
+    // Refreshed mods may have been corrupt in the cache, and so in the archive.
+    let up_to_date = !is_refreshing()
+        && read_archive_metadata(output_file)
+            .is_some_and(|existing| build.is_same_build(&existing, artifact, include_optional));
+    if up_to_date {
+        log::info!(
+            "'{}' is already up to date.",
+            output_file.display().errstyle(FILE_STYLE)
+        );
+    }
+    up_to_date
+}
+
 pub async fn create_curseforge_zip(
     pack: &PackConfig<VerifiedModContainer>,
-    source_dir: &Path,
+    build: &BuildInfo,
+    source: &PackSource,
     output_dir: PathBuf,
-    include_optional: bool,
+    plan: ArtifactPlan<'_>,
+    reuse_overrides: bool,
 ) -> Result<(), CreateCurseForgeZipError> {
-    let output_file = output_dir.join(format!("{} ({}).zip", pack.name, pack.version));
+    let started = Instant::now();
+    let mod_counts = ModCounts {
+        client: Some(plan.files(Side::Client).count()),
+        server: None,
+    };
+    let output_file = output_dir.join(format!("{}.zip", artifact_name(pack, build)));
+    let mut previous = if reuse_overrides {
+        Some(PreviousArchive::open(&output_file)?)
+    } else if is_up_to_date(&output_file, build, "curseforge", plan.include_optional) {
+        record_archive(CURSEFORGE_ZIP, &output_file, mod_counts, started);
+        return Ok(());
+    } else {
+        None
+    };
 
     log::info!(
         "Creating CurseForge zip at '{}'...",
@@ -67,24 +236,30 @@ pub async fn create_curseforge_zip(
 
     std::fs::create_dir_all(&output_dir)?;
 
-    let zip = ZipWriter::new(std::fs::File::create(&output_file)?);
+    // Write to a temporary file, so a failure doesn't leave a truncated archive behind.
+    let (output_atomic, output) = AtomicFile::create(&output_file)?;
+    let zip = ZipWriter::new(output);
 
     log::info!(
         "Downloading {} mods...",
         "Modrinth".errstyle(SITE_NAME_STYLE)
     );
 
+    plan.record_left_out(CURSEFORGE_ZIP, Side::Client);
     let zip_arc = Arc::new(Mutex::new(zip));
     let mut zip_dl_tasks = Vec::with_capacity(pack.mods.modrinth.len());
-    for (cfg_id, mod_) in &pack.mods.modrinth {
-        if !mod_.env_requirements.client.is_needed(include_optional) {
-            continue;
-        }
+    let mut mod_files = Vec::with_capacity(pack.mods.modrinth.len());
+    let overrides = &pack.curseforge_manifest.overrides;
+    for (file, mod_) in plan.mods(Side::Client, &pack.mods.modrinth) {
+        mod_files.push([overrides.as_str(), &file.destination].join("/"));
         zip_dl_tasks.push((
-            cfg_id,
+            &file.key,
             spawn(add_mod_to_zip(
+                Modrinth,
+                file.key.clone(),
                 mod_.clone(),
-                LIT_OVERRIDES,
+                CURSEFORGE_ZIP,
+                overrides.clone(),
                 Arc::clone(&zip_arc),
             )),
         ));
@@ -98,59 +273,84 @@ pub async fn create_curseforge_zip(
         .expect("all zip tasks should be finished")
         .into_inner();
 
-    log::info!("Copying overrides...");
-    zip_dir(
-        source_dir.join(LIT_OVERRIDES),
-        &mut zip,
-        LIT_OVERRIDES,
-        CreateCurseForgeZipError::ZipDir,
-    )?;
-    log::info!("Copying client-only overrides...");
-    zip_dir(
-        source_dir.join(LIT_CLIENT_OVERRIDES),
-        &mut zip,
-        LIT_OVERRIDES,
-        CreateCurseForgeZipError::ZipDir,
-    )?;
+    match &mut previous {
+        Some(previous) => {
+            log::info!("Copying overrides from the previous build...");
+            previous.copy_overrides(&mut zip)?;
+        }
+        None => zip_overrides(
+            source,
+            &mut zip,
+            &curseforge_overrides(overrides),
+            OverridesOptions::new(pack, build, source),
+            CreateCurseForgeZipError::ZipDir,
+        )?,
+    }
 
     log::info!("Writing manifest...");
     let manifest = CurseForgeManifest {
         minecraft: Minecraft {
             version: pack.minecraft_version.clone(),
-            mod_loaders: vec![ModLoader {
+            mod_loaders: std::iter::once(ModLoader {
                 id: format!("{}-{}", pack.mod_loader.id, pack.mod_loader.version),
                 primary: true,
-            }],
+            })
+            .chain(
+                pack.secondary_loaders
+                    .iter()
+                    .map(|l| format!("{}-{}", l.id, l.version))
+                    .chain(pack.curseforge_manifest.mod_loaders.iter().cloned())
+                    .unique()
+                    .map(|id| ModLoader { id, primary: false }),
+            )
+            .collect(),
         },
         manifest_type: ManifestType::MinecraftModpack,
         manifest_version: 1,
         name: pack.name.clone(),
         version: pack.version.clone(),
         author: pack.author.clone(),
-        files: pack
-            .mods
-            .curseforge
-            .values()
-            .filter(|m| m.env_requirements.client.is_needed(include_optional))
-            .map(|m| ManifestFile {
+        files: plan
+            .mods(Side::Client, &pack.mods.curseforge)
+            .map(|(_, m)| ManifestFile {
                 project_id: m.source.project_id,
-                file_id: m.source.version_id,
+                file_id: *m.file_id(),
                 required: true,
             })
             .collect(),
-        overrides: LIT_OVERRIDES.to_string(),
+        overrides: overrides.clone(),
+        project_id: pack.curseforge_manifest.project_id,
     };
     zip.start_file("manifest.json", *ZIP_OPTIONS)?;
     serde_json::to_writer(&mut zip, &manifest)?;
+    zip.start_file(BUILD_METADATA_FILE, *ZIP_OPTIONS)?;
+    serde_json::to_writer(
+        &mut zip,
+        &artifact_metadata(
+            build,
+            "curseforge",
+            plan.include_optional,
+            &pack.mods,
+            mod_files,
+            previous.as_ref().map(|p| &p.metadata),
+        ),
+    )?;
 
     log::info!("Flushing zip...");
 
     zip.finish()?;
+    output_atomic.commit()?;
 
-    log::info!(
-        "Created CurseForge zip at '{}'.",
-        output_file.display().errstyle(FILE_STYLE)
-    );
+    record_generated_file(&output_file);
+    if let Some(keep) = pack.keep_artifacts {
+        prune_artifacts(&output_file, keep);
+    }
+    record_archive(CURSEFORGE_ZIP, &output_file, mod_counts, started);
+    emit(ProgressEvent::ArtifactWritten {
+        artifact: CURSEFORGE_ZIP,
+        path: output_file,
+        overrides_only: false,
+    });
 
     Ok(())
 }
@@ -159,23 +359,54 @@ pub async fn create_curseforge_zip(
 pub enum CreateModrinthPackError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("{0}")]
+    PreviousArtifact(#[from] PreviousArtifactError),
     #[error("Json error: {0}")]
     Json(#[from] serde_json::error::Error),
     #[error("ZIP error: {0}")]
     Zip(#[from] zip::result::ZipError),
-    #[error("Zipping directory {0} failed: {1}")]
+    #[error("Zipping overrides from {0} failed: {1}")]
     ZipDir(String, #[source] ZipDirError),
     #[error("Zipping mod {0} failed: {1}")]
     ZipMod(String, #[source] ZipModError),
 }
 
+/// Create the Modrinth pack in `output_dir`, and return its path.
 pub async fn create_modrinth_pack(
     pack: &PackConfig<VerifiedModContainer>,
-    source_dir: &Path,
+    build: &BuildInfo,
+    source: &PackSource,
     output_dir: PathBuf,
-    include_optional: bool,
-) -> Result<(), CreateModrinthPackError> {
-    let output_file = output_dir.join(format!("{} ({}).mrpack", pack.name, pack.version));
+    plan: ArtifactPlan<'_>,
+    reuse_overrides: bool,
+    manifest_only: bool,
+) -> Result<PathBuf, CreateModrinthPackError> {
+    let started = Instant::now();
+    // Without the CurseForge mods, only the Modrinth mods are installed.
+    let side_mods = |side| {
+        plan.files(side)
+            .filter(|f| !manifest_only || f.site == Modrinth::CONFIG_KEY)
+            .count()
+    };
+    let mod_counts = ModCounts {
+        client: Some(side_mods(Side::Client)),
+        server: Some(side_mods(Side::Server)),
+    };
+    let output_file = output_dir.join(format!("{}.mrpack", artifact_name(pack, build)));
+    // A pack without the CurseForge mods must not count as an up-to-date full pack, and vice versa.
+    let artifact = if manifest_only {
+        "modrinth-manifest-only"
+    } else {
+        "modrinth"
+    };
+    let mut previous = if reuse_overrides {
+        Some(PreviousArchive::open(&output_file)?)
+    } else if is_up_to_date(&output_file, build, artifact, plan.include_optional) {
+        record_archive(MODRINTH_PACK, &output_file, mod_counts, started);
+        return Ok(output_file);
+    } else {
+        None
+    };
 
     log::info!(
         "Creating Modrinth pack at '{}'...",
@@ -187,6 +418,9 @@ pub async fn create_modrinth_pack(
     let mut modrinth_files = Vec::with_capacity(pack.mods.modrinth.len());
     for mod_ in pack.mods.modrinth.values() {
         let mod_info = &mod_.info;
+        // Only optional files are shown to players, so only they need describing.
+        let optional = mod_.env_requirements.client == KnownEnvRequirement::Optional
+            || mod_.env_requirements.server == KnownEnvRequirement::Optional;
         modrinth_files.push(modrinth_manifest::ModFile {
             path: format!("mods/{}", mod_info.filename),
             hashes: modrinth_manifest::ModFileHashes {
@@ -196,33 +430,88 @@ pub async fn create_modrinth_pack(
             env: Some(mod_.env_requirements.into()),
             downloads: vec![mod_info.url.clone()],
             file_size: mod_info.file_length,
+            name: optional.then(|| mod_info.project_info.name.clone()),
+            description: optional
+                .then(|| {
+                    mod_.note
+                        .clone()
+                        .or_else(|| mod_info.project_info.summary.clone())
+                })
+                .flatten(),
         });
     }
 
-    log::info!(
-        "Downloading {} mods...",
-        "CurseForge".errstyle(SITE_NAME_STYLE)
-    );
+    if !manifest_only {
+        log::info!(
+            "Downloading {} mods...",
+            "CurseForge".errstyle(SITE_NAME_STYLE)
+        );
+    }
 
-    let zip = ZipWriter::new(std::fs::File::create(&output_file)?);
+    // Write to a temporary file, so a failure doesn't leave a truncated archive behind.
+    let (output_atomic, output) = AtomicFile::create(&output_file)?;
+    let zip = ZipWriter::new(output);
 
     let zip_arc = Arc::new(Mutex::new(zip));
     let mut zip_dl_tasks = Vec::with_capacity(pack.mods.curseforge.len());
-    for (cfg_id, mod_) in &pack.mods.curseforge {
-        let overrides = match (
-            mod_.env_requirements.client.is_needed(include_optional),
-            mod_.env_requirements.server.is_needed(include_optional),
-        ) {
-            (true, true) => LIT_OVERRIDES,
-            (true, false) => LIT_CLIENT_OVERRIDES,
-            (false, true) => LIT_SERVER_OVERRIDES,
-            (false, false) => continue,
+    let mut mod_files = Vec::with_capacity(pack.mods.curseforge.len());
+    let mut embedded_files = Vec::with_capacity(pack.mods.curseforge.len());
+    for (cfg_id, mod_) in pack
+        .mods
+        .curseforge
+        .iter()
+        .sorted_by_key(|(k, _)| k.as_str())
+    {
+        let Some(side) = plan.override_side(CurseForge::CONFIG_KEY, cfg_id) else {
+            // Mods unsupported on both sides fail verification, so this one is optional.
+            left_out_mod(
+                MODRINTH_PACK,
+                CurseForge::CONFIG_KEY,
+                cfg_id,
+                LeftOutReason::Optional("client or server"),
+            );
+            continue;
+        };
+        let Some(overrides) = MODRINTH_OVERRIDES.dest(side) else {
+            left_out_mod(
+                MODRINTH_PACK,
+                CurseForge::CONFIG_KEY,
+                cfg_id,
+                MODRINTH_OVERRIDES.left_out,
+            );
+            continue;
         };
+        if manifest_only {
+            left_out_mod(
+                MODRINTH_PACK,
+                CurseForge::CONFIG_KEY,
+                cfg_id,
+                LeftOutReason::Format("only the manifest and overrides are generated"),
+            );
+            warn(Warning::for_mod(
+                WarningKind::SkippedFile,
+                CurseForge::CONFIG_KEY,
+                cfg_id,
+                "Left out, as only the manifest and overrides are generated",
+            ));
+            continue;
+        }
+        let path = [overrides, LIT_MODS, &mod_.info.filename].join("/");
+        embedded_files.push(modrinth_manifest::EmbeddedFile {
+            path: path.clone(),
+            project_id: mod_.source.project_id,
+            file_id: *mod_.file_id(),
+            env: mod_.env_requirements.into(),
+        });
+        mod_files.push(path);
         zip_dl_tasks.push((
             cfg_id,
             spawn(add_mod_to_zip(
+                CurseForge,
+                cfg_id.clone(),
                 mod_.clone(),
-                overrides,
+                MODRINTH_PACK,
+                overrides.to_string(),
                 Arc::clone(&zip_arc),
             )),
         ));
@@ -236,38 +525,26 @@ pub async fn create_modrinth_pack(
         .expect("all zip tasks should be finished")
         .into_inner();
 
-    log::info!("Copying overrides...");
-    zip_dir(
-        source_dir.join(LIT_OVERRIDES),
-        &mut zip,
-        LIT_OVERRIDES,
-        CreateModrinthPackError::ZipDir,
-    )?;
-    log::info!("Copying client-only overrides...");
-    zip_dir(
-        source_dir.join(LIT_CLIENT_OVERRIDES),
-        &mut zip,
-        LIT_CLIENT_OVERRIDES,
-        CreateModrinthPackError::ZipDir,
-    )?;
-    log::info!("Copying server-only overrides...");
-    zip_dir(
-        source_dir.join(LIT_SERVER_OVERRIDES),
-        &mut zip,
-        LIT_SERVER_OVERRIDES,
-        CreateModrinthPackError::ZipDir,
-    )?;
+    match &mut previous {
+        Some(previous) => {
+            log::info!("Copying overrides from the previous build...");
+            previous.copy_overrides(&mut zip)?;
+        }
+        None => zip_overrides(
+            source,
+            &mut zip,
+            &MODRINTH_OVERRIDES,
+            OverridesOptions::new(pack, build, source),
+            CreateModrinthPackError::ZipDir,
+        )?,
+    }
 
     log::info!("Writing manifest...");
 
-    let forge =
-        (pack.mod_loader.id == ModLoaderType::Forge).then(|| pack.mod_loader.version.clone());
-    let neoforge =
-        (pack.mod_loader.id == ModLoaderType::Neoforge).then(|| pack.mod_loader.version.clone());
-    let fabric_loader =
-        (pack.mod_loader.id == ModLoaderType::Fabric).then(|| pack.mod_loader.version.clone());
-    let quilt_loader =
-        (pack.mod_loader.id == ModLoaderType::Quilt).then(|| pack.mod_loader.version.clone());
+    let forge = pack.loader_version(&ModLoaderType::Forge).cloned();
+    let neoforge = pack.loader_version(&ModLoaderType::Neoforge).cloned();
+    let fabric_loader = pack.loader_version(&ModLoaderType::Fabric).cloned();
+    let quilt_loader = pack.loader_version(&ModLoaderType::Quilt).cloned();
 
     let manifest = ModrinthManifest {
         format_version: 1,
@@ -286,98 +563,658 @@ pub async fn create_modrinth_pack(
     };
     zip.start_file("modrinth.index.json", *ZIP_OPTIONS)?;
     serde_json::to_writer(&mut zip, &manifest)?;
+    if !embedded_files.is_empty() {
+        embedded_files.sort_by(|a, b| a.path.cmp(&b.path));
+        zip.start_file(modrinth_manifest::EMBEDDED_MANIFEST, *ZIP_OPTIONS)?;
+        serde_json::to_writer(
+            &mut zip,
+            &modrinth_manifest::EmbeddedManifest {
+                files: embedded_files,
+            },
+        )?;
+    }
+    zip.start_file(BUILD_METADATA_FILE, *ZIP_OPTIONS)?;
+    serde_json::to_writer(
+        &mut zip,
+        &artifact_metadata(
+            build,
+            artifact,
+            plan.include_optional,
+            &pack.mods,
+            mod_files,
+            previous.as_ref().map(|p| &p.metadata),
+        ),
+    )?;
 
     log::info!("Flushing zip...");
 
     zip.finish()?;
+    output_atomic.commit()?;
 
-    log::info!(
-        "Created Modrinth pack at '{}'.",
-        output_file.display().errstyle(FILE_STYLE)
-    );
+    record_generated_file(&output_file);
+    if let Some(keep) = pack.keep_artifacts {
+        prune_artifacts(&output_file, keep);
+    }
+    record_archive(MODRINTH_PACK, &output_file, mod_counts, started);
+    emit(ProgressEvent::ArtifactWritten {
+        artifact: MODRINTH_PACK,
+        path: output_file.clone(),
+        overrides_only: false,
+    });
 
-    Ok(())
+    Ok(output_file)
 }
 
 #[derive(Debug, Error)]
 pub enum CreateServerBaseError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("Json error: {0}")]
+    Json(#[from] serde_json::error::Error),
     #[error("Cloning directory {0} failed: {1}")]
     CloneDir(String, #[source] CloneDirError),
     #[error("Error downloading mods: {0}")]
     ModDownload(#[from] ModsDownloadError),
+    #[error("{0}")]
+    PreviousArtifact(#[from] PreviousArtifactError),
 }
 
 pub async fn create_server_base(
     pack: &PackConfig<VerifiedModContainer>,
-    source_dir: &Path,
+    build: &BuildInfo,
+    source: &PackSource,
     output_dir: PathBuf,
-    include_optional: bool,
+    plan: ArtifactPlan<'_>,
+    reuse_overrides: bool,
+    link_mode: LinkMode,
 ) -> Result<(), CreateServerBaseError> {
+    let started = Instant::now();
     log::info!(
         "Creating server base at '{}'...",
         output_dir.display().errstyle(FILE_STYLE)
     );
 
-    // Wipe the output dir first, so we don't have leftover files
-    // Yes this defeats the hash check for now. TODO: cache files for the user as a whole
-    if output_dir.exists() {
-        log::info!("Removing existing server base...");
-        std::fs::remove_dir_all(&output_dir)?;
-    }
+    let mods_folder = output_dir.join(LIT_MODS);
+    let keep_mods = plan
+        .files(Side::Server)
+        .map(|f| f.file.as_str())
+        .collect::<HashSet<_>>();
+    let options = OverridesOptions::new(pack, build, source);
+    let previous = if reuse_overrides {
+        let (previous, previous_mods) = previous_server_base(&output_dir)?;
+        log::info!("Removing mods that are no longer part of the pack...");
+        for name in previous_mods {
+            let path = mods_folder.join(&name);
+            if !keep_mods.contains(name.as_str()) && path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Some(previous)
+    } else {
+        // Clear the output dir first, so we don't have leftover files. Downloaded mods that are
+        // still part of the pack are kept, so an interrupted run can be resumed without
+        // downloading them again. The download checks their hashes. Files that are patched are
+        // kept too, so the changes made to them on the server aren't lost.
+        if output_dir.exists() {
+            log::info!("Clearing existing server base...");
+            let patched = patch_targets(source, options)?;
+            tokio::task::block_in_place(|| clear_server_base(&output_dir, &keep_mods, &patched))?;
+        }
+        None
+    };
 
     std::fs::create_dir_all(&output_dir)?;
-    let mods_folder = output_dir.join(LIT_MODS);
     std::fs::create_dir_all(&mods_folder)?;
 
-    log::info!("Copying overrides...");
-    clone_dir(
-        source_dir.join(LIT_OVERRIDES),
-        &output_dir,
-        CreateServerBaseError::CloneDir,
+    if previous.is_none() {
+        clone_overrides(source, &output_dir, options)?;
+    }
+
+    plan.record_left_out(SERVER_BASE, Side::Server);
+    download_mods(pack, plan, &mods_folder, link_mode).await?;
+
+    let metadata = artifact_metadata(
+        build,
+        "server-base",
+        plan.include_optional,
+        &pack.mods,
+        keep_mods.iter().sorted().map(|m| m.to_string()).collect(),
+        previous.as_ref(),
+    );
+    write_atomic(
+        &output_dir.join(BUILD_METADATA_FILE),
+        &serde_json::to_vec_pretty(&metadata)?,
     )?;
-    log::info!("Copying server-only overrides...");
-    clone_dir(
-        source_dir.join(LIT_SERVER_OVERRIDES),
+
+    if previous.is_some() {
+        // Anything else in it may be from running the server, it's not known to be generated.
+        let files = keep_mods
+            .iter()
+            .map(|m| format!("{}/{}", LIT_MODS, m))
+            .chain([BUILD_METADATA_FILE.to_string()])
+            .collect::<Vec<_>>();
+        record_generated(&output_dir, files.iter().map(String::as_str));
+    } else {
+        record_generated_dir(&output_dir);
+    }
+    record_server_base(
+        SERVER_BASE,
         &output_dir,
-        CreateServerBaseError::CloneDir,
-    )?;
+        ModCounts {
+            client: None,
+            server: Some(keep_mods.len()),
+        },
+        started,
+    );
+    emit(ProgressEvent::ArtifactWritten {
+        artifact: SERVER_BASE,
+        path: output_dir,
+        overrides_only: false,
+    });
 
-    download_mods(pack, &mods_folder, |reqs| {
-        reqs.server.is_needed(include_optional)
-    })
-    .await?;
+    Ok(())
+}
+
+/// Copy the overrides for the server into a server base. Later source directories win, as they
+/// overwrite the files of the ones before them. Patched files that are still in the server base
+/// aren't overwritten, only patched.
+fn clone_overrides(
+    source: &PackSource,
+    output_dir: &Path,
+    options: OverridesOptions,
+) -> Result<(), CreateServerBaseError> {
+    let kept = patch_targets(source, options)?
+        .into_iter()
+        .filter(|relative| output_dir.join(relative).is_file())
+        .collect::<HashSet<_>>();
+    log::info!("Copying overrides...");
+    for dir in source.join_all(&options.dirs.overrides) {
+        clone_dir(
+            dir,
+            output_dir,
+            LIT_OVERRIDES,
+            options,
+            &kept,
+            CreateServerBaseError::CloneDir,
+        )?;
+    }
+    for dir in source.join_all(&options.dirs.client_overrides) {
+        if dir.exists() {
+            left_out_override(
+                SERVER_BASE,
+                dir.display().to_string(),
+                LeftOutReason::Side("server"),
+            );
+        }
+    }
+    log::info!("Copying server-only overrides...");
+    for dir in source.join_all(&options.dirs.server_overrides) {
+        clone_dir(
+            dir,
+            output_dir,
+            LIT_SERVER_OVERRIDES,
+            options,
+            &kept,
+            CreateServerBaseError::CloneDir,
+        )?;
+    }
+    Ok(())
+}
 
+/// The paths of the files patched by the server overrides, relative to the server base.
+fn patch_targets(
+    source: &PackSource,
+    options: OverridesOptions,
+) -> Result<HashSet<PathBuf>, CreateServerBaseError> {
+    let mut targets = HashSet::new();
+    for dir in source.join_all(&options.dirs.server_overrides) {
+        if !dir.exists() {
+            continue;
+        }
+        let dir_error =
+            |e: CloneDirError| CreateServerBaseError::CloneDir(dir.display().to_string(), e);
+        let exclusions =
+            Exclusions::load(&dir, options.exclude).map_err(|e| dir_error(e.into()))?;
+        for entry in walk_overrides(&dir, options.symlinks != Symlinks::Error, &exclusions) {
+            let entry = entry.map_err(|e| dir_error(e.into()))?;
+            let relative = entry
+                .path()
+                .strip_prefix(&dir)
+                .expect("walked path must contain `from` as prefix");
+            if entry.file_type().is_file()
+                && options.is_patch(LIT_SERVER_OVERRIDES, &relative.to_string_lossy())
+            {
+                targets.insert(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(targets)
+}
+
+/// Generate only the overrides of a server base, keeping the mods downloaded by the previous
+/// build. The mods aren't verified or downloaded.
+pub async fn rebuild_server_base_overrides<MC>(
+    pack: &PackConfig<MC>,
+    build: &BuildInfo,
+    source: &PackSource,
+    output_dir: PathBuf,
+) -> Result<(), CreateServerBaseError> {
+    let started = Instant::now();
+    let (previous, previous_mods) = previous_server_base(&output_dir)?;
     log::info!(
-        "Created server base at '{}'.",
+        "Regenerating overrides of server base at '{}'...",
         output_dir.display().errstyle(FILE_STYLE)
     );
+    let keep_mods = previous_mods.iter().map(String::as_str).collect();
+    let options = OverridesOptions::new(pack, build, source);
+    let patched = patch_targets(source, options)?;
+    tokio::task::block_in_place(|| clear_server_base(&output_dir, &keep_mods, &patched))?;
+    clone_overrides(source, &output_dir, options)?;
+
+    write_atomic(
+        &output_dir.join(BUILD_METADATA_FILE),
+        &serde_json::to_vec_pretty(&overrides_only_metadata(build, &previous))?,
+    )?;
+
+    record_generated_dir(&output_dir);
+    record_server_base(SERVER_BASE, &output_dir, ModCounts::default(), started);
+    emit(ProgressEvent::ArtifactWritten {
+        artifact: SERVER_BASE,
+        path: output_dir,
+        overrides_only: true,
+    });
 
     Ok(())
 }
 
+/// The metadata of an artifact whose overrides were generated again, with everything else taken
+/// from the previous build.
+fn overrides_only_metadata(build: &BuildInfo, previous: &BuildMetadata) -> BuildMetadata {
+    BuildMetadata {
+        config_hash: previous.config_hash.clone(),
+        mod_files: previous.mod_files.clone(),
+        mods: previous.mods.clone(),
+        ..build.metadata(&previous.artifact, previous.include_optional)
+    }
+}
+
+/// Generate only the overrides of the CurseForge ZIP, taking the mods and manifest from the
+/// previous build. The mods aren't verified or downloaded.
+pub async fn rebuild_curseforge_zip_overrides<MC>(
+    pack: &PackConfig<MC>,
+    build: &BuildInfo,
+    source: &PackSource,
+    output_dir: PathBuf,
+) -> Result<(), CreateCurseForgeZipError> {
+    let output_file = output_dir.join(format!("{}.zip", artifact_name(pack, build)));
+    rebuild_archive_overrides(
+        &output_file,
+        build,
+        source,
+        &curseforge_overrides(&pack.curseforge_manifest.overrides),
+        OverridesOptions::new(pack, build, source),
+        CreateCurseForgeZipError::ZipDir,
+    )
+}
+
+/// Generate only the overrides of the Modrinth pack, taking the mods and manifest from the
+/// previous build. The mods aren't verified or downloaded.
+pub async fn rebuild_modrinth_pack_overrides<MC>(
+    pack: &PackConfig<MC>,
+    build: &BuildInfo,
+    source: &PackSource,
+    output_dir: PathBuf,
+) -> Result<(), CreateModrinthPackError> {
+    let output_file = output_dir.join(format!("{}.mrpack", artifact_name(pack, build)));
+    rebuild_archive_overrides(
+        &output_file,
+        build,
+        source,
+        &MODRINTH_OVERRIDES,
+        OverridesOptions::new(pack, build, source),
+        CreateModrinthPackError::ZipDir,
+    )
+}
+
+fn rebuild_archive_overrides<E>(
+    output_file: &Path,
+    build: &BuildInfo,
+    source: &PackSource,
+    layout: &OverridesLayout,
+    options: OverridesOptions,
+    error_mapper: fn(String, ZipDirError) -> E,
+) -> Result<(), E>
+where
+    E: From<std::io::Error>
+        + From<zip::result::ZipError>
+        + From<serde_json::Error>
+        + From<PreviousArtifactError>,
+{
+    let started = Instant::now();
+    let mut previous = PreviousArchive::open(output_file)?;
+    log::info!(
+        "Regenerating overrides of '{}'...",
+        output_file.display().errstyle(FILE_STYLE)
+    );
+
+    let (output_atomic, output) = AtomicFile::create(output_file)?;
+    let mut zip = ZipWriter::new(output);
+    log::info!("Copying mods from the previous build...");
+    previous.copy_mods(&mut zip)?;
+    zip_overrides(source, &mut zip, layout, options, error_mapper)?;
+    zip.start_file(BUILD_METADATA_FILE, *ZIP_OPTIONS)?;
+    serde_json::to_writer(
+        &mut zip,
+        &overrides_only_metadata(build, &previous.metadata),
+    )?;
+    zip.finish()?;
+    output_atomic.commit()?;
+
+    record_generated_file(output_file);
+    record_archive(layout.artifact, output_file, ModCounts::default(), started);
+    emit(ProgressEvent::ArtifactWritten {
+        artifact: layout.artifact,
+        path: output_file.to_path_buf(),
+        overrides_only: true,
+    });
+
+    Ok(())
+}
+
+/// How the override directories of a pack are copied into the outputs.
+#[derive(Clone, Copy)]
+struct OverridesOptions<'a> {
+    non_utf8_paths: NonUtf8Paths,
+    symlinks: Symlinks,
+    executables: &'a Globs,
+    exclude: &'a Globs,
+    sides: &'a [OverrideSideRule],
+    templates: &'a Globs,
+    patches: &'a Globs,
+    pack_name: &'a str,
+    pack_version: &'a str,
+    minecraft_version: &'a str,
+    mod_loader: &'a crate::config::pack::ModLoader,
+    dirs: &'a OverrideDirs,
+    build: &'a BuildInfo,
+    /// The source directories of the pack, which followed symlinks must stay inside.
+    source_dirs: &'a [PathBuf],
+}
+
+impl<'a> OverridesOptions<'a> {
+    fn new<MC>(pack: &'a PackConfig<MC>, build: &'a BuildInfo, source: &'a PackSource) -> Self {
+        Self {
+            non_utf8_paths: pack.non_utf8_paths,
+            symlinks: pack.symlinks,
+            executables: &pack.executables,
+            exclude: &pack.exclude,
+            sides: &pack.override_sides,
+            templates: &pack.templates,
+            patches: &pack.patches,
+            pack_name: &pack.name,
+            pack_version: &pack.version,
+            minecraft_version: &pack.minecraft_version,
+            mod_loader: &pack.mod_loader,
+            dirs: &pack.override_dirs,
+            build,
+            source_dirs: &source.dirs,
+        }
+    }
+
+    /// Whether the symlink at `path` points inside one of the pack's source directories, so
+    /// following it can't copy other files on the machine into the outputs, e.g. from a pack
+    /// imported from elsewhere.
+    fn is_contained_symlink(&self, path: &Path) -> bool {
+        let Ok(target) = path.canonicalize() else {
+            // Broken symlinks fail when they're copied.
+            return true;
+        };
+        self.source_dirs
+            .iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .any(|dir| target.starts_with(dir))
+    }
+
+    /// The side the file at `relative` in an override directory of the given kind is for.
+    fn side_of(&self, kind: &str, relative: &str) -> OverrideSide {
+        OverrideSide::of(
+            kind,
+            &relative.replace(std::path::MAIN_SEPARATOR, "/"),
+            self.sides,
+        )
+    }
+
+    /// Whether the pack declares the file at `relative` in an override directory executable.
+    fn is_executable(&self, relative: &str) -> bool {
+        self.executables
+            .is_match(&relative.replace(std::path::MAIN_SEPARATOR, "/"))
+    }
+
+    /// Whether the pack declares the file at `relative` in an override directory a template.
+    fn is_template(&self, relative: &str) -> bool {
+        self.templates
+            .is_match(&relative.replace(std::path::MAIN_SEPARATOR, "/"))
+    }
+
+    /// Whether the file at `relative` in an override directory of the given kind is a patch. Only
+    /// server overrides can be patches.
+    fn is_patch(&self, kind: &str, relative: &str) -> bool {
+        kind == LIT_SERVER_OVERRIDES
+            && self
+                .patches
+                .is_match(&relative.replace(std::path::MAIN_SEPARATOR, "/"))
+    }
+
+    /// Read a template override, replacing the placeholders in it.
+    fn expand_template(&self, src_path: &Path) -> Result<Vec<u8>, std::io::Error> {
+        let content = std::fs::read_to_string(src_path).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("Error reading template {}: {}", src_path.display(), e),
+            )
+        })?;
+        let expanded = self.build.expand_git_placeholders(
+            &content
+                .replace("{pack_name}", self.pack_name)
+                .replace("{pack_version}", self.pack_version)
+                .replace("{minecraft_version}", self.minecraft_version)
+                .replace("{mod_loader}", &self.mod_loader.id.to_string())
+                .replace("{mod_loader_version}", &self.mod_loader.version)
+                .replace("{target}", self.build.target.as_deref().unwrap_or_default()),
+        );
+        Ok(expanded.into_bytes())
+    }
+}
+
+/// Walk an override directory, leaving out its excluded files and directories.
+pub(crate) fn walk_overrides<'a>(
+    from: &'a Path,
+    follow_links: bool,
+    exclusions: &'a Exclusions,
+) -> impl Iterator<Item = walkdir::Result<walkdir::DirEntry>> + 'a {
+    WalkDir::new(from)
+        .follow_links(follow_links)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(move |entry| {
+            let relative = entry
+                .path()
+                .strip_prefix(from)
+                .expect("walked path must contain `from` as prefix");
+            let excluded = entry.depth() > 0
+                && exclusions.is_excluded(entry.path(), relative, entry.file_type().is_dir());
+            if excluded {
+                log::debug!("Skipped {} as it is excluded", entry.path().display());
+            }
+            !excluded
+        })
+}
+
+/// The Unix permissions of an override file in the outputs. These are its own on Unix, which
+/// keeps start scripts executable, and read-write otherwise. Files the pack declares executable
+/// are made so on any OS.
+fn override_permissions(metadata: &std::fs::Metadata, executable: bool) -> u32 {
+    #[cfg(unix)]
+    let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777;
+    #[cfg(not(unix))]
+    let mode = {
+        let _ = metadata;
+        0o644
+    };
+    if executable {
+        mode | 0o111
+    } else {
+        mode
+    }
+}
+
+/// Set the Unix permissions of a file. Other OSes don't have them.
+fn set_permissions(path: &Path, mode: u32) -> Result<(), std::io::Error> {
+    #[cfg(unix)]
+    return std::fs::set_permissions(path, std::os::unix::fs::PermissionsExt::from_mode(mode));
+    #[cfg(not(unix))]
+    {
+        let _ = (path, mode);
+        Ok(())
+    }
+}
+
+/// Remove everything from a server base, except for the given files in its mods folder and the
+/// given files at paths relative to it.
+fn clear_server_base(
+    output_dir: &Path,
+    keep_mods: &HashSet<&str>,
+    keep_files: &HashSet<PathBuf>,
+) -> Result<(), std::io::Error> {
+    for entry in std::fs::read_dir(output_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_name() == LOCK_FILE || entry.file_name() == GENERATED_FILE {
+            continue;
+        }
+        if entry.file_name() == LIT_MODS && entry.file_type()?.is_dir() {
+            for mod_entry in std::fs::read_dir(&path)? {
+                let mod_entry = mod_entry?;
+                let keep = mod_entry.file_type()?.is_file()
+                    && mod_entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|name| keep_mods.contains(name));
+                if !keep {
+                    remove_path(&mod_entry.path())?;
+                }
+            }
+        } else {
+            clear_except(&path, Path::new(&entry.file_name()), keep_files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove `path`, at `relative` in a server base, except for the given files in it.
+fn clear_except(
+    path: &Path,
+    relative: &Path,
+    keep_files: &HashSet<PathBuf>,
+) -> Result<(), std::io::Error> {
+    if path.is_symlink() {
+        return std::fs::remove_file(path);
+    }
+    if path.is_file() && keep_files.contains(relative) {
+        return Ok(());
+    }
+    if path.is_dir() && keep_files.iter().any(|f| f.starts_with(relative)) {
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            clear_except(&entry.path(), &relative.join(entry.file_name()), keep_files)?;
+        }
+        return Ok(());
+    }
+    remove_path(path)
+}
+
+fn remove_path(path: &Path) -> Result<(), std::io::Error> {
+    if path.is_dir() && !path.is_symlink() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum CloneDirError {
     #[error("I/O Error: {0}")]
     Io(#[from] std::io::Error),
     #[error("Walk Error: {0}")]
     Walk(#[from] walkdir::Error),
+    #[error("Error loading {IGNORE_FILE}: {0}")]
+    Ignore(#[from] ignore::Error),
+    #[error("{}", symlinks_message(.0))]
+    Symlinks(Vec<String>),
+    #[error("{}", escaping_symlinks_message(.0))]
+    EscapingSymlinks(Vec<String>),
+    #[error("Error applying patch {0}: {1}")]
+    Patch(String, #[source] Box<PatchError>),
 }
 
-fn clone_dir<F, T, E, EF>(from: F, to: T, error_mapper: EF) -> Result<(), E>
+fn symlinks_message(symlinks: &[String]) -> String {
+    format!(
+        "These overrides are symlinks, set `symlinks` to follow or preserve them:\n{}",
+        symlinks.join("\n")
+    )
+}
+
+fn escaping_symlinks_message(symlinks: &[String]) -> String {
+    format!(
+        "These overrides are symlinks pointing outside the pack, which netherfire refuses to copy:\n{}",
+        symlinks.join("\n")
+    )
+}
+
+/// Whether a symlink at `relative` in a directory, pointing to `target`, stays inside the
+/// directory, judging by the path alone.
+fn symlink_stays_inside(relative: &Path, target: &Path) -> bool {
+    // The depth of the directory the symlink is in.
+    let mut depth = relative.components().count().saturating_sub(1);
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    true
+}
+
+fn clone_dir<F, T, E, EF>(
+    from: F,
+    to: T,
+    kind: &str,
+    options: OverridesOptions,
+    kept: &HashSet<PathBuf>,
+    error_mapper: EF,
+) -> Result<(), E>
 where
     F: AsRef<Path>,
     T: AsRef<Path>,
     EF: FnOnce(String, CloneDirError) -> E,
 {
     let from = from.as_ref();
-    tokio::task::block_in_place(|| clone_dir_impl(from, to))
+    tokio::task::block_in_place(|| clone_dir_impl(from, to, kind, options, kept))
         .map_err(|e| error_mapper(from.display().to_string(), e))
 }
 
-/// Walk [from] and clone its files to [to].
-fn clone_dir_impl<F: AsRef<Path>, T: AsRef<Path>>(from: F, to: T) -> Result<(), CloneDirError> {
+/// Walk [from], an override directory of the given kind, and clone its files that servers need
+/// to [to]. Patches are merged into the files already in [to], and the files in [kept], relative
+/// to [to], aren't replaced. The files are cloned in parallel.
+fn clone_dir_impl<F: AsRef<Path>, T: AsRef<Path>>(
+    from: F,
+    to: T,
+    kind: &str,
+    options: OverridesOptions,
+    kept: &HashSet<PathBuf>,
+) -> Result<(), CloneDirError> {
     let from = from.as_ref();
     let to = to.as_ref();
     if !from.exists() {
@@ -385,16 +1222,50 @@ fn clone_dir_impl<F: AsRef<Path>, T: AsRef<Path>>(from: F, to: T) -> Result<(),
         return Ok(());
     }
     std::fs::create_dir_all(to)?;
-    for entry in WalkDir::new(from) {
+    let mut files = Vec::new();
+    let mut symlink_paths = Vec::new();
+    let mut escaping_paths = Vec::<PathBuf>::new();
+    let exclusions = Exclusions::load(from, options.exclude)?;
+    for entry in walk_overrides(from, options.symlinks == Symlinks::Follow, &exclusions) {
         let entry = entry?;
         let ft = entry.file_type();
+        let is_symlink = entry.path_is_symlink() && entry.depth() > 0;
+        let followed_escaping = entry.path_is_symlink()
+            && !ft.is_symlink()
+            && !options.is_contained_symlink(entry.path());
         let src_path = entry.into_path();
-        let dest_path = to.join(
-            src_path
-                .strip_prefix(from)
-                .expect("walked path must contain `from` as prefix"),
-        );
-        if ft.is_dir() {
+        if escaping_paths.iter().any(|p| src_path.starts_with(p)) {
+            continue;
+        }
+        let relative = src_path
+            .strip_prefix(from)
+            .expect("walked path must contain `from` as prefix");
+        let dest_path = to.join(relative);
+        if is_symlink && options.symlinks == Symlinks::Error {
+            symlink_paths.push(src_path.display().to_string());
+        } else if followed_escaping {
+            escaping_paths.push(src_path);
+        } else if ft.is_symlink() {
+            // Only preserved symlinks aren't followed. They must not point out of the server base.
+            if !symlink_stays_inside(relative, &std::fs::read_link(&src_path)?) {
+                escaping_paths.push(src_path);
+                continue;
+            }
+            if dest_path.symlink_metadata().is_ok() {
+                remove_path(&dest_path)?;
+            }
+            copy_symlink(&src_path, &dest_path)?;
+            log::debug!(
+                "Copied symlink {} to {}",
+                src_path.display(),
+                dest_path.display()
+            );
+        } else if ft.is_dir() {
+            // Don't write through a symlink preserved from an earlier directory.
+            if dest_path.is_symlink() {
+                std::fs::remove_file(&dest_path)?;
+            }
+            // Directories are walked before their contents, so they exist before cloning files.
             match std::fs::create_dir(&dest_path) {
                 Ok(_) => log::debug!("Created directory {}", dest_path.display()),
                 Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
@@ -403,33 +1274,32 @@ fn clone_dir_impl<F: AsRef<Path>, T: AsRef<Path>>(from: F, to: T) -> Result<(),
                 Err(e) => return Err(e.into()),
             }
         } else if ft.is_file() {
-            let mut done = false;
-            while !done {
-                if dest_path.exists() {
-                    std::fs::remove_file(&dest_path)?;
-                }
-                match reflink_or_copy(&src_path, &dest_path) {
-                    Ok(v) => {
-                        done = true;
-                        match v {
-                            Some(_) => log::debug!(
-                                "Copied {} to {}",
-                                src_path.display(),
-                                dest_path.display()
-                            ),
-                            None => log::debug!(
-                                "Reflinked {} to {}",
-                                src_path.display(),
-                                dest_path.display()
-                            ),
-                        }
-                    }
-                    Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
-                        // Loop to try again.
-                    }
-                    Err(e) => return Err(e.into()),
-                }
+            let patch = options.is_patch(kind, &relative.to_string_lossy());
+            if !patch && kept.contains(relative) {
+                log::debug!(
+                    "Skipped {} as {} is patched instead",
+                    src_path.display(),
+                    dest_path.display()
+                );
+                continue;
+            }
+            let relative = relative.to_string_lossy();
+            if options.side_of(kind, &relative) == OverrideSide::Client {
+                left_out_override(
+                    SERVER_BASE,
+                    src_path.display().to_string(),
+                    LeftOutReason::Side("server"),
+                );
+                continue;
+            }
+            if patch {
+                PatchFormat::of(&src_path).map_err(|e| {
+                    CloneDirError::Patch(src_path.display().to_string(), Box::new(e))
+                })?;
             }
+            let executable = options.is_executable(&relative);
+            let template = options.is_template(&relative);
+            files.push((src_path, dest_path, executable, template, patch));
         } else {
             log::debug!(
                 "Skipped {} as it is not a regular file or directory",
@@ -438,7 +1308,90 @@ fn clone_dir_impl<F: AsRef<Path>, T: AsRef<Path>>(from: F, to: T) -> Result<(),
         }
     }
 
-    Ok(())
+    if !symlink_paths.is_empty() {
+        return Err(CloneDirError::Symlinks(symlink_paths));
+    }
+    if !escaping_paths.is_empty() {
+        return Err(CloneDirError::EscapingSymlinks(
+            escaping_paths
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect(),
+        ));
+    }
+
+    for_each_parallel(
+        files,
+        |(src_path, dest_path, executable, template, patch)| {
+            if patch && dest_path.is_file() {
+                let merged = apply_patch(&std::fs::read(&dest_path)?, &src_path).map_err(|e| {
+                    CloneDirError::Patch(src_path.display().to_string(), Box::new(e))
+                })?;
+                std::fs::write(&dest_path, merged)?;
+                log::debug!(
+                    "Patched {} with {}",
+                    dest_path.display(),
+                    src_path.display()
+                );
+                // The patched file keeps its permissions.
+                return Ok(());
+            }
+            if template {
+                let content = options.expand_template(&src_path)?;
+                if dest_path.symlink_metadata().is_ok() {
+                    remove_path(&dest_path)?;
+                }
+                std::fs::write(&dest_path, content)?;
+            } else {
+                clone_file(&src_path, &dest_path)?;
+            }
+            let mode = override_permissions(&std::fs::metadata(&src_path)?, executable);
+            set_permissions(&dest_path, mode)?;
+            Ok(())
+        },
+        |()| Ok(()),
+    )
+}
+
+/// Create a symlink at `dest_path` pointing to the same path as the one at `src_path`.
+fn copy_symlink(src_path: &Path, dest_path: &Path) -> Result<(), std::io::Error> {
+    let target = std::fs::read_link(src_path)?;
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(target, dest_path);
+    #[cfg(windows)]
+    return if src_path.is_dir() {
+        std::os::windows::fs::symlink_dir(target, dest_path)
+    } else {
+        std::os::windows::fs::symlink_file(target, dest_path)
+    };
+}
+
+/// Clone a file, replacing the destination if it exists.
+fn clone_file(src_path: &Path, dest_path: &Path) -> Result<(), CloneDirError> {
+    loop {
+        // Also replace symlinks, including broken ones.
+        if dest_path.symlink_metadata().is_ok() {
+            remove_path(dest_path)?;
+        }
+        match reflink_or_copy(src_path, dest_path) {
+            Ok(Some(_)) => {
+                log::debug!("Copied {} to {}", src_path.display(), dest_path.display());
+                return Ok(());
+            }
+            Ok(None) => {
+                log::debug!(
+                    "Reflinked {} to {}",
+                    src_path.display(),
+                    dest_path.display()
+                );
+                return Ok(());
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                // Loop to try again.
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -447,60 +1400,267 @@ pub enum ZipDirError {
     Io(#[from] std::io::Error),
     #[error("Walk Error: {0}")]
     Walk(#[from] walkdir::Error),
+    #[error("Error loading {IGNORE_FILE}: {0}")]
+    Ignore(#[from] ignore::Error),
     #[error("Zip Error: {0}")]
     Zip(#[from] zip::result::ZipError),
+    #[error("{}", symlinks_message(.0))]
+    Symlinks(Vec<String>),
+    #[error("{}", escaping_symlinks_message(.0))]
+    EscapingSymlinks(Vec<String>),
+    #[error("Error applying patch {0}: {1}")]
+    Patch(String, #[source] Box<PatchError>),
+    #[error(
+        "These files have names that aren't valid UTF-8, set `non_utf8_paths` to skip or rename \
+        them:\n{}",
+        .0.join("\n")
+    )]
+    NonUtf8Paths(Vec<String>),
 }
 
-/// Walk [from] and zip its files to [to].
-fn zip_dir<F, W, E, EF>(
-    from: F,
+/// Paths in the installed pack longer than this may exceed the Windows path length limit of 260,
+/// as launchers put instances a few directories deep.
+const MAX_INSTALLED_PATH_LENGTH: usize = 180;
+
+/// Copy the override directories of the source into an archive, as laid out by `layout`.
+fn zip_overrides<W, E>(
+    source: &PackSource,
+    zip: &mut ZipWriter<W>,
+    layout: &OverridesLayout,
+    options: OverridesOptions,
+    error_mapper: fn(String, ZipDirError) -> E,
+) -> Result<(), E>
+where
+    W: Write + Seek,
+{
+    log::info!("Copying overrides...");
+    let (dirs, left_out): (Vec<_>, Vec<_>) =
+        [LIT_OVERRIDES, LIT_CLIENT_OVERRIDES, LIT_SERVER_OVERRIDES]
+            .into_iter()
+            .partition(|kind| {
+                OverrideSide::of_kind(kind).is_none_or(|side| layout.dest(side).is_some())
+            });
+    for dir in left_out
+        .into_iter()
+        .flat_map(|kind| source.join_all(options.dirs.of_kind(kind)))
+        .filter(|dir| dir.exists())
+    {
+        left_out_override(layout.artifact, dir.display().to_string(), layout.left_out);
+    }
+    let dirs = dirs
+        .into_iter()
+        .flat_map(|kind| {
+            source
+                .join_all(options.dirs.of_kind(kind))
+                .into_iter()
+                .map(move |dir| (kind, dir))
+        })
+        .collect::<Vec<_>>();
+    zip_dir(&dirs, zip, layout, options, error_mapper)
+}
+
+/// Walk the directories in [from], override directories of the given kinds, and zip their files to
+/// where `layout` puts them. If multiple directories have a file at the same place, the one from
+/// the last directory is used. The files are compressed in parallel.
+fn zip_dir<W, E, EF>(
+    from: &[(&str, PathBuf)],
     to: &mut ZipWriter<W>,
-    to_prefix: &str,
+    layout: &OverridesLayout,
+    options: OverridesOptions,
     error_mapper: EF,
 ) -> Result<(), E>
 where
-    F: AsRef<Path>,
     W: Write + Seek,
     EF: FnOnce(String, ZipDirError) -> E,
 {
-    fn zip_dir_impl<F: AsRef<Path>, W: Write + Seek>(
-        from: F,
+    fn zip_dir_impl<W: Write + Seek>(
+        from: &[(&str, PathBuf)],
         to: &mut ZipWriter<W>,
-        to_prefix: &str,
+        layout: &OverridesLayout,
+        options: OverridesOptions,
     ) -> Result<(), ZipDirError> {
-        let from = from.as_ref();
-        if !from.exists() {
-            log::debug!("Skipped zipping {} as it did not exist", from.display());
-            return Ok(());
-        }
-        for entry in WalkDir::new(from) {
-            let entry = entry?;
-            let ft = entry.file_type();
-            let src_path = entry.into_path();
-            let dest_path = [
-                to_prefix,
-                src_path
+        // Destination to source file, whether it's executable and a template, and its patches.
+        let mut files = BTreeMap::<String, (PathBuf, bool, bool, Vec<PathBuf>)>::new();
+        let mut non_utf8 = Vec::new();
+        let mut symlink_paths = Vec::new();
+        let mut escaping_paths = Vec::<PathBuf>::new();
+        for (kind, from) in from {
+            if !from.exists() {
+                log::debug!("Skipped zipping {} as it did not exist", from.display());
+                continue;
+            }
+            let exclusions = Exclusions::load(from, options.exclude)?;
+            // Archives can't hold symlinks, so preserving them means following them.
+            let follow_links = options.symlinks != Symlinks::Error;
+            for entry in walk_overrides(from, follow_links, &exclusions) {
+                let entry = entry?;
+                let ft = entry.file_type();
+                let is_symlink = entry.path_is_symlink() && entry.depth() > 0;
+                let followed_escaping =
+                    entry.path_is_symlink() && !options.is_contained_symlink(entry.path());
+                let src_path = entry.into_path();
+                if escaping_paths.iter().any(|p| src_path.starts_with(p)) {
+                    continue;
+                }
+                if is_symlink && options.symlinks == Symlinks::Error {
+                    symlink_paths.push(src_path.display().to_string());
+                    continue;
+                }
+                if followed_escaping {
+                    escaping_paths.push(src_path);
+                    continue;
+                }
+                if !ft.is_file() {
+                    log::debug!("Skipped {} as it is not a regular file", src_path.display());
+                    continue;
+                }
+                let relative = src_path
                     .strip_prefix(from)
-                    .expect("walked path must contain `from` as prefix")
-                    .to_str()
-                    .expect("must be zip-able path"),
-            ]
-            .join("/");
-            if ft.is_file() {
-                to.start_file(&dest_path, *ZIP_OPTIONS)?;
-                std::io::copy(&mut std::fs::File::open(&src_path)?, to)?;
-                log::debug!("Copied {} to {}", src_path.display(), dest_path);
-            } else {
-                log::debug!("Skipped {} as it is not a regular file", src_path.display());
+                    .expect("walked path must contain `from` as prefix");
+                let relative = match (relative.to_str(), options.non_utf8_paths) {
+                    (Some(relative), _) => relative.to_string(),
+                    (None, NonUtf8Paths::Error) => {
+                        non_utf8.push(src_path.display().to_string());
+                        continue;
+                    }
+                    (None, NonUtf8Paths::Skip) => {
+                        warn(Warning::new(
+                            WarningKind::SkippedFile,
+                            format!(
+                                "Skipped {} as its name isn't valid UTF-8",
+                                src_path.display()
+                            ),
+                        ));
+                        continue;
+                    }
+                    (None, NonUtf8Paths::Transliterate) => {
+                        let renamed = relative.to_string_lossy().replace('\u{FFFD}', "_");
+                        warn(Warning::new(
+                            WarningKind::Overrides,
+                            format!(
+                                "Renamed {} to {} as its name isn't valid UTF-8",
+                                src_path.display(),
+                                renamed
+                            ),
+                        ));
+                        renamed
+                    }
+                };
+                // ZIP entries always use `/`, whatever the OS.
+                let relative = relative.replace(std::path::MAIN_SEPARATOR, "/");
+                if relative.chars().count() > MAX_INSTALLED_PATH_LENGTH {
+                    warn(Warning::new(
+                        WarningKind::Overrides,
+                        format!(
+                            "{} is {} characters long, which may be too long to install on Windows",
+                            src_path.display(),
+                            relative.chars().count()
+                        ),
+                    ));
+                }
+                let Some(to_prefix) = layout.dest(options.side_of(kind, &relative)) else {
+                    left_out_override(
+                        layout.artifact,
+                        src_path.display().to_string(),
+                        layout.left_out,
+                    );
+                    continue;
+                };
+                let dest_path = [to_prefix, &relative].join("/");
+                if options.is_patch(kind, &relative) {
+                    PatchFormat::of(&src_path).map_err(|e| {
+                        ZipDirError::Patch(src_path.display().to_string(), Box::new(e))
+                    })?;
+                    // Patches are merged into the file the server would have otherwise.
+                    let base = files.get(&dest_path).or_else(|| {
+                        let both = layout.dest(OverrideSide::Both)?;
+                        files.get(&[both, &relative].join("/"))
+                    });
+                    if let Some((base_path, executable, template, patches)) = base {
+                        let mut patches = patches.clone();
+                        patches.push(src_path);
+                        let entry = (base_path.clone(), *executable, *template, patches);
+                        files.insert(dest_path, entry);
+                        continue;
+                    }
+                }
+                let executable = options.is_executable(&relative);
+                let template = options.is_template(&relative);
+                files.insert(dest_path, (src_path, executable, template, Vec::new()));
             }
         }
+        if !symlink_paths.is_empty() {
+            return Err(ZipDirError::Symlinks(symlink_paths));
+        }
+        if !escaping_paths.is_empty() {
+            return Err(ZipDirError::EscapingSymlinks(
+                escaping_paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect(),
+            ));
+        }
+        if !non_utf8.is_empty() {
+            return Err(ZipDirError::NonUtf8Paths(non_utf8));
+        }
+        for_each_parallel(
+            files.into_iter().collect(),
+            |(dest_path, (src_path, executable, template, patches))| {
+                let mut content = template
+                    .then(|| options.expand_template(&src_path))
+                    .transpose()?;
+                if !patches.is_empty() {
+                    let mut merged = match content {
+                        Some(content) => content,
+                        None => std::fs::read(&src_path)?,
+                    };
+                    for patch in patches {
+                        merged = apply_patch(&merged, &patch).map_err(|e| {
+                            ZipDirError::Patch(patch.display().to_string(), Box::new(e))
+                        })?;
+                    }
+                    content = Some(merged);
+                }
+                let compressed = compress_file(&dest_path, &src_path, executable, content)?;
+                Ok((dest_path, src_path, compressed))
+            },
+            |(dest_path, src_path, mut compressed)| {
+                to.raw_copy_file(compressed.by_index_raw(0)?)?;
+                log::debug!("Copied {} to {}", src_path.display(), dest_path);
+                Ok(())
+            },
+        )
+    }
 
-        Ok(())
+    /// Compress a file on its own, to be copied into the archive as is. Its `content` replaces
+    /// that of the file if given, e.g. for templates.
+    fn compress_file(
+        dest_path: &str,
+        src_path: &Path,
+        executable: bool,
+        content: Option<Vec<u8>>,
+    ) -> Result<ZipArchive<Cursor<Vec<u8>>>, ZipDirError> {
+        let file = std::fs::File::open(src_path)?;
+        let mode = override_permissions(&file.metadata()?, executable);
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(dest_path, ZIP_OPTIONS.unix_permissions(mode))?;
+        match content {
+            Some(content) => zip.write_all(&content)?,
+            None => {
+                std::io::copy(&mut &file, &mut zip)?;
+            }
+        }
+        Ok(ZipArchive::new(zip.finish()?)?)
     }
 
-    let from = from.as_ref();
-    tokio::task::block_in_place(|| zip_dir_impl(from, to, to_prefix))
-        .map_err(|e| error_mapper(from.display().to_string(), e))
+    tokio::task::block_in_place(|| zip_dir_impl(from, to, layout, options)).map_err(|e| {
+        error_mapper(
+            from.iter()
+                .map(|(_, dir)| dir.display().to_string())
+                .join(", "),
+            e,
+        )
+    })
 }
 
 #[derive(Debug, Error)]
@@ -514,8 +1674,11 @@ pub enum ZipModError {
 }
 
 async fn add_mod_to_zip<S: ModSite, W>(
+    site: S,
+    cfg_id: String,
     mod_: VerifiedMod<S>,
-    dest_overrides: &'static str,
+    artifact: &'static str,
+    dest_overrides: String,
     zip: Arc<Mutex<ZipWriter<W>>>,
 ) -> Result<(), ZipModError>
 where
@@ -523,23 +1686,35 @@ where
 {
     let mod_info = mod_.info;
 
+    // Finish the download before starting the entry, so a failed download can't leave a truncated
+    // entry behind, and other mods can be written meanwhile. Large mods are read from disk.
+    let mut content = cached_mod_download(site, &mod_info).await?;
+
     let mut zip = zip.lock().await;
     zip.start_file(
-        [dest_overrides, LIT_MODS, &mod_info.filename].join("/"),
+        [dest_overrides.as_str(), LIT_MODS, &mod_info.filename].join("/"),
         *ZIP_OPTIONS,
     )?;
-
-    let mut content = mod_download(mod_info.url).await?;
-    tokio::task::block_in_place(|| {
-        std::io::copy(&mut SyncIoBridge::new(&mut content), zip.deref_mut())
-    })?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut written = 0;
+    loop {
+        let read = content.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        tokio::task::block_in_place(|| zip.write_all(&buffer[..read]))?;
+        written += read as u64;
+    }
+    check_size(&mod_info, written)?;
     drop(zip);
 
-    log::info!(
-        "[{}] Mod {} downloaded.",
-        S::NAME.errstyle(SITE_NAME_STYLE),
-        mod_info.filename.errstyle(FILE_STYLE),
-    );
+    emit(ProgressEvent::FileCopied {
+        artifact,
+        site: S::CONFIG_KEY,
+        key: cfg_id,
+        file: mod_info.filename,
+        already_present: false,
+    });
 
     Ok(())
 }
@@ -1,22 +1,37 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-use futures::TryStreamExt;
-use itertools::Itertools;
+use clap::ValueEnum;
+use digest::Digest;
+use futures::StreamExt;
 use once_cell::sync::Lazy;
+use reqwest::header::{CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use reqwest::StatusCode;
+use serde::{Serialize, Serializer};
+use tempfile::TempPath;
 use thiserror::Error;
-use tokio::io::AsyncRead;
-use tokio::sync::Semaphore;
+use tokio::io::{AsyncRead, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::{Mutex, Semaphore};
 use tokio::task::JoinHandle;
-use tokio_util::compat::FuturesAsyncReadCompatExt;
 
-use crate::checks::verify_mods::{KnownEnvRequirements, VerifiedMod, VerifiedModContainer};
+use crate::cache;
+use crate::checks::install_plan::{ArtifactPlan, PlannedFile, Side};
+use crate::checks::verify_mods::{VerifiedMod, VerifiedModContainer};
+use crate::config::global::{RETRY_POLICY, SPOOL_THRESHOLD};
 use crate::config::pack::PackConfig;
-use crate::mod_site::{ModHash, ModLoadingError, ModSite};
-use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, FILE_STYLE, SITE_NAME_STYLE};
+use crate::json_report::{failure_reports, FailureReport};
+use crate::mod_site::{
+    hex_to_hash_output, CurseForge, ModFileInfo, ModHash, ModLoadingError, ModSite, Modrinth,
+};
+use crate::output::SERVER_BASE;
+use crate::progress::{emit, ProgressEvent};
+use crate::units;
 
 #[derive(Debug, Error)]
 pub enum ModDownloadToFileError {
@@ -28,47 +43,86 @@ pub enum ModDownloadToFileError {
     ModDownload(#[from] ModDownloadError),
 }
 
+impl ModDownloadToFileError {
+    /// A stable identifier for the kind of error, for machine-readable output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ModDownloadToFileError::Io(_) => "io",
+            ModDownloadToFileError::ModLoading(_) => "loading",
+            ModDownloadToFileError::ModDownload(_) => "download",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ModsDownloadError {
-    pub failures: HashMap<String, ModDownloadToFileError>,
+    /// Failures by site config key (e.g. `modrinth`) and mod config key.
+    pub failures: HashMap<(&'static str, String), ModDownloadToFileError>,
 }
 
 impl Error for ModsDownloadError {}
 
+impl ModsDownloadError {
+    pub fn failure_reports(&self) -> Vec<FailureReport> {
+        failure_reports(&self.failures, ModDownloadToFileError::code)
+    }
+}
+
+impl Serialize for ModsDownloadError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.failure_reports().serialize(serializer)
+    }
+}
+
 impl Display for ModsDownloadError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut failures_vec = self.failures.iter().collect::<Vec<_>>();
         failures_vec.sort_by_key(|(k, _)| (*k).clone());
-        for (k, error) in failures_vec {
-            writeln!(f, "Mod {}: {}", k, error)?;
+        for ((site, k), error) in failures_vec {
+            writeln!(f, "[{}] Mod {}: {}", site, k, error)?;
         }
 
         Ok(())
     }
 }
 
-pub(crate) async fn download_mods<F>(
+/// How mod files are put into a server base from the download cache.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum LinkMode {
+    /// Reflink the cached file, so it shares its content until either copy is changed. Files are
+    /// copied on filesystems that don't support reflinks.
+    #[default]
+    Reflink,
+    /// Hardlink the cached file, so it takes no extra space. Files are copied if the cache is on
+    /// another filesystem.
+    Hardlink,
+    /// Always copy the cached file.
+    Copy,
+}
+
+/// Download the server files of the mods that the plan installs on the server into `dest_dir`.
+pub(crate) async fn download_mods(
     pack_config: &PackConfig<VerifiedModContainer>,
+    plan: ArtifactPlan<'_>,
     dest_dir: &Path,
-    side_test: F,
-) -> Result<(), ModsDownloadError>
-where
-    F: FnMut(KnownEnvRequirements) -> bool + Clone,
-{
-    let mut failures = HashMap::<String, ModDownloadToFileError>::new();
+    link_mode: LinkMode,
+) -> Result<(), ModsDownloadError> {
+    let mut failures = HashMap::new();
 
     download_from_site(
+        CurseForge,
         dest_dir,
+        link_mode,
         &mut failures,
-        &pack_config.mods.curseforge,
-        side_test.clone(),
+        plan.mods(Side::Server, &pack_config.mods.curseforge),
     )
     .await;
     download_from_site(
+        Modrinth,
         dest_dir,
+        link_mode,
         &mut failures,
-        &pack_config.mods.modrinth,
-        side_test,
+        plan.mods(Side::Server, &pack_config.mods.modrinth),
     )
     .await;
 
@@ -79,32 +133,40 @@ where
     Ok(())
 }
 
-async fn download_from_site<S, F>(
+async fn download_from_site<'a, S: ModSite>(
+    site: S,
     dest_dir: &Path,
-    failures: &mut HashMap<String, ModDownloadToFileError>,
-    mods: &HashMap<String, VerifiedMod<S>>,
-    mut side_test: F,
-) where
-    F: FnMut(KnownEnvRequirements) -> bool,
-    S: ModSite,
-{
+    link_mode: LinkMode,
+    failures: &mut HashMap<(&'static str, String), ModDownloadToFileError>,
+    mods: impl Iterator<Item = (&'a PlannedFile, &'a VerifiedMod<S>)>,
+) {
     let downloads = mods
-        .iter()
-        .filter(|(_, m)| side_test(m.env_requirements))
-        .sorted_by_key(|(k, _)| k.as_str())
-        .map(|(k, m)| (k.clone(), submit_download(k.clone(), m.clone(), dest_dir)))
+        .map(|(f, m)| {
+            (
+                f.key.clone(),
+                submit_download(
+                    site,
+                    f.key.clone(),
+                    m.server_file().clone(),
+                    dest_dir,
+                    link_mode,
+                ),
+            )
+        })
         .collect::<Vec<_>>();
     for (cfg_id, dl_ftr) in downloads {
         if let Err(e) = dl_ftr.await.expect("tokio failure") {
-            failures.insert(cfg_id.clone(), e);
+            failures.insert((S::CONFIG_KEY, cfg_id), e);
         }
     }
 }
 
 fn submit_download<S>(
+    site: S,
     cfg_id: String,
-    mod_: VerifiedMod<S>,
+    mod_info: ModFileInfo<S::Id, S::ModHash>,
     dest_dir: &Path,
+    link_mode: LinkMode,
 ) -> JoinHandle<Result<PathBuf, ModDownloadToFileError>>
 where
     S: ModSite,
@@ -114,43 +176,96 @@ where
     let dest_dir = dest_dir.to_owned();
     tokio::task::spawn(async move {
         let _guard = CONCURRENCY_LIMITER.acquire().await.expect("tokio failure");
-        let mod_info = mod_.info;
         let dest_file = dest_dir.join(&mod_info.filename);
-        if dest_file.exists() {
+        if dest_file.exists() && !is_refreshed(&mod_info.url) {
             // Check if we already have the file.
             let content = tokio::fs::read(&dest_file).await?;
             if mod_info
                 .hash
                 .check_hash_if_possible(&content)
                 .is_some_and(|valid| valid)
+                && check_size(&mod_info, content.len() as u64).is_ok()
             {
-                log::info!(
-                    "[{}] Found cached {} for {}",
-                    S::NAME.errstyle(SITE_NAME_STYLE),
-                    mod_info.filename.errstyle(FILE_STYLE),
-                    cfg_id.errstyle(CONFIG_VAL_STYLE),
-                );
+                emit(ProgressEvent::FileCopied {
+                    artifact: SERVER_BASE,
+                    site: S::CONFIG_KEY,
+                    key: cfg_id,
+                    file: mod_info.filename,
+                    already_present: true,
+                });
                 return Ok(dest_file);
             }
         }
 
-        tokio::io::copy(
-            &mut mod_download(mod_info.url).await?,
-            &mut tokio::fs::File::create(&dest_file).await?,
-        )
-        .await?;
+        let mut content = cached_mod_download(site, &mod_info).await?;
+        // Never write through an existing file, it may be a hardlink into the cache.
+        if dest_file.exists() {
+            tokio::fs::remove_file(&dest_file).await?;
+        }
+        let cached_file = known_sha256(&mod_info)
+            .map(|h| cache::download_path(&format!("{:x}", h)))
+            .filter(|p| p.exists());
+        let linked = cached_file.is_some_and(|cached_file| {
+            tokio::task::block_in_place(|| link_file(&cached_file, &dest_file, link_mode))
+        });
+        let written = if linked {
+            tokio::fs::metadata(&dest_file).await?.len()
+        } else {
+            tokio::io::copy(
+                &mut content,
+                &mut tokio::fs::File::create(&dest_file).await?,
+            )
+            .await?
+        };
+        if let Err(e) = check_size(&mod_info, written) {
+            // Don't leave a truncated file behind for the next run.
+            tokio::fs::remove_file(&dest_file).await?;
+            return Err(e.into());
+        }
 
-        log::info!(
-            "[{}] Downloaded {} for {}",
-            S::NAME.errstyle(SITE_NAME_STYLE),
-            mod_info.filename.errstyle(FILE_STYLE),
-            cfg_id.errstyle(CONFIG_VAL_STYLE),
-        );
+        emit(ProgressEvent::FileCopied {
+            artifact: SERVER_BASE,
+            site: S::CONFIG_KEY,
+            key: cfg_id,
+            file: mod_info.filename,
+            already_present: false,
+        });
 
         Ok(dest_file)
     })
 }
 
+/// Link a cached download to `dest_file` as `link_mode` asks. Returns `false` if it must be copied
+/// instead.
+fn link_file(cached_file: &Path, dest_file: &Path, link_mode: LinkMode) -> bool {
+    let (verb, result) = match link_mode {
+        LinkMode::Reflink => ("reflink", reflink::reflink(cached_file, dest_file)),
+        LinkMode::Hardlink => ("hardlink", std::fs::hard_link(cached_file, dest_file)),
+        LinkMode::Copy => return false,
+    };
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            // Reflinks are best-effort, but a failed hardlink defeats the point of asking for it.
+            let level = match link_mode {
+                LinkMode::Hardlink => log::Level::Warn,
+                _ => log::Level::Debug,
+            };
+            log::log!(
+                level,
+                "Couldn't {} {} to {}, copying instead: {}",
+                verb,
+                cached_file.display(),
+                dest_file.display(),
+                e
+            );
+            // A failed reflink may leave an empty file behind.
+            let _ = std::fs::remove_file(dest_file);
+            false
+        }
+    }
+}
+
 type BoxAsyncRead = Pin<Box<dyn AsyncRead + Send + Sync>>;
 
 #[derive(Debug, Error)]
@@ -159,14 +274,501 @@ pub enum ModDownloadError {
     Io(#[from] std::io::Error),
     #[error("Reqwest Error: {0}")]
     Reqwest(#[from] reqwest::Error),
+    #[error("The content downloaded from {0} does not match the expected hash")]
+    HashMismatch(String),
+    #[error("The content downloaded from {url} is {actual} bytes, but {expected} were expected")]
+    SizeMismatch {
+        url: String,
+        expected: u64,
+        actual: u64,
+    },
+    #[error("The content downloaded from {0} is not a mod file: {1}")]
+    InvalidContent(String, String),
+}
+
+/// Check the size of a downloaded mod against the size the site reported, to catch truncated
+/// downloads, even of files without a usable hash. Sites report 0 if they don't know the size.
+pub fn check_size<K, H>(mod_info: &ModFileInfo<K, H>, actual: u64) -> Result<(), ModDownloadError> {
+    if mod_info.file_length != 0 && actual != mod_info.file_length {
+        return Err(ModDownloadError::SizeMismatch {
+            url: mod_info.url.clone(),
+            expected: mod_info.file_length,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Whether every download of this run bypasses the download cache.
+static REFRESH_ALL: AtomicBool = AtomicBool::new(false);
+/// URLs of the downloads of this run that bypass the download cache.
+static REFRESHED_URLS: Lazy<std::sync::Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+/// Download every mod again instead of taking it from the download cache.
+pub fn refresh_all_downloads() {
+    REFRESH_ALL.store(true, Ordering::Relaxed);
+}
+
+/// Download the files at these URLs again instead of taking them from the download cache. The
+/// new downloads replace the cached files.
+pub fn refresh_downloads(urls: impl IntoIterator<Item = String>) {
+    REFRESHED_URLS
+        .lock()
+        .expect("refreshed URLs poisoned")
+        .extend(urls);
+}
+
+/// Whether any download of this run bypasses the download cache.
+pub fn is_refreshing() -> bool {
+    REFRESH_ALL.load(Ordering::Relaxed)
+        || !REFRESHED_URLS
+            .lock()
+            .expect("refreshed URLs poisoned")
+            .is_empty()
+}
+
+fn is_refreshed(url: &str) -> bool {
+    REFRESH_ALL.load(Ordering::Relaxed)
+        || REFRESHED_URLS
+            .lock()
+            .expect("refreshed URLs poisoned")
+            .contains(url)
 }
 
-pub async fn mod_download(url: String) -> Result<BoxAsyncRead, ModDownloadError> {
-    let req = reqwest::get(url).await?.error_for_status()?;
-    Ok(Box::pin(
-        req.bytes_stream()
-            .map_err(|e| futures::io::Error::new(futures::io::ErrorKind::Other, e))
-            .into_async_read()
-            .compat(),
-    ))
+/// The content of a download of this run. Large files are spooled to disk, see
+/// [SPOOL_THRESHOLD].
+enum RunContent {
+    Memory(Arc<[u8]>),
+    Spooled(Arc<TempPath>),
+}
+
+/// A download of this run. Its content is only kept if it couldn't be stored in the download
+/// cache, otherwise it's read from there.
+type RunDownload = Arc<Mutex<Option<RunContent>>>;
+
+/// Downloads of this run by URL, so every output of a run shares them.
+static RUN_DOWNLOADS: Lazy<std::sync::Mutex<HashMap<String, RunDownload>>> =
+    Lazy::new(Default::default);
+
+/// Downloads that were needed again by another output of this run or found in the download cache,
+/// instead of being downloaded again.
+#[derive(Debug, Default, Copy, Clone)]
+struct ReusedDownloads {
+    shared_files: u64,
+    shared_bytes: u64,
+    cached_files: u64,
+    cached_bytes: u64,
+}
+
+static REUSED_DOWNLOADS: std::sync::Mutex<ReusedDownloads> =
+    std::sync::Mutex::new(ReusedDownloads {
+        shared_files: 0,
+        shared_bytes: 0,
+        cached_files: 0,
+        cached_bytes: 0,
+    });
+
+/// The URLs downloaded in this run, to tell files shared between outputs from ones that were
+/// cached by an earlier run.
+static DOWNLOADED_URLS: Lazy<std::sync::Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+fn record_reuse(url: &str, bytes: u64) {
+    let shared = DOWNLOADED_URLS
+        .lock()
+        .expect("downloaded URLs poisoned")
+        .contains(url);
+    let mut reused = REUSED_DOWNLOADS.lock().expect("reused downloads poisoned");
+    if shared {
+        reused.shared_files += 1;
+        reused.shared_bytes += bytes;
+    } else {
+        reused.cached_files += 1;
+        reused.cached_bytes += bytes;
+    }
+}
+
+/// Log how many mod files were shared between the outputs of this run and taken from the
+/// download cache, rather than downloaded again.
+pub fn log_reused_downloads() {
+    let reused = *REUSED_DOWNLOADS.lock().expect("reused downloads poisoned");
+    if reused.shared_files == 0 && reused.cached_files == 0 {
+        return;
+    }
+    log::info!(
+        "Saved downloading {}: {} ({}) needed by more than one output, and {} ({}) in the download cache.",
+        units::size(reused.shared_bytes + reused.cached_bytes),
+        units::count(reused.shared_files, "mod file"),
+        units::size(reused.shared_bytes),
+        units::count(reused.cached_files, "mod file"),
+        units::size(reused.cached_bytes),
+    );
+}
+
+/// Delete the downloads of this run that were spooled to disk, but not stored in the cache.
+pub fn remove_spooled_downloads() {
+    RUN_DOWNLOADS
+        .lock()
+        .expect("run downloads poisoned")
+        .clear();
+}
+
+/// Download a mod through the download cache, which stores files by their SHA-256 hash. Only
+/// mods the site reports a hash for are cached.
+/// Downloads that don't match the known hashes, or don't look like the expected file, are rejected.
+/// Each file is downloaded at most once per run, even if multiple outputs need it at once.
+pub async fn cached_mod_download<S: ModSite>(
+    site: S,
+    mod_info: &ModFileInfo<S::Id, S::ModHash>,
+) -> Result<BoxAsyncRead, ModDownloadError> {
+    let url = &mod_info.url;
+    let run_download = RUN_DOWNLOADS
+        .lock()
+        .expect("run downloads poisoned")
+        .entry(url.clone())
+        .or_default()
+        .clone();
+    // Wait for other downloads of the same file, then use their result.
+    let mut run_download = run_download.lock().await;
+    match &*run_download {
+        Some(RunContent::Memory(content)) => {
+            check_size(mod_info, content.len() as u64)?;
+            log::debug!("Using earlier download of {}", url);
+            record_reuse(url, content.len() as u64);
+            return Ok(Box::pin(std::io::Cursor::new(content.to_vec())));
+        }
+        Some(RunContent::Spooled(path)) => {
+            let file = tokio::fs::File::open(path.as_ref()).await?;
+            let size = file.metadata().await?.len();
+            check_size(mod_info, size)?;
+            log::debug!("Using earlier download of {}", url);
+            record_reuse(url, size);
+            return Ok(Box::pin(file));
+        }
+        None => {}
+    }
+
+    let hash = &mod_info.hash;
+    let site_hash = hash.site_hash_key();
+    let sha256 = hash.sha256().map(|h| format!("{:x}", h)).or_else(|| {
+        let site_hash = site_hash.as_deref()?;
+        tokio::task::block_in_place(|| cache::download_sha256(site_hash))
+    });
+    let sha256 = sha256.filter(|_| !is_refreshed(url));
+    if let Some(sha256) = sha256 {
+        if mod_info.file_length >= *SPOOL_THRESHOLD {
+            if let Some(path) = tokio::task::block_in_place(|| cache::download_file(&sha256)) {
+                let valid = tokio::task::block_in_place(|| check_file(&path, &sha256, hash));
+                let size = tokio::fs::metadata(&path).await?.len();
+                if valid.is_ok_and(|v| v) && check_size(mod_info, size).is_ok() {
+                    log::debug!("Using cached download of {}", url);
+                    record_reuse(url, size);
+                    record_sha256(url, sha256);
+                    return Ok(Box::pin(tokio::fs::File::open(path).await?));
+                }
+                log::debug!("Cached download of {} is corrupt, downloading again", url);
+            }
+        } else if let Some(content) = tokio::task::block_in_place(|| cache::load_download(&sha256))
+        {
+            if format!("{:x}", sha2::Sha256::digest(&content)) == sha256
+                && hash.check_hash_if_possible(&content) != Some(false)
+                && check_size(mod_info, content.len() as u64).is_ok()
+            {
+                log::debug!("Using cached download of {}", url);
+                record_reuse(url, content.len() as u64);
+                record_sha256(url, sha256);
+                return Ok(Box::pin(std::io::Cursor::new(content)));
+            }
+            log::debug!("Cached download of {} is corrupt, downloading again", url);
+        }
+    }
+
+    let content = download_mod_file(site, mod_info).await?;
+    DOWNLOADED_URLS
+        .lock()
+        .expect("downloaded URLs poisoned")
+        .insert(url.clone());
+    let content = match content {
+        DownloadContent::Memory(content) => content,
+        DownloadContent::Spooled(path) => {
+            let (sha256, valid) = tokio::task::block_in_place(|| {
+                Ok::<_, std::io::Error>((
+                    file_sha256(&path)?,
+                    hash.check_reader_hash_if_possible(&mut std::fs::File::open(&path)?)?,
+                ))
+            })?;
+            if valid == Some(false) {
+                return Err(ModDownloadError::HashMismatch(url.clone()));
+            }
+            record_sha256(url, sha256.clone());
+            if let (Some(true), Some(site_hash)) = (valid, &site_hash) {
+                match tokio::task::block_in_place(|| {
+                    cache::store_download_file(site_hash, &sha256, &path)
+                }) {
+                    Ok(()) => {
+                        let cached = cache::download_path(&sha256);
+                        return Ok(Box::pin(tokio::fs::File::open(cached).await?));
+                    }
+                    Err(e) => log::warn!("Failed to cache download of {}: {}", url, e),
+                }
+            }
+            let file = tokio::fs::File::open(&path).await?;
+            *run_download = Some(RunContent::Spooled(Arc::new(path)));
+            return Ok(Box::pin(file));
+        }
+    };
+    let sha256 = format!("{:x}", sha2::Sha256::digest(&content));
+    let mut cached = false;
+    match hash.check_hash_if_possible(&content) {
+        Some(false) => return Err(ModDownloadError::HashMismatch(url.clone())),
+        Some(true) => {
+            if let Some(site_hash) = &site_hash {
+                match tokio::task::block_in_place(|| {
+                    cache::store_download(site_hash, &sha256, &content)
+                }) {
+                    Ok(()) => cached = true,
+                    Err(e) => log::warn!("Failed to cache download of {}: {}", url, e),
+                }
+            }
+        }
+        None => {}
+    }
+    record_sha256(url, sha256);
+    if !cached {
+        *run_download = Some(RunContent::Memory(content.as_slice().into()));
+    }
+    Ok(Box::pin(std::io::Cursor::new(content)))
+}
+
+/// Check a cached file against its hex SHA-256 hash and the hash its site reports, reading it in
+/// chunks.
+fn check_file<H: ModHash>(path: &Path, sha256: &str, hash: &H) -> std::io::Result<bool> {
+    Ok(file_sha256(path)? == sha256
+        && hash.check_reader_hash_if_possible(&mut std::fs::File::open(path)?)? != Some(false))
+}
+
+/// The hex SHA-256 hash of a file, reading it in chunks.
+pub(crate) fn file_sha256(path: &Path) -> std::io::Result<String> {
+    let mut hasher = sha2::Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// SHA-256 hashes of the downloads of this run, by URL.
+static RUN_SHA256: Lazy<std::sync::Mutex<HashMap<String, String>>> = Lazy::new(Default::default);
+
+fn record_sha256(url: &str, sha256: String) {
+    RUN_SHA256
+        .lock()
+        .expect("run hashes poisoned")
+        .insert(url.to_string(), sha256);
+}
+
+/// The SHA-256 hash of a mod file, if it's known, or the file was downloaded in this run or is
+/// in the download cache.
+pub fn known_sha256<K, H: ModHash>(
+    mod_info: &ModFileInfo<K, H>,
+) -> Option<digest::Output<sha2::Sha256>> {
+    if let Some(sha256) = mod_info.hash.sha256() {
+        return Some(*sha256);
+    }
+    let run_sha256 = RUN_SHA256
+        .lock()
+        .expect("run hashes poisoned")
+        .get(&mod_info.url)
+        .cloned();
+    let sha256 = run_sha256.or_else(|| cache::download_sha256(&mod_info.hash.site_hash_key()?))?;
+    hex_to_hash_output::<sha2::Sha256>(&sha256)
+}
+
+/// Fill in the SHA-256 hashes of the files of the mods that are known, see [known_sha256].
+pub fn fill_known_sha256(mods: &mut VerifiedModContainer) {
+    fn fill<S: ModSite>(mods: &mut HashMap<String, VerifiedMod<S>>) {
+        for m in mods.values_mut() {
+            for info in [Some(&mut m.info), m.server_info.as_mut()]
+                .into_iter()
+                .flatten()
+            {
+                if let Some(sha256) = known_sha256(info) {
+                    info.hash.set_sha256(sha256);
+                }
+            }
+        }
+    }
+    fill(&mut mods.curseforge);
+    fill(&mut mods.modrinth);
+}
+
+/// How many bytes to download between progress events.
+const PROGRESS_INTERVAL: u64 = 256 * 1024;
+
+/// Header of a ZIP file, which includes JARs.
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// A completed download, in memory or spooled to a temporary file.
+enum DownloadContent {
+    Memory(Vec<u8>),
+    Spooled(TempPath),
+}
+
+/// Where a download is written to while it's received.
+enum Spool {
+    Memory(Vec<u8>),
+    File {
+        file: tokio::fs::File,
+        path: TempPath,
+        len: u64,
+    },
+}
+
+impl Spool {
+    /// Spool to a temporary file if the download is expected to be at least [SPOOL_THRESHOLD].
+    fn new(expected_size: u64) -> std::io::Result<Self> {
+        if expected_size < *SPOOL_THRESHOLD {
+            return Ok(Spool::Memory(Vec::with_capacity(expected_size as usize)));
+        }
+        let (file, path) = tokio::task::block_in_place(cache::spool_file)?.into_parts();
+        Ok(Spool::File {
+            file: tokio::fs::File::from_std(file),
+            path,
+            len: 0,
+        })
+    }
+
+    fn received(&self) -> u64 {
+        match self {
+            Spool::Memory(content) => content.len() as u64,
+            Spool::File { len, .. } => *len,
+        }
+    }
+
+    async fn write(&mut self, chunk: &[u8]) -> std::io::Result<()> {
+        match self {
+            Spool::Memory(content) => content.extend_from_slice(chunk),
+            Spool::File { file, len, .. } => {
+                file.write_all(chunk).await?;
+                *len += chunk.len() as u64;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop what was received, to start over.
+    async fn clear(&mut self) -> std::io::Result<()> {
+        match self {
+            Spool::Memory(content) => content.clear(),
+            Spool::File { file, len, .. } => {
+                file.set_len(0).await?;
+                file.rewind().await?;
+                *len = 0;
+            }
+        }
+        Ok(())
+    }
+
+    async fn finish(self) -> std::io::Result<DownloadContent> {
+        match self {
+            Spool::Memory(content) => Ok(DownloadContent::Memory(content)),
+            Spool::File { mut file, path, .. } => {
+                file.flush().await?;
+                Ok(DownloadContent::Spooled(path))
+            }
+        }
+    }
+}
+
+impl DownloadContent {
+    fn starts_with(&self, prefix: &[u8]) -> std::io::Result<bool> {
+        match self {
+            DownloadContent::Memory(content) => Ok(content.starts_with(prefix)),
+            DownloadContent::Spooled(path) => {
+                let mut start = Vec::with_capacity(prefix.len());
+                std::fs::File::open(path)?
+                    .take(prefix.len() as u64)
+                    .read_to_end(&mut start)?;
+                Ok(start == prefix)
+            }
+        }
+    }
+}
+
+/// Download a mod file with the authentication its site needs, checking that it looks like the
+/// file the site described. This catches e.g. a CDN error page served with a success status, even
+/// when there's no hash to check. Large files are spooled to disk, see [SPOOL_THRESHOLD].
+async fn download_mod_file<S: ModSite>(
+    site: S,
+    mod_info: &ModFileInfo<S::Id, S::ModHash>,
+) -> Result<DownloadContent, ModDownloadError> {
+    let url = &mod_info.url;
+    let invalid = |reason: String| ModDownloadError::InvalidContent(url.clone(), reason);
+
+    // What was received before a download failed, so the retry only requests the rest.
+    let received = Mutex::new(Spool::new(mod_info.file_length)?);
+    let (content_type, content_length) = RETRY_POLICY
+        .run(|| async {
+            let mut received = received.lock().await;
+            let resume_from = received.received();
+            let mut request = site.download_request(url);
+            if resume_from > 0 {
+                request = request.header(RANGE, format!("bytes={}-", resume_from));
+            }
+            let response = request.send().await?.error_for_status()?;
+            let resumed = response.status() == StatusCode::PARTIAL_CONTENT
+                && response
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|v| v.starts_with(&format!("bytes {}-", resume_from)));
+            if resume_from > 0 {
+                if resumed {
+                    log::debug!("Resuming download of {} at {} bytes", url, resume_from);
+                } else {
+                    // The server sent the whole file again.
+                    received.clear().await?;
+                }
+            }
+            let offset = if resumed { resume_from } else { 0 };
+            let content_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let content_length = response.content_length().map(|l| l + offset);
+            let mut reported = offset;
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                received.write(&chunk?).await?;
+                if received.received() - reported >= PROGRESS_INTERVAL {
+                    reported = received.received();
+                    emit(ProgressEvent::DownloadProgress {
+                        url: url.clone(),
+                        downloaded: reported,
+                        total: content_length,
+                    });
+                }
+            }
+            Ok::<_, ModDownloadError>((content_type, content_length))
+        })
+        .await?;
+    let received = received.into_inner();
+    let size = received.received();
+    let content = received.finish().await?;
+    emit(ProgressEvent::DownloadCompleted {
+        url: url.clone(),
+        bytes: size,
+    });
+
+    if let Some(content_type) = content_type.filter(|t| t.starts_with("text/")) {
+        return Err(invalid(format!("got content type {}", content_type)));
+    }
+    for length in content_length.into_iter().chain([size]) {
+        check_size(mod_info, length)?;
+    }
+    let filename = mod_info.filename.to_ascii_lowercase();
+    if (filename.ends_with(".jar") || filename.ends_with(".zip"))
+        && !tokio::task::block_in_place(|| content.starts_with(ZIP_MAGIC))?
+    {
+        return Err(invalid("not a ZIP or JAR file".to_string()));
+    }
+
+    Ok(content)
 }
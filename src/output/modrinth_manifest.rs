@@ -1,8 +1,8 @@
 use crate::checks::verify_mods::KnownEnvRequirements;
 use crate::config::mods::KnownEnvRequirement;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ModrinthManifest {
     pub format_version: u32,
@@ -14,13 +14,13 @@ pub struct ModrinthManifest {
     pub dependencies: GameDependencies,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum Game {
     Minecraft,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ModFile {
     pub path: String,
@@ -28,16 +28,23 @@ pub struct ModFile {
     pub env: Option<Environment>,
     pub downloads: Vec<String>,
     pub file_size: u64,
+    /// The name launchers show for an optional file, instead of its filename. Not part of the
+    /// format, but read by launchers that let players choose optional files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The description launchers show for an optional file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ModFileHashes {
     pub sha1: String,
     pub sha512: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Environment {
     pub client: KnownEnvRequirement,
@@ -53,7 +60,29 @@ impl From<KnownEnvRequirements> for Environment {
     }
 }
 
-#[derive(Debug, Serialize)]
+/// The file in the root of Modrinth packs generated by netherfire that records the CurseForge mods
+/// embedded in the overrides, whose sides the manifest can't describe.
+pub const EMBEDDED_MANIFEST: &str = "netherfire-embedded.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedManifest {
+    pub files: Vec<EmbeddedFile>,
+}
+
+/// A CurseForge mod embedded in the overrides of a Modrinth pack.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedFile {
+    /// The path of the file in the pack, e.g. `client-overrides/mods/foo.jar`.
+    pub path: String,
+    pub project_id: i32,
+    pub file_id: i32,
+    /// The sides the mod is intended for, including whether it's optional.
+    pub env: Environment,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct GameDependencies {
     pub minecraft: String,
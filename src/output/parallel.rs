@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc::sync_channel;
+use std::sync::Mutex;
+
+/// The most threads used for processing files in parallel.
+const MAX_THREADS: usize = 8;
+
+/// Run `work` on every item on a bounded number of threads, and pass the results to `consume` in
+/// the order of the items, as soon as they're available. Stops at the first error.
+pub fn for_each_parallel<T, R, E, W, C>(items: Vec<T>, work: W, mut consume: C) -> Result<(), E>
+where
+    T: Send,
+    R: Send,
+    E: Send,
+    W: Fn(T) -> Result<R, E> + Sync,
+    C: FnMut(R) -> Result<(), E>,
+{
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_THREADS)
+        .min(items.len());
+    if threads <= 1 {
+        for item in items {
+            consume(work(item)?)?;
+        }
+        return Ok(());
+    }
+
+    let queue = Mutex::new(items.into_iter().enumerate());
+    std::thread::scope(|scope| {
+        // Bound the results waiting to be consumed, so they don't pile up in memory.
+        let (sender, receiver) = sync_channel(threads * 2);
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let (queue, work) = (&queue, &work);
+            scope.spawn(move || loop {
+                let Some((index, item)) = queue.lock().expect("queue poisoned").next() else {
+                    break;
+                };
+                // The receiver is gone if consuming failed, then there's nothing left to do.
+                if sender.send((index, work(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&next) {
+                consume(result?)?;
+                next += 1;
+            }
+        }
+        Ok(())
+    })
+}
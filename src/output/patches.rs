@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use thiserror::Error;
+use toml_edit::{Document, TableLike};
+
+#[derive(Debug, Error)]
+pub enum PatchError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Patches must be .toml, .json or .properties files")]
+    UnknownFormat,
+    #[error("Patched files must be UTF-8 text: {0}")]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml_edit::TomlError),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The formats patches can be merged in, by file extension.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum PatchFormat {
+    Toml,
+    Json,
+    Properties,
+}
+
+impl PatchFormat {
+    pub(crate) fn of(path: &Path) -> Result<Self, PatchError> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Ok(Self::Toml),
+            Some("json") => Ok(Self::Json),
+            Some("properties") => Ok(Self::Properties),
+            _ => Err(PatchError::UnknownFormat),
+        }
+    }
+}
+
+/// Merge the patch at `patch_path` into `base`, the content of the file it patches. Values in the
+/// patch replace those in the file, and tables or objects are merged recursively, so everything
+/// the patch doesn't mention is left as it is. TOML files keep their comments and formatting.
+pub(crate) fn apply_patch(base: &[u8], patch_path: &Path) -> Result<Vec<u8>, PatchError> {
+    let format = PatchFormat::of(patch_path)?;
+    let base = String::from_utf8(base.to_vec())?;
+    let patch = String::from_utf8(std::fs::read(patch_path)?)?;
+    let merged = match format {
+        PatchFormat::Toml => {
+            let mut base = base.parse::<Document>()?;
+            let patch = patch.parse::<Document>()?;
+            merge_toml(base.as_table_mut(), patch.as_table());
+            base.to_string()
+        }
+        PatchFormat::Json => {
+            let mut base = serde_json::from_str::<serde_json::Value>(&base)?;
+            merge_json(&mut base, serde_json::from_str(&patch)?);
+            let mut merged = serde_json::to_string_pretty(&base)?;
+            merged.push('\n');
+            merged
+        }
+        PatchFormat::Properties => merge_properties(&base, &patch),
+    };
+    Ok(merged.into_bytes())
+}
+
+fn merge_toml(base: &mut dyn TableLike, patch: &dyn TableLike) {
+    for (key, item) in patch.iter() {
+        match (
+            base.get_mut(key).and_then(|b| b.as_table_like_mut()),
+            item.as_table_like(),
+        ) {
+            (Some(base_table), Some(patch_table)) => merge_toml(base_table, patch_table),
+            _ => {
+                base.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+fn merge_json(base: &mut serde_json::Value, patch: serde_json::Value) {
+    match (base, patch) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge_json(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
+/// The key of a `key=value` or `key: value` line in a properties file, if it isn't a comment.
+fn property_key(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+        return None;
+    }
+    let end = line.find(['=', ':']).unwrap_or(line.len());
+    Some(line[..end].trim_end())
+}
+
+/// Replace the lines of properties set by the patch, and add the ones the file doesn't have yet
+/// at its end.
+fn merge_properties(base: &str, patch: &str) -> String {
+    let mut patched = patch
+        .lines()
+        .filter_map(|line| property_key(line).map(|key| (key, line)))
+        .collect::<BTreeMap<_, _>>();
+    let mut merged = Vec::new();
+    for line in base.lines() {
+        match property_key(line).and_then(|key| patched.remove(key)) {
+            Some(patch_line) => merged.push(patch_line),
+            None => merged.push(line),
+        }
+    }
+    // Added in the order of the patch.
+    merged.extend(
+        patch
+            .lines()
+            .filter(|line| property_key(line).is_some_and(|key| patched.contains_key(key))),
+    );
+    let mut merged = merged.join("\n");
+    merged.push('\n');
+    merged
+}
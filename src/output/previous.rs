@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::Path;
+
+use thiserror::Error;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::output::build_metadata::{BuildMetadata, BUILD_METADATA_FILE};
+use crate::output::is_safe_path;
+use crate::output::modrinth_manifest::EMBEDDED_MANIFEST;
+
+/// Names of the manifests in the root of the archives, which describe the mods.
+const MANIFEST_FILES: [&str; 3] = ["manifest.json", "modrinth.index.json", EMBEDDED_MANIFEST];
+
+#[derive(Debug, Error)]
+pub enum PreviousArtifactError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error(
+        "There is no previous build at {0} to take the other parts from, generate it fully first"
+    )]
+    Missing(String),
+    #[error("{0} doesn't record which files are mods, generate it fully first")]
+    NoModFiles(String),
+    #[error("{0} has a file at {1}, which points outside of it, generate it fully instead")]
+    UnsafePath(String, String),
+}
+
+/// The metadata of an artifact from a previous run, which must record its mod files.
+fn previous_metadata(
+    path: &Path,
+    metadata: Option<BuildMetadata>,
+) -> Result<(BuildMetadata, HashSet<String>), PreviousArtifactError> {
+    let metadata =
+        metadata.ok_or_else(|| PreviousArtifactError::Missing(path.display().to_string()))?;
+    let mod_files = metadata
+        .mod_files
+        .clone()
+        .ok_or_else(|| PreviousArtifactError::NoModFiles(path.display().to_string()))?;
+    // The mod files are removed when they're no longer part of the pack.
+    if let Some(unsafe_path) = mod_files.iter().find(|f| !is_safe_path(f)) {
+        return Err(PreviousArtifactError::UnsafePath(
+            path.display().to_string(),
+            unsafe_path.clone(),
+        ));
+    }
+    Ok((metadata, mod_files.into_iter().collect()))
+}
+
+/// An archive from a previous run, to take the parts from that aren't generated again.
+pub struct PreviousArchive {
+    /// The path of the archive, for errors.
+    name: String,
+    zip: ZipArchive<File>,
+    pub metadata: BuildMetadata,
+    mod_files: HashSet<String>,
+}
+
+impl PreviousArchive {
+    pub fn open(path: &Path) -> Result<Self, PreviousArtifactError> {
+        let missing = || PreviousArtifactError::Missing(path.display().to_string());
+        let file = File::open(path).map_err(|_| missing())?;
+        let mut zip = ZipArchive::new(file).map_err(|_| missing())?;
+        let metadata = zip
+            .by_name(BUILD_METADATA_FILE)
+            .ok()
+            .and_then(|f| serde_json::from_reader(f).ok());
+        let (metadata, mod_files) = previous_metadata(path, metadata)?;
+        Ok(Self {
+            name: path.display().to_string(),
+            zip,
+            metadata,
+            mod_files,
+        })
+    }
+
+    /// Copy the entries that came from the mods, including the manifest.
+    pub fn copy_mods<W: Write + Seek>(
+        &mut self,
+        to: &mut ZipWriter<W>,
+    ) -> Result<(), PreviousArtifactError> {
+        self.copy_entries(to, true)
+    }
+
+    /// Copy the entries that came from the overrides.
+    pub fn copy_overrides<W: Write + Seek>(
+        &mut self,
+        to: &mut ZipWriter<W>,
+    ) -> Result<(), PreviousArtifactError> {
+        self.copy_entries(to, false)
+    }
+
+    fn copy_entries<W: Write + Seek>(
+        &mut self,
+        to: &mut ZipWriter<W>,
+        mods: bool,
+    ) -> Result<(), PreviousArtifactError> {
+        for i in 0..self.zip.len() {
+            let entry = self.zip.by_index_raw(i)?;
+            let name = entry.name();
+            if name == BUILD_METADATA_FILE || entry.is_dir() {
+                continue;
+            }
+            if !is_safe_path(name) {
+                return Err(PreviousArtifactError::UnsafePath(
+                    self.name.clone(),
+                    name.to_string(),
+                ));
+            }
+            let is_mod = MANIFEST_FILES.contains(&name) || self.mod_files.contains(name);
+            if is_mod == mods {
+                to.raw_copy_file(entry)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read the metadata of a server base from a previous run. Returns the metadata and the names of
+/// the downloaded mods in its mods folder.
+pub fn previous_server_base(
+    output_dir: &Path,
+) -> Result<(BuildMetadata, HashSet<String>), PreviousArtifactError> {
+    let metadata = std::fs::read(output_dir.join(BUILD_METADATA_FILE))
+        .ok()
+        .and_then(|m| serde_json::from_slice(&m).ok());
+    previous_metadata(output_dir, metadata)
+}
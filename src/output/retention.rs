@@ -0,0 +1,68 @@
+//! Pruning of older versions of an artifact from its output directory, so release folders that
+//! are published as a whole only hold the newest few.
+
+use std::cmp::Reverse;
+use std::num::NonZeroUsize;
+use std::path::Path;
+
+use itertools::Itertools;
+
+use crate::output::build_metadata::read_archive_metadata;
+use crate::output::generated::remove_generated;
+use crate::units;
+use crate::uwu_colors::{ErrStyle, FILE_STYLE};
+
+/// Remove all but the newest `keep` archives in the directory of `output_file` that are the same
+/// kind of artifact of the same pack (and target) as it, by when they were built. Only archives
+/// netherfire generated there and that weren't changed since are removed.
+pub(crate) fn prune_artifacts(output_file: &Path, keep: NonZeroUsize) {
+    let (Some(dir), Some(current)) = (output_file.parent(), read_archive_metadata(output_file))
+    else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let older = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p != output_file && p.extension() == output_file.extension())
+        .filter_map(|p| Some((read_archive_metadata(&p)?, p)))
+        .filter(|(m, _)| {
+            m.pack.is_some()
+                && m.pack == current.pack
+                && m.artifact == current.artifact
+                && m.target == current.target
+        })
+        .sorted_by_key(|(m, p)| Reverse((m.built_at, p.clone())))
+        // The artifact just generated is the newest.
+        .skip(keep.get() - 1)
+        .filter_map(|(_, p)| Some(p.file_name()?.to_str()?.to_string()))
+        .collect::<Vec<_>>();
+    if older.is_empty() {
+        return;
+    }
+    match tokio::task::block_in_place(|| remove_generated(dir, older)) {
+        Ok(removed) => {
+            if removed.removed_files > 0 {
+                log::info!(
+                    "Removed {} of the pack from '{}', keeping the newest {}.",
+                    units::count(removed.removed_files, "older artifact"),
+                    dir.display().errstyle(FILE_STYLE),
+                    keep
+                );
+            }
+            if !removed.kept.is_empty() {
+                log::warn!(
+                    "Kept older artifacts that changed since they were generated: {}",
+                    removed.kept.join(", ")
+                );
+            }
+        }
+        Err(e) => log::warn!(
+            "Failed to remove older artifacts from '{}': {}",
+            dir.display(),
+            e
+        ),
+    }
+}
@@ -0,0 +1,200 @@
+//! The artifacts written during a run, summarized in a table at its end and in the `--json`
+//! report, so there's one record of what a run produced.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use zip::ZipArchive;
+
+use crate::output::build_metadata::{read_archive_metadata, BuildMetadata, BUILD_METADATA_FILE};
+use crate::output::generated::generated_files;
+use crate::output::LIT_MODS;
+use crate::units;
+use crate::uwu_colors::{ErrStyle, FILE_STYLE};
+
+/// An artifact written during the run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactSummary {
+    /// The kind of artifact, e.g. `CurseForge ZIP`.
+    pub artifact: &'static str,
+    pub path: PathBuf,
+    /// The size of the archive, or of the files generated into the server base, in bytes.
+    pub size: u64,
+    /// The mods the artifact installs on the client, if it's for clients and its mods were
+    /// generated.
+    pub client_mods: Option<usize>,
+    /// The mods the artifact installs on the server, if it's for servers and its mods were
+    /// generated.
+    pub server_mods: Option<usize>,
+    pub override_files: usize,
+    /// How long generating the artifact took, or checking that it was up to date.
+    pub elapsed_ms: u64,
+}
+
+/// The mods an artifact installs on each side, `None` for sides it isn't for.
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct ModCounts {
+    pub client: Option<usize>,
+    pub server: Option<usize>,
+}
+
+static ARTIFACTS: Mutex<Vec<ArtifactSummary>> = Mutex::new(Vec::new());
+
+/// Record the archive at `path`, e.g. a Modrinth pack, that was generated since `started`.
+pub(crate) fn record_archive(
+    artifact: &'static str,
+    path: &Path,
+    mods: ModCounts,
+    started: Instant,
+) {
+    let contents = tokio::task::block_in_place(|| archive_contents(path));
+    record(artifact, path, mods, started, contents);
+}
+
+/// Record the server base in `dir` that was generated since `started`.
+pub(crate) fn record_server_base(
+    artifact: &'static str,
+    dir: &Path,
+    mods: ModCounts,
+    started: Instant,
+) {
+    let contents = tokio::task::block_in_place(|| server_base_contents(dir));
+    record(artifact, dir, mods, started, contents);
+}
+
+fn record(
+    artifact: &'static str,
+    path: &Path,
+    mods: ModCounts,
+    started: Instant,
+    contents: std::io::Result<(u64, usize)>,
+) {
+    let (size, override_files) = match contents {
+        Ok(contents) => contents,
+        Err(e) => {
+            log::warn!(
+                "Failed to read '{}' for the summary: {}",
+                path.display().errstyle(FILE_STYLE),
+                e
+            );
+            return;
+        }
+    };
+    ARTIFACTS
+        .lock()
+        .expect("artifact summary lock")
+        .push(ArtifactSummary {
+            artifact,
+            path: path.to_path_buf(),
+            size,
+            client_mods: mods.client,
+            server_mods: mods.server,
+            override_files,
+            elapsed_ms: started.elapsed().as_millis().try_into().unwrap_or(u64::MAX),
+        });
+}
+
+/// The size of an archive and the number of override files in it. Everything in a directory that
+/// isn't a downloaded mod is an override, the files at the root are manifests and metadata.
+fn archive_contents(path: &Path) -> std::io::Result<(u64, usize)> {
+    let size = std::fs::metadata(path)?.len();
+    let mod_files = read_archive_metadata(path)
+        .and_then(|m| m.mod_files)
+        .unwrap_or_default();
+    let zip = ZipArchive::new(std::fs::File::open(path)?).map_err(std::io::Error::other)?;
+    let override_files = zip
+        .file_names()
+        .filter(|name| name.contains('/') && !name.ends_with('/'))
+        .filter(|name| !mod_files.iter().any(|m| m == name))
+        .count();
+    Ok((size, override_files))
+}
+
+/// The size of the files generated into a server base and the number of override files among
+/// them, i.e. those that aren't downloaded mods or the build metadata.
+fn server_base_contents(dir: &Path) -> std::io::Result<(u64, usize)> {
+    let metadata =
+        serde_json::from_slice::<BuildMetadata>(&std::fs::read(dir.join(BUILD_METADATA_FILE))?)?;
+    let mod_files = metadata
+        .mod_files
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| format!("{}/{}", LIT_MODS, m))
+        .collect::<Vec<_>>();
+    let files = generated_files(dir)?;
+    let size = files.iter().map(|(_, size)| size).sum();
+    let override_files = files
+        .iter()
+        .filter(|(file, _)| file != BUILD_METADATA_FILE && !mod_files.contains(file))
+        .count();
+    Ok((size, override_files))
+}
+
+/// Take the artifacts recorded so far.
+pub fn take_artifacts() -> Vec<ArtifactSummary> {
+    std::mem::take(&mut *ARTIFACTS.lock().expect("artifact summary lock"))
+}
+
+/// Log a table of the artifacts written during the run, which took `elapsed`, if there are any.
+pub fn log_summary(artifacts: &[ArtifactSummary], elapsed: Duration) {
+    if artifacts.is_empty() {
+        return;
+    }
+    let count = |mods: Option<usize>| mods.map_or("-".to_string(), |n| units::number(n as u64));
+    let rows = std::iter::once(
+        [
+            "Artifact",
+            "Size",
+            "Client mods",
+            "Server mods",
+            "Overrides",
+            "Time",
+        ]
+        .map(String::from),
+    )
+    .chain(artifacts.iter().map(|a| {
+        [
+            a.artifact.to_string(),
+            units::size(a.size),
+            count(a.client_mods),
+            count(a.server_mods),
+            units::number(a.override_files as u64),
+            units::duration(Duration::from_millis(a.elapsed_ms)),
+        ]
+    }))
+    .collect::<Vec<_>>();
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    log::info!(
+        "Generated {} in {}:",
+        units::count(artifacts.len() as u64, "artifact"),
+        units::duration(elapsed)
+    );
+    let paths = std::iter::once("Path".to_string()).chain(
+        artifacts
+            .iter()
+            .map(|a| a.path.display().errstyle(FILE_STYLE).to_string()),
+    );
+    for (row, path) in rows.iter().zip(paths) {
+        let mut line = String::new();
+        for (column, (cell, width)) in row.iter().zip(widths).enumerate() {
+            // Left-align the names, right-align the numbers.
+            if column == 0 {
+                write!(line, "{:<width$}  ", cell, width = width).unwrap();
+            } else {
+                write!(line, "{:>width$}  ", cell, width = width).unwrap();
+            }
+        }
+        line.push_str(&path);
+        log::info!("{}", line);
+    }
+}
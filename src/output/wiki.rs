@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use crate::api::ModrinthError;
+use crate::checks::verify_mods::{KnownEnvRequirements, VerifiedModContainer};
+use crate::config::global::FERINTH;
+use crate::config::mods::KnownEnvRequirement;
+use crate::config::pack::PackConfig;
+use crate::mod_site::ModInfo;
+
+/// The section of mods the site doesn't list in any category.
+const UNCATEGORIZED: &str = "Other";
+
+/// How many teams to look up per request, to keep the URL short.
+const TEAMS_PER_REQUEST: usize = 100;
+
+/// Look up the authors of the Modrinth mods, which are only listed by their teams, with as few
+/// requests as possible.
+pub(crate) async fn load_modrinth_authors(
+    pack: &mut PackConfig<VerifiedModContainer>,
+) -> Result<(), ModrinthError> {
+    let teams = pack
+        .mods
+        .modrinth
+        .values()
+        .filter_map(|m| m.info.project_info.team.as_deref())
+        .unique()
+        .collect::<Vec<_>>();
+    let mut authors = HashMap::<String, Vec<String>>::new();
+    for chunk in teams.chunks(TEAMS_PER_REQUEST) {
+        for members in FERINTH.list_multiple_teams_members(chunk).await? {
+            for member in members {
+                authors
+                    .entry(member.team_id)
+                    .or_default()
+                    .push(member.user.username);
+            }
+        }
+    }
+    for m in pack.mods.modrinth.values_mut() {
+        let info = &mut m.info.project_info;
+        if let Some(team_authors) = info.team.as_ref().and_then(|t| authors.get(t)) {
+            info.authors = team_authors.clone();
+        }
+    }
+    Ok(())
+}
+
+/// A mod of the pack, as it's described in the wiki.
+struct WikiMod<'a> {
+    info: &'a ModInfo,
+    sides: KnownEnvRequirements,
+}
+
+/// Render a Markdown overview of the pack for publishing as a wiki page or project description:
+/// its mods by category, the mods only needed on one side, and the optional mods.
+pub(crate) fn wiki_markdown(pack: &PackConfig<VerifiedModContainer>) -> String {
+    let mut mods = pack
+        .mods
+        .curseforge
+        .values()
+        .map(|m| WikiMod {
+            info: &m.info.project_info,
+            sides: m.env_requirements,
+        })
+        .chain(pack.mods.modrinth.values().map(|m| WikiMod {
+            info: &m.info.project_info,
+            sides: m.env_requirements,
+        }))
+        .collect::<Vec<_>>();
+    mods.sort_by_cached_key(|m| m.info.name.to_lowercase());
+
+    let mut out = String::new();
+    writeln!(out, "# {} {}", escape(&pack.name), escape(&pack.version)).unwrap();
+    writeln!(out).unwrap();
+    if !pack.description.is_empty() {
+        writeln!(out, "{}", pack.description.trim()).unwrap();
+        writeln!(out).unwrap();
+    }
+    let loaders = std::iter::once(&pack.mod_loader)
+        .chain(&pack.secondary_loaders)
+        .map(|l| format!("{} {}", l.id, l.version))
+        .join(" with ");
+    writeln!(
+        out,
+        "Minecraft {}, {}. {} mods.",
+        pack.minecraft_version,
+        loaders,
+        mods.len()
+    )
+    .unwrap();
+
+    let mut categories = BTreeMap::<&str, Vec<&WikiMod>>::new();
+    for m in &mods {
+        let category = m.info.categories.first().map_or(UNCATEGORIZED, |c| c);
+        categories.entry(category).or_default().push(m);
+    }
+    // Keep the catch-all section last.
+    let other = categories.remove(UNCATEGORIZED);
+    if !mods.is_empty() {
+        writeln!(out, "\n## Mods").unwrap();
+    }
+    for (category, mods) in categories
+        .iter()
+        .map(|(c, m)| (*c, m))
+        .chain(other.as_ref().map(|m| (UNCATEGORIZED, m)))
+    {
+        writeln!(out, "\n### {}\n", escape(category)).unwrap();
+        for m in mods {
+            writeln!(out, "{}", mod_entry(m.info, true)).unwrap();
+        }
+    }
+
+    for (title, description, mods) in [
+        (
+            "Client-only mods",
+            "These mods aren't installed on servers.",
+            mods.iter()
+                .filter(|m| m.sides.server == KnownEnvRequirement::Unsupported)
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "Server-only mods",
+            "These mods aren't installed on clients.",
+            mods.iter()
+                .filter(|m| m.sides.client == KnownEnvRequirement::Unsupported)
+                .collect(),
+        ),
+        (
+            "Optional features",
+            "These mods can be left out, e.g. when installing the pack with a launcher that asks.",
+            mods.iter()
+                .filter(|m| {
+                    m.sides.client == KnownEnvRequirement::Optional
+                        || m.sides.server == KnownEnvRequirement::Optional
+                })
+                .collect(),
+        ),
+    ] {
+        if mods.is_empty() {
+            continue;
+        }
+        writeln!(out, "\n## {}\n\n{}\n", title, description).unwrap();
+        for m in mods {
+            writeln!(out, "{}", mod_entry(m.info, false)).unwrap();
+        }
+    }
+    out
+}
+
+/// A list item for a mod, linking to its page, with its summary and optionally its authors.
+fn mod_entry(info: &ModInfo, with_authors: bool) -> String {
+    let mut entry = match &info.url {
+        Some(url) => format!("- [{}]({})", escape(&info.name), url),
+        None => format!("- {}", escape(&info.name)),
+    };
+    if with_authors && !info.authors.is_empty() {
+        write!(entry, " by {}", escape(&info.authors.join(", "))).unwrap();
+    }
+    if let Some(summary) = info.summary.as_deref().map(str::trim) {
+        if !summary.is_empty() {
+            write!(entry, ": {}", escape(summary)).unwrap();
+        }
+    }
+    entry
+}
+
+/// Escape the characters that would be read as Markdown syntax in site-provided text.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
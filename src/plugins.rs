@@ -0,0 +1,207 @@
+//! External subcommands: `netherfire foo` runs a `netherfire-foo` executable from the `PATH`, like
+//! cargo and git do. Plugins get the path of netherfire in `NETHERFIRE`, and can run
+//! `netherfire resolve <source>` to get the resolved pack as JSON.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus};
+
+use itertools::Itertools;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::checks::verify_mods::{KnownEnvRequirements, VerifiedMod, VerifiedModContainer};
+use crate::config::mods::KnownEnvRequirement;
+use crate::config::pack::PackConfig;
+use crate::mod_site::{CurseForge, ModHash, ModSite, Modrinth};
+use crate::output::mod_download::known_sha256;
+
+/// The prefix of the names of plugin executables.
+const PLUGIN_PREFIX: &str = "netherfire-";
+
+/// The version of the format of [ResolvedPack]. Fields may be added without changing it.
+const RESOLVED_PACK_FORMAT: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("No such command or plugin '{0}', there's no {PLUGIN_PREFIX}{0} on the PATH")]
+    NotFound(String),
+    #[error("Error running plugin {0}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("Plugin {0} failed with {1}")]
+    Failed(String, ExitStatus),
+}
+
+/// Run the plugin named by the first of `args` with the rest of them.
+pub fn run_plugin(args: Vec<OsString>) -> Result<(), PluginError> {
+    let mut args = args.into_iter();
+    let name = args
+        .next()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let Some(executable) = find_plugins().find(|(n, _)| *n == name).map(|(_, p)| p) else {
+        return Err(PluginError::NotFound(name));
+    };
+    log::debug!("Running plugin {}", executable.display());
+    let mut command = Command::new(&executable);
+    command
+        .args(args)
+        .env("NETHERFIRE_VERSION", env!("CARGO_PKG_VERSION"));
+    if let Ok(exe) = std::env::current_exe() {
+        command.env("NETHERFIRE", exe);
+    }
+    let status = tokio::task::block_in_place(|| command.status())
+        .map_err(|e| PluginError::Io(name.clone(), e))?;
+    if !status.success() {
+        return Err(PluginError::Failed(name, status));
+    }
+    Ok(())
+}
+
+/// Log the plugins on the `PATH`.
+pub fn list_plugins() {
+    let plugins = find_plugins()
+        .unique_by(|(name, _)| name.clone())
+        .sorted()
+        .collect::<Vec<_>>();
+    if plugins.is_empty() {
+        log::info!("There are no plugins on the PATH.");
+    }
+    for (name, path) in plugins {
+        log::info!("{} ({})", name, path.display());
+    }
+}
+
+/// The names and paths of the plugin executables on the `PATH`, in the order they're found.
+fn find_plugins() -> impl Iterator<Item = (String, PathBuf)> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    std::env::split_paths(&path)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            let name = file_name
+                .strip_prefix(PLUGIN_PREFIX)?
+                .strip_suffix(std::env::consts::EXE_SUFFIX)?;
+            let path = entry.path();
+            (!name.is_empty() && path.is_file()).then(|| (name.to_string(), path))
+        })
+}
+
+/// A verified pack, as printed by `netherfire resolve` for plugins.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedPack {
+    pub format_version: u32,
+    pub name: String,
+    pub version: String,
+    pub author: String,
+    pub description: String,
+    pub minecraft_version: String,
+    pub mod_loader: ResolvedModLoader,
+    /// The mod loaders the pack runs on top of its own.
+    pub secondary_loaders: Vec<ResolvedModLoader>,
+    /// The Java version the pack needs at least, e.g. for start scripts. Missing if unknown.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub java_version: Option<u32>,
+    /// By site and config key.
+    pub mods: Vec<ResolvedMod>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedModLoader {
+    /// `forge`, `neoforge`, `fabric` or `quilt`.
+    pub id: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedMod {
+    /// The config key of the site, e.g. `modrinth`.
+    pub site: &'static str,
+    pub config_key: String,
+    pub project_id: String,
+    /// The ID of the file that is installed.
+    pub version_id: String,
+    pub name: String,
+    pub slug: String,
+    pub filename: String,
+    pub url: String,
+    pub file_size: u64,
+    /// The hash the site provides, as `<algorithm>-<hex>`, e.g. `sha1-...`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_hash: Option<String>,
+    /// The SHA-256 of the file, if it was downloaded before.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// `required`, `optional` or `unsupported`.
+    pub client: KnownEnvRequirement,
+    pub server: KnownEnvRequirement,
+}
+
+impl ResolvedPack {
+    pub fn new(pack: &PackConfig<VerifiedModContainer>) -> Self {
+        let curseforge = pack
+            .mods
+            .curseforge
+            .iter()
+            .map(|(k, m)| ResolvedMod::new::<CurseForge>(k, m));
+        let modrinth = pack
+            .mods
+            .modrinth
+            .iter()
+            .map(|(k, m)| ResolvedMod::new::<Modrinth>(k, m));
+        Self {
+            format_version: RESOLVED_PACK_FORMAT,
+            name: pack.name.clone(),
+            version: pack.version.clone(),
+            author: pack.author.clone(),
+            description: pack.description.clone(),
+            minecraft_version: pack.minecraft_version.clone(),
+            mod_loader: ResolvedModLoader {
+                id: pack.mod_loader.id.to_string().to_lowercase(),
+                version: pack.mod_loader.version.clone(),
+            },
+            secondary_loaders: pack
+                .secondary_loaders
+                .iter()
+                .map(|l| ResolvedModLoader {
+                    id: l.id.to_string(),
+                    version: l.version.clone(),
+                })
+                .collect(),
+            java_version: pack.min_java_version(),
+            mods: curseforge
+                .chain(modrinth)
+                .sorted_by(|a, b| (a.site, &a.config_key).cmp(&(b.site, &b.config_key)))
+                .collect(),
+        }
+    }
+}
+
+impl ResolvedMod {
+    fn new<S: ModSite>(config_key: &str, m: &VerifiedMod<S>) -> Self {
+        let KnownEnvRequirements { client, server } = m.env_requirements;
+        Self {
+            site: S::CONFIG_KEY,
+            config_key: config_key.to_string(),
+            project_id: m.source.project_id.to_string(),
+            version_id: m.file_id().to_string(),
+            name: m.info.project_info.name.clone(),
+            slug: m.info.project_info.slug.clone(),
+            filename: m.info.filename.clone(),
+            url: m.info.url.clone(),
+            file_size: m.info.file_length,
+            site_hash: m.info.hash.site_hash_key(),
+            sha256: known_sha256(&m.info).map(|h| format!("{:x}", h)),
+            client,
+            server,
+        }
+    }
+}
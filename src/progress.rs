@@ -0,0 +1,185 @@
+//! Progress events of a run, for front-ends and bots that render their own progress instead of
+//! reading the logs. The events about mods and artifacts are logged as well.
+
+use std::path::PathBuf;
+
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+
+use crate::mod_site::{CurseForge, ModSite, Modrinth};
+use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, FILE_STYLE, SITE_NAME_STYLE, SITE_VAL_STYLE};
+
+/// A part of generating a pack.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Verification,
+    ModIdCheck,
+    CurseForgeZip,
+    ModrinthPack,
+    ServerBase,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    PhaseStarted {
+        phase: Phase,
+    },
+    PhaseCompleted {
+        phase: Phase,
+    },
+    /// A mod started verifying, by site config key (e.g. `modrinth`) and mod config key.
+    VerificationStarted {
+        site: &'static str,
+        key: String,
+    },
+    ModVerified {
+        site: &'static str,
+        key: String,
+        name: String,
+        slug: String,
+    },
+    ModFailed {
+        site: &'static str,
+        key: String,
+    },
+    /// Part of a file was downloaded. `total` is the size of the file, if it's known.
+    DownloadProgress {
+        url: String,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    DownloadCompleted {
+        url: String,
+        bytes: u64,
+    },
+    /// A mod file was put into an artifact. `already_present` if the right file was there
+    /// already, from a previous build.
+    FileCopied {
+        artifact: &'static str,
+        site: &'static str,
+        key: String,
+        file: String,
+        already_present: bool,
+    },
+    /// An artifact was written. `overrides_only` if only its overrides were generated again.
+    ArtifactWritten {
+        artifact: &'static str,
+        path: PathBuf,
+        overrides_only: bool,
+    },
+}
+
+/// Receives the progress events of a run. Events may be sent from multiple threads at once.
+pub trait ProgressListener: Send + Sync {
+    fn on_event(&self, event: &ProgressEvent);
+}
+
+impl<F: Fn(&ProgressEvent) + Send + Sync> ProgressListener for F {
+    fn on_event(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+static LISTENER: OnceCell<Box<dyn ProgressListener>> = OnceCell::new();
+
+/// Set the listener for the progress events of this process. Returns `false` if one is set
+/// already, which is then kept.
+pub fn set_progress_listener(listener: impl ProgressListener + 'static) -> bool {
+    LISTENER.set(Box::new(listener)).is_ok()
+}
+
+/// Log the event, if it's one users follow in the logs, and send it to the listener, if there is
+/// one.
+pub fn emit(event: ProgressEvent) {
+    log_event(&event);
+    if let Some(listener) = LISTENER.get() {
+        listener.on_event(&event);
+    }
+}
+
+/// Emit the start and end of a phase around `run`. The end is only emitted if it succeeds.
+pub async fn in_phase<T, E>(
+    phase: Phase,
+    run: impl std::future::Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    emit(ProgressEvent::PhaseStarted { phase });
+    let result = run.await?;
+    emit(ProgressEvent::PhaseCompleted { phase });
+    Ok(result)
+}
+
+fn log_event(event: &ProgressEvent) {
+    match event {
+        ProgressEvent::ModVerified {
+            site,
+            key,
+            name,
+            slug,
+        } => log::info!(
+            "[{}] Mod {} ({}, in config: {}) verified.",
+            site_name(site).errstyle(SITE_NAME_STYLE),
+            name.errstyle(SITE_VAL_STYLE),
+            slug.errstyle(SITE_VAL_STYLE),
+            key.errstyle(CONFIG_VAL_STYLE)
+        ),
+        ProgressEvent::ModFailed { site, key } => log::info!(
+            "[{}] Mod (in config: {}) FAILED verification.",
+            site_name(site).errstyle(SITE_NAME_STYLE),
+            key.errstyle(CONFIG_VAL_STYLE)
+        ),
+        ProgressEvent::FileCopied {
+            artifact,
+            site,
+            key,
+            file,
+            already_present,
+        } => log::info!(
+            "[{}] {} {} for {} in the {}",
+            site_name(site).errstyle(SITE_NAME_STYLE),
+            if *already_present {
+                "Found cached"
+            } else {
+                "Downloaded"
+            },
+            file.errstyle(FILE_STYLE),
+            key.errstyle(CONFIG_VAL_STYLE),
+            artifact
+        ),
+        ProgressEvent::ArtifactWritten {
+            artifact,
+            path,
+            overrides_only,
+        } => {
+            if *overrides_only {
+                log::info!(
+                    "Regenerated overrides of {} at '{}'.",
+                    artifact,
+                    path.display().errstyle(FILE_STYLE)
+                )
+            } else {
+                log::info!(
+                    "Created {} at '{}'.",
+                    artifact,
+                    path.display().errstyle(FILE_STYLE)
+                )
+            }
+        }
+        // Logged by the code emitting them, or too frequent to log.
+        ProgressEvent::PhaseStarted { .. }
+        | ProgressEvent::PhaseCompleted { .. }
+        | ProgressEvent::VerificationStarted { .. }
+        | ProgressEvent::DownloadProgress { .. }
+        | ProgressEvent::DownloadCompleted { .. } => {}
+    }
+}
+
+/// The display name of the site with the config key `site`.
+fn site_name(site: &str) -> &str {
+    match site {
+        CurseForge::CONFIG_KEY => CurseForge::NAME,
+        Modrinth::CONFIG_KEY => Modrinth::NAME,
+        _ => site,
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::api::ModrinthError;
+use crate::config::global::{HTTP_CLIENT, RETRY_POLICY};
+use crate::output::mod_download::ModDownloadError;
+use crate::units;
+
+/// How often and how long to wait before retrying failed network requests.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// How many times a request is retried before giving up.
+    pub retries: u32,
+    /// The delay before the first retry in seconds, doubled for every following retry.
+    pub backoff_base_secs: f64,
+    /// The longest delay between retries in seconds. Rate limits may still require waiting longer.
+    pub max_delay_secs: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 5,
+            backoff_base_secs: 1.0,
+            max_delay_secs: 60.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, retry: u32) -> Duration {
+        let delay = self.backoff_base_secs * 2f64.powi(retry.min(30) as i32);
+        Duration::from_secs_f64(delay.min(self.max_delay_secs).max(0.0))
+    }
+
+    /// Run `request` until it succeeds, fails with an error that isn't worth retrying, or the
+    /// retries are exhausted.
+    pub async fn run<T, E, Fut>(&self, request: impl Fn() -> Fut) -> Result<T, E>
+    where
+        E: Retryable,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut retry = 0;
+        loop {
+            match request().await {
+                Ok(v) => return Ok(v),
+                Err(e) => {
+                    let Some(min_delay) = e.min_retry_delay() else {
+                        return Err(e);
+                    };
+                    if retry >= self.retries {
+                        return Err(e);
+                    }
+                    let delay = self.backoff(retry).max(min_delay);
+                    log::warn!(
+                        "Retrying request in {} ({}/{}): {}",
+                        units::duration(delay),
+                        retry + 1,
+                        self.retries,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+            }
+        }
+    }
+}
+
+/// An error that may go away when retrying the request.
+pub trait Retryable: Display {
+    /// The minimum time to wait before retrying, or [None] if retrying won't help.
+    fn min_retry_delay(&self) -> Option<Duration>;
+}
+
+impl Retryable for reqwest::Error {
+    fn min_retry_delay(&self) -> Option<Duration> {
+        let transient_status = self
+            .status()
+            .is_some_and(|s| s.is_server_error() || s == reqwest::StatusCode::TOO_MANY_REQUESTS);
+        (self.is_timeout() || self.is_connect() || self.is_body() || transient_status)
+            .then_some(Duration::ZERO)
+    }
+}
+
+impl Retryable for ferinth::Error {
+    fn min_retry_delay(&self) -> Option<Duration> {
+        match self {
+            ferinth::Error::RateLimitExceeded(reset_sec) => {
+                Some(Duration::from_secs(*reset_sec as u64 + 1))
+            }
+            ferinth::Error::ReqwestError(e) => e.min_retry_delay(),
+            _ => None,
+        }
+    }
+}
+
+impl Retryable for ModrinthError {
+    fn min_retry_delay(&self) -> Option<Duration> {
+        match self {
+            ModrinthError::Api(e) => e.min_retry_delay(),
+            ModrinthError::UrlParse(_) | ModrinthError::NoV3Api(_) => None,
+        }
+    }
+}
+
+impl Retryable for furse::Error {
+    fn min_retry_delay(&self) -> Option<Duration> {
+        match self {
+            furse::Error::ReqwestError(e) => e.min_retry_delay(),
+            furse::Error::URLParseError(_) => None,
+        }
+    }
+}
+
+impl Retryable for ModDownloadError {
+    fn min_retry_delay(&self) -> Option<Duration> {
+        match self {
+            ModDownloadError::Reqwest(e) => e.min_retry_delay(),
+            _ => None,
+        }
+    }
+}
+
+/// Download the content at `url`, retrying according to the global retry policy.
+pub async fn download(url: &str) -> Result<Vec<u8>, reqwest::Error> {
+    RETRY_POLICY
+        .run(|| async {
+            HTTP_CLIENT
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await
+                .map(|b| b.to_vec())
+        })
+        .await
+}
@@ -0,0 +1,54 @@
+//! Formatting of sizes, counts and durations for logs and reports, so they read the same
+//! everywhere.
+
+use std::time::Duration;
+
+const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+/// A size in bytes, in the largest binary unit it's at least one of, e.g. `1.5 GiB`.
+pub fn size(bytes: u64) -> String {
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// A number with its digits grouped in thousands, e.g. `12,345`.
+pub fn number(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// A count of things, with the noun in plural unless there's exactly one, e.g. `3 files`.
+pub fn count(n: u64, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", number(n), noun)
+    }
+}
+
+/// A duration, in seconds, or minutes and seconds from a minute on, e.g. `2 min 5 sec`.
+pub fn duration(duration: Duration) -> String {
+    let secs = duration.as_secs_f64();
+    if secs < 60.0 {
+        format!("{:.1} sec", secs)
+    } else {
+        let secs = duration.as_secs();
+        format!("{} min {} sec", number(secs / 60), secs % 60)
+    }
+}
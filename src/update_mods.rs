@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use futures::StreamExt;
+use itertools::Itertools;
+use toml_edit::{Document, Item, TableLike};
+
+use crate::add_mods::ConfigIdValue;
+use crate::config::mods::{ConfigMod, ConfigModContainer};
+use crate::config::pack::{PackConfig, VersionType};
+use crate::mod_site::{CurseForge, ModLoadingError, ModSite, Modrinth};
+use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, SITE_NAME_STYLE, SITE_VAL_STYLE};
+
+#[derive(Debug)]
+pub struct ModsUpdateError {
+    /// The number of mods that were updated despite the failures.
+    pub updated: usize,
+    pub failures: Vec<(String, ModLoadingError)>,
+}
+
+impl Error for ModsUpdateError {}
+
+impl Display for ModsUpdateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (k, error) in &self.failures {
+            writeln!(f, "Mod {}: {}", k, error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Look up the latest version for the pack of every mod in the config document, and set its
+/// `version_id` to it, unless `dry_run` is set. Only the mods of the document are updated, not
+/// those of a pack it extends. Mods with a `server_version_id` are skipped, as the server file
+/// must be picked to match. Mods with `ignore_mod_loader` in the config, or every mod if
+/// `ignore_mod_loader` is set, are updated regardless of the pack's mod loader.
+///
+/// Returns the number of mods that are, or would be, updated, or the failures if the latest
+/// version of any mod could not be looked up. The other mods are updated either way.
+pub(crate) async fn update_mods(
+    pack_config: &PackConfig<ConfigModContainer>,
+    document: &mut Document,
+    version_types: Option<&[VersionType]>,
+    ignore_mod_loader: bool,
+    dry_run: bool,
+) -> Result<usize, ModsUpdateError> {
+    let version_types = version_types.unwrap_or(&pack_config.version_types);
+    let mut updated = 0;
+    let mut failures = Vec::new();
+    if let Some(site_table) = site_table_mut(document, CurseForge::CONFIG_KEY) {
+        let (site_updated, site_failures) = update_site(
+            pack_config,
+            &pack_config.mods.curseforge,
+            site_table,
+            CurseForge,
+            version_types,
+            ignore_mod_loader,
+            dry_run,
+        )
+        .await;
+        updated += site_updated;
+        failures.extend(site_failures);
+    }
+    if let Some(site_table) = site_table_mut(document, Modrinth::CONFIG_KEY) {
+        let (site_updated, site_failures) = update_site(
+            pack_config,
+            &pack_config.mods.modrinth,
+            site_table,
+            Modrinth,
+            version_types,
+            ignore_mod_loader,
+            dry_run,
+        )
+        .await;
+        updated += site_updated;
+        failures.extend(site_failures);
+    }
+
+    if failures.is_empty() {
+        Ok(updated)
+    } else {
+        Err(ModsUpdateError { updated, failures })
+    }
+}
+
+/// Get the `mods.<site_key>` table of the document, if it has one.
+fn site_table_mut<'d>(document: &'d mut Document, site_key: &str) -> Option<&'d mut dyn TableLike> {
+    document
+        .get_mut("mods")?
+        .as_table_like_mut()?
+        .get_mut(site_key)?
+        .as_table_like_mut()
+}
+
+async fn update_site<K, S>(
+    pack_config: &PackConfig<ConfigModContainer>,
+    mods: &HashMap<String, ConfigMod<K>>,
+    site_table: &mut dyn TableLike,
+    site: S,
+    version_types: &[VersionType],
+    ignore_mod_loader: bool,
+    dry_run: bool,
+) -> (usize, Vec<(String, ModLoadingError)>)
+where
+    K: ConfigIdValue,
+    S: ModSite<Id = K>,
+{
+    let to_check = site_table
+        .iter()
+        .filter_map(|(key, _)| Some((key.to_string(), mods.get(key)?)))
+        .filter(|(key, m)| {
+            if m.server_version_id.is_some() {
+                log::warn!(
+                    "[{}] {} has a server_version_id, skipping. Update it by hand.",
+                    S::NAME.errstyle(SITE_NAME_STYLE),
+                    key.errstyle(CONFIG_VAL_STYLE),
+                );
+            }
+            m.server_version_id.is_none()
+        })
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        .collect::<Vec<_>>();
+    let results = futures::stream::iter(to_check)
+        .map(|(key, m)| async move {
+            let minecraft_version = m
+                .game_version
+                .as_ref()
+                .unwrap_or(&pack_config.minecraft_version);
+            let result = site
+                .get_latest_version_for_pack(
+                    m.source.project_id.clone(),
+                    minecraft_version,
+                    &pack_config.mod_loader.id,
+                    ignore_mod_loader || m.ignore_mod_loader,
+                    pack_config.prefer_featured,
+                    version_types,
+                )
+                .await;
+            (key, m, minecraft_version, result)
+        })
+        .buffered(5)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut updated = 0;
+    let mut failures = Vec::new();
+    for (key, m, minecraft_version, result) in results {
+        let latest = match result {
+            Ok(Some(latest)) => latest,
+            Ok(None) => {
+                log::warn!(
+                    "[{}] No version of {} found for Minecraft {} on {}, keeping it.",
+                    S::NAME.errstyle(SITE_NAME_STYLE),
+                    key.errstyle(CONFIG_VAL_STYLE),
+                    minecraft_version,
+                    pack_config.mod_loader.id,
+                );
+                continue;
+            }
+            Err(e) => {
+                failures.push((key, e));
+                continue;
+            }
+        };
+        if latest.version_id == m.source.version_id {
+            continue;
+        }
+        log::info!(
+            "[{}] {}{}: version {} -> {}",
+            S::NAME.errstyle(SITE_NAME_STYLE),
+            if dry_run { "Would update " } else { "Updated " },
+            key.errstyle(CONFIG_VAL_STYLE),
+            m.source.version_id.errstyle(SITE_VAL_STYLE),
+            latest.version_id.errstyle(SITE_VAL_STYLE),
+        );
+        updated += 1;
+        if dry_run {
+            continue;
+        }
+        let entry = site_table
+            .get_mut(&key)
+            .and_then(|e| e.as_table_like_mut())
+            .expect("mods in the config document are tables");
+        match entry.get_mut("version_id").and_then(|v| v.as_value_mut()) {
+            Some(version_id) => {
+                // Keep the formatting around the value.
+                let decor = version_id.decor().clone();
+                *version_id = latest.version_id.to_toml();
+                *version_id.decor_mut() = decor;
+            }
+            None => {
+                entry.insert("version_id", Item::Value(latest.version_id.to_toml()));
+            }
+        }
+    }
+    (updated, failures)
+}
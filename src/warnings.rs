@@ -0,0 +1,140 @@
+//! Non-fatal issues found during a run. They're collected instead of only being logged, so they
+//! can be shown together at the end of the run, and included in `--json` output.
+
+use std::sync::Mutex;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::uwu_colors::{ErrStyle, CONFIG_VAL_STYLE, SITE_NAME_STYLE};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// The config and the site disagree on the sides a mod is needed on.
+    EnvRequirement,
+    /// A mod isn't listed for the pack's Minecraft version, but the check only warns.
+    MinecraftVersion,
+    /// An optional dependency isn't in the config, or couldn't be loaded.
+    OptionalDependency,
+    /// A mod embeds another mod of the config, so it's on the classpath twice.
+    EmbeddedDependency,
+    /// A license couldn't be checked against the policy.
+    License,
+    /// The file of a mod version was guessed.
+    FileSelection,
+    /// A file was left out of an artifact.
+    SkippedFile,
+    /// An override is renamed, redundant, or may not install everywhere.
+    Overrides,
+}
+
+impl WarningKind {
+    fn title(self) -> &'static str {
+        match self {
+            WarningKind::EnvRequirement => "Side requirements",
+            WarningKind::MinecraftVersion => "Minecraft versions",
+            WarningKind::OptionalDependency => "Optional dependencies",
+            WarningKind::EmbeddedDependency => "Embedded dependencies",
+            WarningKind::License => "Licenses",
+            WarningKind::FileSelection => "File selection",
+            WarningKind::SkippedFile => "Skipped files",
+            WarningKind::Overrides => "Overrides",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// The site config key (e.g. `modrinth`) of the mod the warning is about, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+    /// The config key of the mod the warning is about, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_key: Option<String>,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            site: None,
+            config_key: None,
+            message: message.into(),
+        }
+    }
+
+    /// A warning about the mod `mods.<site>.<config_key>`.
+    pub fn for_mod(
+        kind: WarningKind,
+        site: &str,
+        config_key: &str,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            site: Some(site.to_string()),
+            config_key: Some(config_key.to_string()),
+            message: message.into(),
+        }
+    }
+}
+
+static WARNINGS: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+
+/// Record a warning. It's logged at debug level right away, and with the others by
+/// [log_summary].
+pub fn warn(warning: Warning) {
+    log::debug!("Warning: {}", warning.message);
+    WARNINGS.lock().expect("warnings lock").push(warning);
+}
+
+/// The number of warnings recorded so far, to pass to [warnings_since].
+pub fn mark() -> usize {
+    WARNINGS.lock().expect("warnings lock").len()
+}
+
+/// The warnings recorded after [mark] returned `mark`.
+pub fn warnings_since(mark: usize) -> Vec<Warning> {
+    WARNINGS.lock().expect("warnings lock")[mark..].to_vec()
+}
+
+/// All warnings recorded in this run.
+pub fn all_warnings() -> Vec<Warning> {
+    warnings_since(0)
+}
+
+/// Record warnings from an earlier run again, e.g. from a cached verification.
+pub fn restore(warnings: Vec<Warning>) {
+    WARNINGS.lock().expect("warnings lock").extend(warnings);
+}
+
+/// Log all warnings of the run, grouped by kind.
+pub fn log_summary() {
+    let warnings = all_warnings();
+    if warnings.is_empty() {
+        return;
+    }
+    log::warn!("{} warning(s):", warnings.len());
+    for (kind, group) in &warnings
+        .iter()
+        .sorted_by_key(|w| (w.kind, w.site.clone(), w.config_key.clone()))
+        .group_by(|w| w.kind)
+    {
+        log::warn!("{}:", kind.title());
+        for warning in group {
+            match (&warning.site, &warning.config_key) {
+                (Some(site), Some(key)) => log::warn!(
+                    "- [{}] {}: {}",
+                    site.errstyle(SITE_NAME_STYLE),
+                    key.errstyle(CONFIG_VAL_STYLE),
+                    warning.message
+                ),
+                _ => log::warn!("- {}", warning.message),
+            }
+        }
+    }
+}